@@ -0,0 +1,69 @@
+// The audit sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    audit::{
+        audit_lookup, audit_zip_table, conflicts, read_records, state_conflicts, summarize,
+        to_text as audit_text, AuditFile,
+    },
+    cli::flag_value,
+    command::{stamp, write_json, write_text, ZIP_TABLE},
+};
+use log::info;
+use serde_json::json;
+
+const AUDIT_TXT: &str = "audit.txt";
+const AUDIT_JSON: &str = "audit.json";
+
+// audit: check the lookup and zip code files before a
+// run and print a report with severities: invalid
+// records, duplicate and missing states, coordinates
+// outside the state, zip code prefixes of another
+// state and records that conflict between the files.
+//   --json file.json           lookup (default: look_up.json)
+//   --csv file.csv             capitals table (default: zip_codes.csv)
+//   --table file.csv           zip code table (default: codes.csv)
+pub fn audit(args: &[String]) {
+    let json_path = flag_value(args, "--json").unwrap_or("look_up.json");
+    let csv_path = flag_value(args, "--csv").unwrap_or("zip_codes.csv");
+    let table_path = flag_value(args, "--table").unwrap_or(ZIP_TABLE);
+
+    let (json_records, mut findings) = read_records(json_path);
+    let (csv_records, found) = read_records(csv_path);
+    findings.extend(found);
+    let (table_records, found) = read_records(table_path);
+    findings.extend(found);
+    let look_up = AuditFile {
+        name: json_path,
+        records: &json_records,
+    };
+    let capitals = AuditFile {
+        name: csv_path,
+        records: &csv_records,
+    };
+    let table = AuditFile {
+        name: table_path,
+        records: &table_records,
+    };
+
+    findings.extend(audit_lookup(&look_up));
+    findings.extend(audit_lookup(&capitals));
+    findings.extend(audit_zip_table(&table));
+    findings.extend(conflicts(&look_up, &table));
+    findings.extend(conflicts(&capitals, &table));
+    findings.extend(state_conflicts(&look_up, &capitals));
+    let report = summarize(&[json_path, csv_path, table_path], findings);
+
+    let text = audit_text(&report);
+    print!("{}", text);
+    write_text(AUDIT_TXT, &text);
+
+    let obj = json!({
+        "audit": report,
+    });
+    write_json(AUDIT_JSON, &stamp(obj));
+    info!(
+        "Audit of {:?} error(s), {:?} warning(s) written to {:?} and {:?}",
+        report.errors, report.warnings, AUDIT_TXT, AUDIT_JSON
+    );
+}
@@ -0,0 +1,732 @@
+pub mod command;
+
+use crate::{
+    lookup::{read_look_up, ObjLookUp},
+    place::{Place, StateCode},
+};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::{collections::HashMap, convert::TryFrom, fmt::Write};
+
+// Degrees a coordinate may be outside its state's box.
+const BOUNDS_MARGIN: f64 = 0.05;
+
+// Coordinates a record may differ by between files.
+const SAME_COORDINATE: f64 = 0.0001;
+
+// Bounding box of each state and territory: min and
+// max latitude, min and max longitude. A min longitude
+// above the max wraps around 180 (the Aleutians).
+const STATE_BOUNDS: [(&str, f64, f64, f64, f64); 56] = [
+    ("AL", 30.14, 35.01, -88.47, -84.89),
+    ("AK", 51.20, 71.50, 172.00, -129.90),
+    ("AZ", 31.33, 37.00, -114.82, -109.04),
+    ("AR", 33.00, 36.50, -94.62, -89.64),
+    ("CA", 32.53, 42.01, -124.41, -114.13),
+    ("CO", 36.99, 41.00, -109.06, -102.04),
+    ("CT", 40.98, 42.05, -73.73, -71.78),
+    ("DE", 38.45, 39.84, -75.79, -75.05),
+    ("DC", 38.79, 39.00, -77.12, -76.91),
+    ("FL", 24.52, 31.00, -87.63, -80.03),
+    ("GA", 30.36, 35.00, -85.61, -80.84),
+    ("HI", 18.91, 28.40, -178.33, -154.81),
+    ("ID", 41.99, 49.00, -117.24, -111.04),
+    ("IL", 36.97, 42.51, -91.51, -87.02),
+    ("IN", 37.77, 41.76, -88.10, -84.78),
+    ("IA", 40.38, 43.50, -96.64, -90.14),
+    ("KS", 36.99, 40.00, -102.05, -94.59),
+    ("KY", 36.50, 39.15, -89.57, -81.96),
+    ("LA", 28.93, 33.02, -94.04, -88.82),
+    ("ME", 42.98, 47.46, -71.08, -66.95),
+    ("MD", 37.91, 39.72, -79.49, -75.05),
+    ("MA", 41.24, 42.89, -73.51, -69.93),
+    ("MI", 41.70, 48.31, -90.42, -82.41),
+    ("MN", 43.50, 49.38, -97.24, -89.49),
+    ("MS", 30.17, 35.00, -91.66, -88.10),
+    ("MO", 35.99, 40.61, -95.77, -89.10),
+    ("MT", 44.36, 49.00, -116.05, -104.04),
+    ("NE", 40.00, 43.00, -104.05, -95.31),
+    ("NV", 35.00, 42.00, -120.01, -114.04),
+    ("NH", 42.70, 45.31, -72.56, -70.61),
+    ("NJ", 38.93, 41.36, -75.56, -73.89),
+    ("NM", 31.33, 37.00, -109.05, -103.00),
+    ("NY", 40.50, 45.02, -79.76, -71.86),
+    ("NC", 33.84, 36.59, -84.32, -75.46),
+    ("ND", 45.94, 49.00, -104.05, -96.55),
+    ("OH", 38.40, 41.98, -84.82, -80.52),
+    ("OK", 33.62, 37.00, -103.00, -94.43),
+    ("OR", 41.99, 46.29, -124.57, -116.46),
+    ("PA", 39.72, 42.27, -80.52, -74.69),
+    ("RI", 41.15, 42.02, -71.91, -71.12),
+    ("SC", 32.03, 35.22, -83.35, -78.54),
+    ("SD", 42.48, 45.95, -104.06, -96.44),
+    ("TN", 34.98, 36.68, -90.31, -81.65),
+    ("TX", 25.84, 36.50, -106.65, -93.51),
+    ("UT", 37.00, 42.00, -114.05, -109.04),
+    ("VT", 42.73, 45.02, -73.44, -71.46),
+    ("VA", 36.54, 39.47, -83.68, -75.24),
+    ("WA", 45.54, 49.00, -124.85, -116.92),
+    ("WV", 37.20, 40.64, -82.64, -77.72),
+    ("WI", 42.49, 47.31, -92.89, -86.25),
+    ("WY", 40.99, 45.01, -111.06, -104.05),
+    ("AS", -14.55, -11.04, -171.09, -168.14),
+    ("GU", 13.23, 13.65, 144.62, 144.96),
+    ("MP", 14.11, 20.55, 144.89, 145.87),
+    ("PR", 17.88, 18.52, -67.95, -65.22),
+    ("VI", 17.67, 18.42, -65.09, -64.56),
+];
+
+// The 3 digit zip code prefixes the Postal Service
+// assigns to each state, as inclusive ranges.
+const ZIP_PREFIXES: [(&str, &[(u16, u16)]); 62] = [
+    ("AL", &[(350, 369)]),
+    ("AK", &[(995, 999)]),
+    ("AZ", &[(850, 865)]),
+    ("AR", &[(716, 729), (755, 755)]),
+    ("CA", &[(900, 961)]),
+    ("CO", &[(800, 816)]),
+    ("CT", &[(60, 69)]),
+    ("DE", &[(197, 199)]),
+    ("DC", &[(200, 205), (569, 569)]),
+    ("FL", &[(320, 349)]),
+    ("GA", &[(300, 319), (398, 399)]),
+    ("HI", &[(967, 968)]),
+    ("ID", &[(832, 838)]),
+    ("IL", &[(600, 629)]),
+    ("IN", &[(460, 479)]),
+    ("IA", &[(500, 528)]),
+    ("KS", &[(660, 679)]),
+    ("KY", &[(400, 427)]),
+    ("LA", &[(700, 714)]),
+    ("ME", &[(39, 49)]),
+    ("MD", &[(206, 219)]),
+    ("MA", &[(10, 27), (55, 55)]),
+    ("MI", &[(480, 499)]),
+    ("MN", &[(550, 567)]),
+    ("MS", &[(386, 397)]),
+    ("MO", &[(630, 658)]),
+    ("MT", &[(590, 599)]),
+    ("NE", &[(680, 693)]),
+    ("NV", &[(889, 898)]),
+    ("NH", &[(30, 38)]),
+    ("NJ", &[(70, 89)]),
+    ("NM", &[(870, 884)]),
+    ("NY", &[(5, 5), (63, 63), (100, 149)]),
+    ("NC", &[(270, 289)]),
+    ("ND", &[(580, 588)]),
+    ("OH", &[(430, 459)]),
+    ("OK", &[(730, 749)]),
+    ("OR", &[(970, 979)]),
+    ("PA", &[(150, 196)]),
+    ("RI", &[(28, 29)]),
+    ("SC", &[(290, 299)]),
+    ("SD", &[(570, 577)]),
+    ("TN", &[(370, 385)]),
+    ("TX", &[(733, 733), (750, 799), (885, 885)]),
+    ("UT", &[(840, 847)]),
+    ("VT", &[(50, 59)]),
+    ("VA", &[(201, 201), (220, 246)]),
+    ("WA", &[(980, 994)]),
+    ("WV", &[(247, 268)]),
+    ("WI", &[(530, 549)]),
+    ("WY", &[(820, 831), (834, 834)]),
+    ("AS", &[(967, 967)]),
+    ("GU", &[(969, 969)]),
+    ("MP", &[(969, 969)]),
+    ("PR", &[(6, 7), (9, 9)]),
+    ("VI", &[(8, 8)]),
+    ("FM", &[(969, 969)]),
+    ("MH", &[(969, 969)]),
+    ("PW", &[(969, 969)]),
+    ("AA", &[(340, 340)]),
+    ("AE", &[(90, 99)]),
+    ("AP", &[(962, 966)]),
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "ERROR",
+            Severity::Warning => "WARNING",
+            Severity::Info => "INFO",
+        }
+    }
+}
+
+// One problem found in a file; `row` counts records
+// from 1, a csv header not included.
+#[derive(Serialize, Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: String,
+    pub row: Option<usize>,
+    pub check: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Audit {
+    pub files: Vec<String>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    pub findings: Vec<Finding>,
+}
+
+// The records of one file as read, with the number of
+// each record.
+pub struct AuditFile<'a> {
+    pub name: &'a str,
+    pub records: &'a [(usize, ObjLookUp)],
+}
+
+fn finding(
+    severity: Severity,
+    file: &str,
+    row: Option<usize>,
+    check: &str,
+    message: String,
+) -> Finding {
+    Finding {
+        severity,
+        file: file.to_owned(),
+        row,
+        check: check.to_owned(),
+        message,
+    }
+}
+
+// function reads the records of a lookup file shaped
+// like look_up.json or of a zip code table (.csv), each
+// with its number. Csv rows that cannot be read are
+// returned as findings.
+pub fn read_records(path: &str) -> (Vec<(usize, ObjLookUp)>, Vec<Finding>) {
+    if !path.to_lowercase().ends_with(".csv") {
+        let records = read_look_up(path);
+        return (
+            records
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| (i + 1, r))
+                .collect(),
+            Vec::new(),
+        );
+    }
+    let mut rdr = match csv::Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening zip code table";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let mut records: Vec<(usize, ObjLookUp)> = Vec::new();
+    let mut found: Vec<Finding> = Vec::new();
+    for (i, result) in rdr.deserialize::<ObjLookUp>().enumerate() {
+        match result {
+            Ok(rec) => records.push((i + 1, rec)),
+            Err(e) => found.push(finding(
+                Severity::Error,
+                path,
+                Some(i + 1),
+                "unreadable row",
+                e.to_string(),
+            )),
+        }
+    }
+    info!("Read {:?} record(s) of {:?} to audit", records.len(), path);
+    (records, found)
+}
+
+// function returns true when a coordinate lies in the
+// box of a state, or the state has no box.
+pub fn in_bounds(state: &str, lat: f64, lon: f64) -> bool {
+    let (min_lat, max_lat, min_lon, max_lon) = match STATE_BOUNDS.iter().find(|b| b.0 == state) {
+        Some(&(_, a, b, c, d)) => (a, b, c, d),
+        None => return true,
+    };
+    let lat_ok = lat >= min_lat - BOUNDS_MARGIN && lat <= max_lat + BOUNDS_MARGIN;
+    let lon_ok = match min_lon <= max_lon {
+        true => lon >= min_lon - BOUNDS_MARGIN && lon <= max_lon + BOUNDS_MARGIN,
+        false => lon >= min_lon - BOUNDS_MARGIN || lon <= max_lon + BOUNDS_MARGIN,
+    };
+    lat_ok && lon_ok
+}
+
+// function returns true when the zip code prefix
+// belongs to the state, or the state has no prefixes.
+pub fn prefix_matches(state: &str, zip: &str) -> bool {
+    let prefix = match zip.get(..3).and_then(|p| p.parse::<u16>().ok()) {
+        Some(prefix) => prefix,
+        None => return false,
+    };
+    match ZIP_PREFIXES.iter().find(|p| p.0 == state) {
+        Some((_, ranges)) => ranges.iter().any(|&(lo, hi)| prefix >= lo && prefix <= hi),
+        None => true,
+    }
+}
+
+// function checks each record of a file on its own:
+// valid fields, coordinates within the state's box and
+// a zip code prefix of the state. A record without
+// coordinates (0, 0) is reported, not checked.
+fn check_records(file: &AuditFile, severity: Severity) -> (Vec<Finding>, Vec<(usize, Place)>) {
+    let mut found: Vec<Finding> = Vec::new();
+    let mut places: Vec<(usize, Place)> = Vec::new();
+    for (row, rec) in file.records {
+        let place = match Place::try_from(rec) {
+            Ok(place) => place,
+            Err(errors) => {
+                found.push(finding(
+                    Severity::Error,
+                    file.name,
+                    Some(*row),
+                    "invalid record",
+                    format!("{}: {}", rec.zip_code, errors.join("; ")),
+                ));
+                continue;
+            }
+        };
+        let state = place.state.as_str();
+        let (lat, lon) = (place.location.lat, place.location.lon);
+        if lat == 0.0 && lon == 0.0 {
+            found.push(finding(
+                Severity::Info,
+                file.name,
+                Some(*row),
+                "no coordinates",
+                format!("{} {} has 0, 0 for coordinates", place.zip_code, state),
+            ));
+        } else if !in_bounds(state, lat, lon) {
+            found.push(finding(
+                severity,
+                file.name,
+                Some(*row),
+                "outside state",
+                format!(
+                    "{} {}, {} at {}, {} is outside {}",
+                    place.zip_code, place.city, state, lat, lon, state
+                ),
+            ));
+        }
+        if !prefix_matches(state, place.zip_code.as_str()) {
+            found.push(finding(
+                Severity::Warning,
+                file.name,
+                Some(*row),
+                "zip prefix",
+                format!(
+                    "{} {} does not have a zip code prefix of {}",
+                    place.zip_code, place.city, state
+                ),
+            ));
+        }
+        places.push((*row, place));
+    }
+    (found, places)
+}
+
+// function audits a lookup of one record per state,
+// such as look_up.json: besides the record checks,
+// states listed twice and the 50 states and DC that
+// are missing.
+pub fn audit_lookup(file: &AuditFile) -> Vec<Finding> {
+    let (mut found, places) = check_records(file, Severity::Error);
+    let mut seen: HashMap<StateCode, usize> = HashMap::new();
+    for (row, place) in &places {
+        match seen.get(&place.state) {
+            Some(first) => found.push(finding(
+                Severity::Warning,
+                file.name,
+                Some(*row),
+                "duplicate state",
+                format!(
+                    "{} is listed again ({} {}), first at record {}",
+                    place.state, place.zip_code, place.city, first
+                ),
+            )),
+            None => {
+                seen.insert(place.state, *row);
+            }
+        }
+    }
+    for state in StateCode::ALL
+        .iter()
+        .filter(|s| s.is_state() || **s == StateCode::DC)
+    {
+        if !seen.contains_key(state) {
+            found.push(finding(
+                Severity::Error,
+                file.name,
+                None,
+                "missing state",
+                format!("{} has no record", state),
+            ));
+        }
+    }
+    found
+}
+
+// function audits a zip code table such as codes.csv:
+// besides the record checks, zip codes listed twice
+// and states without any zip code.
+pub fn audit_zip_table(file: &AuditFile) -> Vec<Finding> {
+    let (mut found, places) = check_records(file, Severity::Warning);
+    let mut seen: HashMap<&str, usize> = HashMap::with_capacity(places.len());
+    let mut states: Vec<StateCode> = Vec::new();
+    for (row, place) in &places {
+        match seen.get(place.zip_code.as_str()) {
+            // the index and geocoder keep the first
+            Some(first) => found.push(finding(
+                Severity::Warning,
+                file.name,
+                Some(*row),
+                "duplicate zip code",
+                format!(
+                    "{} is listed again, first at record {}",
+                    place.zip_code, first
+                ),
+            )),
+            None => {
+                seen.insert(place.zip_code.as_str(), *row);
+            }
+        }
+        if !states.contains(&place.state) {
+            states.push(place.state);
+        }
+    }
+    for state in StateCode::ALL
+        .iter()
+        .filter(|s| s.is_state() || **s == StateCode::DC)
+    {
+        if !states.contains(state) {
+            found.push(finding(
+                Severity::Error,
+                file.name,
+                None,
+                "missing state",
+                format!("{} has no zip code", state),
+            ));
+        }
+    }
+    found
+}
+
+// function compares the records of a file with those
+// of a reference file sharing their zip codes: a zip
+// code the reference lacks, or one whose city, state,
+// coordinates or population differ, is a conflict.
+pub fn conflicts(file: &AuditFile, reference: &AuditFile) -> Vec<Finding> {
+    let by_zip: HashMap<&str, &ObjLookUp> = reference
+        .records
+        .iter()
+        .map(|(_, rec)| (rec.zip_code.trim(), rec))
+        .collect();
+    let number = |v: &str| v.trim().parse::<f64>().ok();
+    let mut found: Vec<Finding> = Vec::new();
+    for (row, rec) in file.records {
+        let other = match by_zip.get(rec.zip_code.trim()) {
+            Some(other) => other,
+            None => {
+                found.push(finding(
+                    Severity::Error,
+                    file.name,
+                    Some(*row),
+                    "conflict",
+                    format!("{} is not in {}", rec.zip_code, reference.name),
+                ));
+                continue;
+            }
+        };
+        let mut differ: Vec<String> = Vec::new();
+        if !rec.city.trim().eq_ignore_ascii_case(other.city.trim()) {
+            differ.push(format!("city {} vs {}", rec.city, other.city));
+        } else if rec.city.trim() != other.city.trim() {
+            differ.push(format!("city spelled {} vs {}", rec.city, other.city));
+        }
+        if rec.state.trim() != other.state.trim() {
+            differ.push(format!("state {} vs {}", rec.state, other.state));
+        }
+        let moved = |a: &str, b: &str| match (number(a), number(b)) {
+            (Some(a), Some(b)) => (a - b).abs() > SAME_COORDINATE,
+            _ => a != b,
+        };
+        if moved(&rec.latitude, &other.latitude) || moved(&rec.longitude, &other.longitude) {
+            differ.push(format!(
+                "coordinates {}, {} vs {}, {}",
+                rec.latitude, rec.longitude, other.latitude, other.longitude
+            ));
+        }
+        if number(&rec.population) != number(&other.population) {
+            differ.push(format!(
+                "population {} vs {}",
+                rec.population, other.population
+            ));
+        }
+        if !differ.is_empty() {
+            found.push(finding(
+                Severity::Warning,
+                file.name,
+                Some(*row),
+                "conflict",
+                format!(
+                    "{} differs from {}: {}",
+                    rec.zip_code,
+                    reference.name,
+                    differ.join("; ")
+                ),
+            ));
+        }
+    }
+    found
+}
+
+// function compares two lookups of one record per
+// state: a state listed with a different zip code in
+// the other file is a conflict.
+pub fn state_conflicts(file: &AuditFile, other: &AuditFile) -> Vec<Finding> {
+    let mut found: Vec<Finding> = Vec::new();
+    for (row, rec) in file.records {
+        let zips: Vec<&str> = other
+            .records
+            .iter()
+            .filter(|(_, o)| o.state == rec.state)
+            .map(|(_, o)| o.zip_code.as_str())
+            .collect();
+        if !zips.is_empty() && !zips.contains(&rec.zip_code.as_str()) {
+            found.push(finding(
+                Severity::Warning,
+                file.name,
+                Some(*row),
+                "conflict",
+                format!(
+                    "{} is {} here, {} in {}",
+                    rec.state,
+                    rec.zip_code,
+                    zips.join(", "),
+                    other.name
+                ),
+            ));
+        }
+    }
+    found
+}
+
+// function sorts the findings, most severe first, and
+// counts them.
+pub fn summarize(files: &[&str], mut findings: Vec<Finding>) -> Audit {
+    findings.sort_by(|a, b| {
+        (a.severity, &a.file, a.row, &a.check).cmp(&(b.severity, &b.file, b.row, &b.check))
+    });
+    let count = |s: Severity| findings.iter().filter(|f| f.severity == s).count();
+    let audit = Audit {
+        files: files.iter().map(|f| f.to_string()).collect(),
+        errors: count(Severity::Error),
+        warnings: count(Severity::Warning),
+        infos: count(Severity::Info),
+        findings,
+    };
+    match audit.errors {
+        0 => info!(
+            "Audit found {:?} warning(s), {:?} info(s)",
+            audit.warnings, audit.infos
+        ),
+        n => error!(
+            "Audit found {:?} error(s), {:?} warning(s), {:?} info(s)",
+            n, audit.warnings, audit.infos
+        ),
+    }
+    for f in audit
+        .findings
+        .iter()
+        .filter(|f| f.severity == Severity::Warning)
+    {
+        warn!("{}: {}: {}", f.file, f.check, f.message);
+    }
+    audit
+}
+
+// function renders an audit as plain text. Info
+// findings are counted per file and check rather than
+// listed, codes.csv alone has hundreds.
+pub fn to_text(audit: &Audit) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "AUDIT OF: {}", audit.files.join(", "));
+    let _ = writeln!(
+        out,
+        "ERRORS: {}  WARNINGS: {}  INFOS: {}",
+        audit.errors, audit.warnings, audit.infos
+    );
+    let _ = writeln!(out);
+    let mut infos: Vec<(&str, &str, usize)> = Vec::new();
+    for f in &audit.findings {
+        if f.severity == Severity::Info {
+            match infos.iter_mut().find(|i| i.0 == f.file && i.1 == f.check) {
+                Some(i) => i.2 += 1,
+                None => infos.push((&f.file, &f.check, 1)),
+            }
+            continue;
+        }
+        let row = f.row.map_or(String::new(), |r| format!(" #{}", r));
+        let _ = writeln!(
+            out,
+            "{:<8} {}{}: {}: {}",
+            f.severity.as_str(),
+            f.file,
+            row,
+            f.check,
+            f.message
+        );
+    }
+    for (file, check, n) in infos {
+        let _ = writeln!(out, "{:<8} {}: {}: {} record(s)", "INFO", file, check, n);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::test_record;
+
+    fn record(zip_code: &str, city: &str, state: &str, lat: &str, lon: &str) -> ObjLookUp {
+        ObjLookUp {
+            city: city.to_owned(),
+            latitude: lat.to_owned(),
+            longitude: lon.to_owned(),
+            ..test_record(zip_code, state)
+        }
+    }
+
+    fn numbered(records: Vec<ObjLookUp>) -> Vec<(usize, ObjLookUp)> {
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| (i + 1, r))
+            .collect()
+    }
+
+    fn checks(found: &[Finding]) -> Vec<(Option<usize>, &str)> {
+        found.iter().map(|f| (f.row, f.check.as_str())).collect()
+    }
+
+    #[test]
+    fn boxes_and_prefixes_of_a_state() {
+        assert!(in_bounds("IA", 41.6006, -93.6087));
+        assert!(!in_bounds("IA", 42.6525, -73.7567));
+        // the Aleutians cross the 180th meridian
+        assert!(in_bounds("AK", 52.0, 175.0));
+        assert!(in_bounds("AK", 52.0, -175.0));
+        assert!(!in_bounds("AK", 52.0, 100.0));
+        assert!(in_bounds("ZZ", 0.0, 0.0));
+        assert!(prefix_matches("CT", "06106"));
+        assert!(!prefix_matches("IA", "12224"));
+        assert!(!prefix_matches("IA", "5a319"));
+    }
+
+    #[test]
+    fn the_bundled_lookup_misses_arkansas_and_lists_connecticut_twice() {
+        let (records, unread) = read_records("look_up.json");
+        assert!(unread.is_empty());
+        let found = audit_lookup(&AuditFile {
+            name: "look_up.json",
+            records: &records,
+        });
+        assert_eq!(
+            checks(&found),
+            [(Some(35), "duplicate state"), (None, "missing state")]
+        );
+        assert_eq!(found[1].message, "AR has no record");
+        assert_eq!(found[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn checks_every_record_of_a_zip_table() {
+        let records = numbered(vec![
+            record("50319", "DES MOINES", "IA", "41.6006", "-93.6087"),
+            record("50319", "DES MOINES", "IA", "41.6006", "-93.6087"),
+            record("12224", "ALBANY", "IA", "42.6525", "-73.7567"),
+            record("09002", "APO", "AE", "0.0000", "0.0000"),
+            record("5031", "", "IA", "north", "-93.6"),
+        ]);
+        let found = audit_zip_table(&AuditFile {
+            name: "table.csv",
+            records: &records,
+        });
+        let mut rows = checks(&found);
+        rows.retain(|r| r.1 != "missing state");
+        assert_eq!(
+            rows,
+            [
+                (Some(3), "outside state"),
+                (Some(3), "zip prefix"),
+                (Some(4), "no coordinates"),
+                (Some(5), "invalid record"),
+                (Some(2), "duplicate zip code"),
+            ]
+        );
+        // IA is the only state with a zip code
+        assert_eq!(found.len() - rows.len(), 50);
+    }
+
+    #[test]
+    fn compares_records_with_a_reference() {
+        let reference = numbered(vec![
+            record("84103", "SALT LAKE CITY", "UT", "40.7774", "-111.9301"),
+            record("20004", "WASHINGTON", "DC", "38.8951", "-77.0252"),
+        ]);
+        let records = numbered(vec![
+            record("84103", "SALT LAKE city", "UT", "40.77745", "-111.9301"),
+            record("20004", "WASHINGTON", "DC", "38.9", "-77.0252"),
+            record("20500", "WASHINGTON", "DC", "38.8987", "-77.0377"),
+        ]);
+        let file = AuditFile {
+            name: "mine.json",
+            records: &records,
+        };
+        let other = AuditFile {
+            name: "theirs.csv",
+            records: &reference,
+        };
+        let found = conflicts(&file, &other);
+        let messages: Vec<&str> = found.iter().map(|f| f.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "84103 differs from theirs.csv: city spelled SALT LAKE city vs SALT LAKE CITY",
+                "20004 differs from theirs.csv: coordinates 38.9, -77.0252 vs 38.8951, -77.0252",
+                "20500 is not in theirs.csv",
+            ]
+        );
+        let found = state_conflicts(&file, &other);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].message, "DC is 20500 here, 20004 in theirs.csv");
+    }
+
+    #[test]
+    fn summarizes_the_most_severe_first() {
+        let f = |severity, row, check: &str| finding(severity, "a.csv", row, check, String::new());
+        let audit = summarize(
+            &["a.csv"],
+            vec![
+                f(Severity::Info, Some(1), "no coordinates"),
+                f(Severity::Warning, Some(2), "zip prefix"),
+                f(Severity::Info, Some(3), "no coordinates"),
+                f(Severity::Error, None, "missing state"),
+            ],
+        );
+        assert_eq!((audit.errors, audit.warnings, audit.infos), (1, 1, 2));
+        assert_eq!(audit.findings[0].severity, Severity::Error);
+        let text = to_text(&audit);
+        assert!(text.starts_with("AUDIT OF: a.csv\nERRORS: 1  WARNINGS: 1  INFOS: 2\n"));
+        assert!(text.contains("WARNING  a.csv #2: zip prefix: \n"));
+        assert!(text.ends_with("INFO     a.csv: no coordinates: 2 record(s)\n"));
+    }
+}
@@ -0,0 +1,101 @@
+// The capitals sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    capitals::{generate as generate_capitals, FAR_MILES},
+    cli::{flag_value, parse_flag},
+    command::{write_json, write_rows, ZIP_TABLE},
+    index::LookupIndex,
+    territory::Inclusion,
+    zips::read_zip_csv,
+};
+use log::info;
+
+const CAPITALS_JSON: &str = "capitals.json";
+const CAPITALS_CSV: &str = "capitals.csv";
+const CAPITALS_CHECK_CSV: &str = "capitals_check.csv";
+
+// capitals: regenerate the capitals lookup from the
+// bundled capitals table and a zip code table, and
+// flag capitol zip codes far from the capitol building.
+//   --table file.csv           zip code table (default: codes.csv)
+//   --out file.json            lookup file (default: capitals.json;
+//                              look_up.json to replace it)
+//   --csv file.csv             the same as a zip code table
+//                              (default: capitals.csv)
+//   --far MILES                flag distance (default: 5)
+//   --territories include      add the capitals of PR, VI,
+//                              GU, AS and MP
+pub fn capitals(args: &[String]) {
+    let table = flag_value(args, "--table").unwrap_or(ZIP_TABLE);
+    let out = flag_value(args, "--out").unwrap_or(CAPITALS_JSON);
+    let out_csv = flag_value(args, "--csv").unwrap_or(CAPITALS_CSV);
+    let far = parse_flag(args, "--far", FAR_MILES);
+    let index = LookupIndex::new(read_zip_csv(table));
+    let territories = parse_flag(args, "--territories", Inclusion::Exclude);
+    let (records, checks) = generate_capitals(&index, far, territories);
+
+    // serialized in field order, as in look_up.json
+    write_json(out, &records);
+    let mut rows: Vec<Vec<String>> = vec![[
+        "zip_code",
+        "City",
+        "State",
+        "Latitude",
+        "Longitude",
+        "Classification",
+        "Population",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    for r in &records {
+        rows.push(vec![
+            r.zip_code.clone(),
+            r.city.clone(),
+            r.state.clone(),
+            r.latitude.clone(),
+            r.longitude.clone(),
+            r.classification.clone(),
+            r.population.clone(),
+        ]);
+    }
+    write_rows(out_csv, &rows);
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "STATE",
+        "CITY",
+        "ZIP_CODE",
+        "MILES",
+        "FAR",
+        "NEAREST_ZIP",
+        "NEAREST_MILES",
+        "NOTES",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    for c in &checks {
+        rows.push(vec![
+            c.state.clone(),
+            c.city.clone(),
+            c.zip_code.clone(),
+            format!("{:.1}", c.miles),
+            c.far.to_string(),
+            c.nearest_zip.clone().unwrap_or_default(),
+            c.nearest_miles
+                .map_or(String::new(), |m| format!("{:.1}", m)),
+            c.notes.join("; "),
+        ]);
+    }
+    write_rows(CAPITALS_CHECK_CSV, &rows);
+    info!(
+        "{:?} capital(s) from {:?} written to {:?} and {:?}, {:?} far from the capitol in {:?}",
+        records.len(),
+        table,
+        out,
+        out_csv,
+        checks.iter().filter(|c| c.far).count(),
+        CAPITALS_CHECK_CSV
+    );
+}
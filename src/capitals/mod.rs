@@ -0,0 +1,264 @@
+pub mod command;
+
+use crate::{
+    distance::haversine_dist, index::LookupIndex, lookup::ObjLookUp, territory::Inclusion,
+};
+use log::{error, info, warn};
+use serde::Serialize;
+
+// Capitol zip codes further than this from the capitol
+// building, in miles, are flagged.
+pub const FAR_MILES: f64 = 5.0;
+
+// The state capitals in the order of look_up.json: state
+// code, city as spelled in codes.csv, the zip code the
+// lookup uses and the capitol building's coordinates.
+const CAPITALS: [(&str, &str, &str, f64, f64); 51] = [
+    ("IA", "DES MOINES", "50319", 41.5911, -93.6038),
+    ("NY", "ALBANY", "12224", 42.6526, -73.7573),
+    ("WI", "MADISON", "53703", 43.0747, -89.3842),
+    ("TX", "AUSTIN", "78701", 30.2747, -97.7404),
+    ("CA", "SACRAMENTO", "95814", 38.5766, -121.4934),
+    ("TN", "NASHVILLE", "37243", 36.1659, -86.7844),
+    ("VA", "RICHMOND", "23218", 37.5387, -77.4336),
+    ("CT", "HARTFORD", "06106", 41.7640, -72.6822),
+    ("OR", "SALEM", "97301", 44.9386, -123.0302),
+    ("LA", "BATON ROUGE", "70802", 30.4571, -91.1874),
+    ("MT", "HELENA", "59601", 46.5857, -112.0184),
+    ("WV", "CHARLESTON", "25305", 38.3365, -81.6123),
+    ("IL", "SPRINGFIELD", "62756", 39.7983, -89.6544),
+    ("NM", "SANTA FE", "87501", 35.6824, -105.9396),
+    ("ND", "BISMARCK", "58505", 46.8208, -100.7827),
+    ("AZ", "PHOENIX", "85007", 33.4482, -112.0970),
+    ("MN", "SAINT PAUL", "55155", 44.9551, -93.1022),
+    ("WA", "OLYMPIA", "98504", 47.0358, -122.9050),
+    ("KY", "FRANKFORT", "40601", 38.1867, -84.8753),
+    ("ID", "BOISE", "83702", 43.6178, -116.1996),
+    ("CO", "DENVER", "80203", 39.7393, -104.9848),
+    ("UT", "SALT LAKE CITY", "84103", 40.7774, -111.8882),
+    ("NE", "LINCOLN", "68508", 40.8081, -96.6997),
+    ("OK", "OKLAHOMA CITY", "73105", 35.4923, -97.5033),
+    ("WY", "CHEYENNE", "82001", 41.1403, -104.8203),
+    ("MO", "JEFFERSON CITY", "65101", 38.5791, -92.1729),
+    ("GA", "ATLANTA", "30334", 33.7490, -84.3880),
+    ("MI", "LANSING", "48933", 42.7336, -84.5555),
+    ("AL", "MONTGOMERY", "36104", 32.3777, -86.3006),
+    ("FL", "TALLAHASSEE", "32399", 30.4381, -84.2816),
+    ("KS", "TOPEKA", "66612", 39.0481, -95.6780),
+    ("SD", "PIERRE", "57501", 44.3670, -100.3462),
+    ("NC", "RALEIGH", "27601", 35.7804, -78.6391),
+    ("AK", "JUNEAU", "99801", 58.3019, -134.4105),
+    ("AR", "LITTLE ROCK", "72201", 34.7466, -92.2890),
+    ("DE", "DOVER", "19901", 39.1573, -75.5197),
+    ("HI", "HONOLULU", "96813", 21.3073, -157.8574),
+    ("IN", "INDIANAPOLIS", "46204", 39.7686, -86.1626),
+    ("ME", "AUGUSTA", "04330", 44.3072, -69.7817),
+    ("MD", "ANNAPOLIS", "21401", 38.9788, -76.4910),
+    ("MA", "BOSTON", "02133", 42.3587, -71.0638),
+    ("MS", "JACKSON", "39201", 32.3038, -90.1821),
+    ("NV", "CARSON CITY", "89701", 39.1640, -119.7663),
+    ("NH", "CONCORD", "03303", 43.2067, -71.5381),
+    ("NJ", "TRENTON", "08608", 40.2206, -74.7699),
+    ("OH", "COLUMBUS", "43215", 39.9612, -82.9988),
+    ("PA", "HARRISBURG", "17120", 40.2643, -76.8837),
+    ("RI", "PROVIDENCE", "02903", 41.8309, -71.4150),
+    ("SC", "COLUMBIA", "29208", 34.0007, -81.0331),
+    ("VT", "MONTPELIER", "05633", 44.2623, -72.5805),
+    ("DC", "WASHINGTON", "20004", 38.8899, -77.0091),
+];
+
+// The capitals of the territories, laid out as
+// CAPITALS; Charlotte Amalie is ST THOMAS in codes.csv.
+const TERRITORY_CAPITALS: [(&str, &str, &str, f64, f64); 5] = [
+    ("PR", "SAN JUAN", "00901", 18.4660, -66.1057),
+    ("VI", "ST THOMAS", "00802", 18.3422, -64.9307),
+    ("GU", "HAGATNA", "96910", 13.4767, 144.7503),
+    ("AS", "PAGO PAGO", "96799", -14.2794, -170.6889),
+    ("MP", "SAIPAN", "96950", 15.2139, 145.7531),
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Capital {
+    pub state: String,
+    pub city: String,
+    pub zip_code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+// How far the centroid of a capital's zip code is from
+// the capitol building, with the closest zip code of
+// the state when that one is closer.
+#[derive(Serialize, Debug, Clone)]
+pub struct CapitolCheck {
+    pub state: String,
+    pub city: String,
+    pub zip_code: String,
+    pub miles: f64,
+    pub far: bool,
+    pub nearest_zip: Option<String>,
+    pub nearest_miles: Option<f64>,
+    pub notes: Vec<String>,
+}
+
+// function returns the bundled table of capitals,
+// followed by those of the territories if included.
+pub fn capitals(territories: Inclusion) -> Vec<Capital> {
+    let extra: &[(&str, &str, &str, f64, f64)] = match territories {
+        Inclusion::Include => &TERRITORY_CAPITALS,
+        Inclusion::Exclude => &[],
+    };
+    CAPITALS
+        .iter()
+        .chain(extra)
+        .map(|&(state, city, zip_code, latitude, longitude)| Capital {
+            state: state.to_owned(),
+            city: city.to_owned(),
+            zip_code: zip_code.to_owned(),
+            latitude,
+            longitude,
+        })
+        .collect()
+}
+
+// function returns the capital of a state or
+// territory code.
+pub fn capital(state: &str) -> Option<Capital> {
+    capitals(Inclusion::Include)
+        .into_iter()
+        .find(|c| c.state == state)
+}
+
+// function joins the capitals with the zip code table
+// behind `index` into lookup records, coordinates with
+// 4 decimals as in codes.csv, and checks each capitol
+// zip code against the capitol building. Panics on a
+// zip code the table does not have or has no
+// coordinates for.
+pub fn generate(
+    index: &LookupIndex,
+    far_miles: f64,
+    territories: Inclusion,
+) -> (Vec<ObjLookUp>, Vec<CapitolCheck>) {
+    let caps = capitals(territories);
+    let mut records: Vec<ObjLookUp> = Vec::with_capacity(caps.len());
+    let mut checks: Vec<CapitolCheck> = Vec::with_capacity(caps.len());
+    for cap in caps {
+        let rec = match index.zip(&cap.zip_code) {
+            Some(rec) if rec.located() => rec,
+            _ => {
+                let msg = "Capitol zip code not found in table";
+                error!("{:?}: {:?} {:?}", msg, cap.state, cap.zip_code);
+                panic!("{:?}: {:?} {:?}", msg, cap.state, cap.zip_code)
+            }
+        };
+        let mut notes: Vec<String> = Vec::new();
+        if rec.state != cap.state {
+            notes.push(format!("zip code {} is in {}", rec.zip_code, rec.state));
+        }
+        if rec.city != cap.city {
+            notes.push(format!("zip code {} is in {}", rec.zip_code, rec.city));
+        }
+        let miles = haversine_dist(cap.latitude, cap.longitude, rec.latitude, rec.longitude);
+        let far = miles > far_miles;
+        // closest zip code of the same state, if closer
+        let nearest = index
+            .nearest(cap.latitude, cap.longitude, 10)
+            .into_iter()
+            .find(|(r, _)| r.state == cap.state)
+            .filter(|(r, m)| r.zip_code != rec.zip_code && *m < miles);
+        if far {
+            notes.push(format!("{:.1} mi from the capitol", miles));
+            warn!(
+                "Capitol zip code {:?} of {:?} is {:.1} mi from the capitol",
+                rec.zip_code, cap.state, miles
+            );
+        }
+        records.push(ObjLookUp {
+            zip_code: rec.zip_code.clone(),
+            city: rec.city.clone(),
+            state: rec.state.clone(),
+            latitude: format!("{:.4}", rec.latitude),
+            longitude: format!("{:.4}", rec.longitude),
+            classification: rec.classification.clone(),
+            population: rec.population.to_string(),
+        });
+        checks.push(CapitolCheck {
+            state: cap.state,
+            city: cap.city,
+            zip_code: cap.zip_code,
+            miles,
+            far,
+            nearest_zip: nearest.map(|(r, _)| r.zip_code.clone()),
+            nearest_miles: nearest.map(|(_, m)| m),
+            notes,
+        });
+    }
+    info!(
+        "Generated {:?} capital(s), {:?} far from the capitol",
+        records.len(),
+        checks.iter().filter(|c| c.far).count()
+    );
+    (records, checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lookup::{one_per_state, read_look_up},
+        zips::read_zip_csv,
+    };
+
+    #[test]
+    fn the_table_follows_look_up_json() {
+        let data = one_per_state(read_look_up("look_up.json"));
+        let caps = capitals(Inclusion::Exclude);
+        assert_eq!(caps.len(), 51);
+        for (k, cap) in caps.iter().enumerate() {
+            assert!(caps[k + 1..].iter().all(|c| c.state != cap.state));
+        }
+        // look_up.json leaves out AR
+        let states: Vec<&str> = caps
+            .iter()
+            .map(|c| c.state.as_str())
+            .filter(|&s| s != "AR")
+            .collect();
+        let lookup: Vec<&str> = data.iter().map(|r| r.state.as_str()).collect();
+        assert_eq!(states, lookup);
+        assert_eq!(capitals(Inclusion::Include).len(), 56);
+        assert_eq!(capital("VI").unwrap().city, "ST THOMAS");
+        assert!(capital("XX").is_none());
+    }
+
+    #[test]
+    fn generates_the_lookup_from_the_zip_code_table() {
+        let index = LookupIndex::new(read_zip_csv("codes.csv"));
+        let (records, checks) = generate(&index, FAR_MILES, Inclusion::Include);
+        assert_eq!(records.len(), 56);
+        assert_eq!(checks.len(), 56);
+        let data = read_look_up("look_up.json");
+        for (rec, cap) in records.iter().zip(capitals(Inclusion::Include)) {
+            assert_eq!((&rec.state, &rec.zip_code), (&cap.state, &cap.zip_code));
+            if let Some(old) = data.iter().find(|d| d.zip_code == rec.zip_code) {
+                assert_eq!(
+                    (&rec.latitude, &rec.longitude),
+                    (&old.latitude, &old.longitude)
+                );
+            }
+        }
+        for check in &checks {
+            assert_eq!(check.far, check.miles > FAR_MILES);
+            if let Some(m) = check.nearest_miles {
+                assert!(m < check.miles);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Capitol zip code not found in table")]
+    fn a_missing_capitol_zip_code_is_refused() {
+        let index = LookupIndex::new(read_zip_csv("zip_codes.csv"));
+        generate(&index, FAR_MILES, Inclusion::Include);
+    }
+}
@@ -0,0 +1,94 @@
+// Helpers for the optional command line arguments,
+// e.g. `read_json pareto --states IA,NY,WI,DC`.
+
+// function returns the value following a flag.
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+// function returns every value of a repeatable flag.
+pub fn flag_values<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+    let mut values: Vec<&str> = Vec::new();
+    for (i, a) in args.iter().enumerate() {
+        if a == flag {
+            if let Some(v) = args.get(i + 1) {
+                values.push(v.as_str());
+            }
+        }
+    }
+    values
+}
+
+// function returns true when a switch is present.
+pub fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|a| a == flag)
+}
+
+// function splits a comma separated list such as
+// "IA,NY,DC" and drops empty entries.
+pub fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// function parses the value of a flag, falling back
+// to a default when the flag is absent.
+pub fn parse_flag<T>(args: &[String], flag: &str, default: T) -> T
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Debug,
+{
+    match flag_value(args, flag) {
+        Some(v) => match v.parse::<T>() {
+            Ok(v) => v,
+            Err(e) => {
+                let msg = "Invalid value for command line flag";
+                log::error!("{:?}: {:?} {:?}: {:?}", msg, flag, v, e);
+                panic!("{:?}: {:?} {:?}: {:?}", msg, flag, v, e)
+            }
+        },
+        None => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|s| s.to_owned()).collect()
+    }
+
+    #[test]
+    fn reads_flags_and_switches() {
+        let a = args("read_json pareto --states IA,NY --matrix A=a.csv --matrix B=b.csv --return");
+        assert_eq!(flag_value(&a, "--states"), Some("IA,NY"));
+        assert_eq!(flag_value(&a, "--seed"), None);
+        assert_eq!(flag_values(&a, "--matrix"), vec!["A=a.csv", "B=b.csv"]);
+        assert!(has_flag(&a, "--return"));
+        assert!(!has_flag(&a, "--open"));
+        // a flag at the end has no value
+        assert_eq!(flag_value(&a, "--return"), None);
+    }
+
+    #[test]
+    fn splits_lists_and_parses_values() {
+        assert_eq!(split_list(" IA, NY,,DC "), vec!["IA", "NY", "DC"]);
+        let a = args("read_json pareto --seed 42 --mutation 0.5");
+        assert_eq!(parse_flag(&a, "--seed", 2020u64), 42);
+        assert_eq!(parse_flag(&a, "--mutation", 0.2), 0.5);
+        assert_eq!(parse_flag(&a, "--population", 100usize), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid value for command line flag")]
+    fn rejects_a_value_that_does_not_parse() {
+        parse_flag(&args("read_json pareto --seed many"), "--seed", 2020u64);
+    }
+}
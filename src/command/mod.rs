@@ -0,0 +1,407 @@
+// Glue for the optional sub commands of the binary:
+// the flags, stops and output files they share. Each
+// sub command lives next to its library module, e.g.
+// pareto::command, and is re-exported here; it reads
+// its flags, calls into the library modules and writes
+// its output files.
+
+use crate::{
+    cli::{flag_value, parse_flag, split_list},
+    constraints::read_constraints,
+    lookup::{
+        one_per_state, one_per_zip, read_look_up, stop_indices, stop_name, stop_records, ObjLookUp,
+    },
+    modal::{read_airports, Airport, ModalParams, Mode},
+    place::{report_invalid, validate_look_up},
+    precedence::{from_groups, violations, Precedence},
+    route::{read_route, read_route_row, RouteFile},
+    stops::{from_constraints, violations as stop_violations, visits_between, Stops},
+    territory::{filter_records, force_flights, Inclusion},
+    zips::read_zip_look_up,
+};
+use chrono::prelude::*;
+use csv::Writer;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+
+pub use crate::{
+    audit::command::audit,
+    capitals::command::capitals,
+    compare::command::compare,
+    costing::command::cost,
+    geocode::command::{geocode, reverse},
+    index::command::lookup,
+    matrix::command::distances,
+    modal::command::modal,
+    mtsp::command::mtsp,
+    pairs::command::pairs,
+    pareto::command::pareto,
+    prize::command::prize,
+    schedule::command::schedule,
+    validate::command::validate,
+    windows::command::windows,
+};
+
+pub const MODAL_JSON: &str = "modal.json";
+pub const AIRPORTS_CSV: &str = "airports.csv";
+pub const CYPHER_CSV: &str = "cypher.csv";
+pub const ZIP_TABLE: &str = "codes.csv";
+
+// Most lookup records a command works on; every
+// command builds matrices over all of them.
+const LOOKUP_LIMIT: usize = 1000;
+
+// function returns the lookup indices of the start,
+// the stops to visit in between and the end, if any.
+// The in between stops are the records of `--states
+// AL,AZ,...`, state or zip codes, or default to every
+// record of the lookup. Required stops are added and
+// listed as many times as they may be visited;
+// optional stops are left out and the others listed
+// once, `visits` only caps them.
+pub fn route_stops(
+    args: &[String],
+    data: &[ObjLookUp],
+    start: &str,
+    end: Option<&str>,
+    stops: &Stops,
+) -> (usize, Vec<usize>, Option<usize>) {
+    let first = stop_indices(data, &[start])[0];
+    let last = end.map(|e| stop_indices(data, &[e])[0]);
+    let (start, end) = (stop_name(data, first), last.map(|l| stop_name(data, l)));
+
+    let mut candidates: Vec<usize> = match flag_value(args, "--states") {
+        Some(list) => stop_records(data, &split_list(list)),
+        None => (0..data.len()).collect(),
+    };
+    for name in &stops.required {
+        candidates.extend(stop_indices(data, &[name.as_str()]));
+    }
+    let mut middle: Vec<usize> = Vec::with_capacity(candidates.len());
+    for i in candidates {
+        // skip optional and repeated stops; the
+        // endpoints count as visits of their stops
+        let name = stop_name(data, i);
+        if !stops.is_optional(&name) && !middle.contains(&i) {
+            let times = match stops.is_required(&name) {
+                true => visits_between(&name, &start, end.as_deref(), stops),
+                false => visits_between(&name, &start, end.as_deref(), stops).min(1),
+            };
+            middle.resize(middle.len() + times, i);
+        }
+    }
+    info!(
+        "Route stops: {:?} -> {:?} stops -> {:?}",
+        start,
+        middle.len(),
+        end
+    );
+    (first, middle, last)
+}
+
+// function panics, before a zip code table is read in
+// full, when it has more rows than LOOKUP_LIMIT; such
+// a table, e.g. codes.csv, is a lookup only through
+// the zip codes picked with `--zips`.
+fn table_fits(path: &str) {
+    let rows = match fs::read_to_string(path) {
+        Ok(text) => text
+            .lines()
+            .skip(1)
+            .filter(|l| !l.trim().is_empty())
+            .count(),
+        Err(e) => {
+            let msg = "Error reading file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    if rows > LOOKUP_LIMIT {
+        let msg = "Zip code table is too large for a lookup, pick zip codes with --zips";
+        error!(
+            "{:?}: {:?}: {:?} rows, at most {:?}",
+            msg, path, rows, LOOKUP_LIMIT
+        );
+        panic!(
+            "{:?}: {:?}: {:?} rows, at most {:?}",
+            msg, path, rows, LOOKUP_LIMIT
+        )
+    }
+}
+
+// function returns the lookup records of `--lookup`
+// when given: a zip code table (.csv) such as codes.csv,
+// keeping the zip codes of `--zips` if any, or a json
+// file shaped like look_up.json. Without the flag the
+// records already read are kept. Every record of a
+// table is a stop of its own, a repeated zip code
+// dropped; a json lookup lists the capitals and keeps
+// the first record of each state. The territories are
+// left out unless `--territories include`, AK and HI
+// are kept unless `--noncontiguous exclude`. Every
+// record is validated; all invalid ones are logged
+// before the program stops.
+pub fn look_up_source(args: &[String], data: Vec<ObjLookUp>) -> Vec<ObjLookUp> {
+    let (path, records) = match flag_value(args, "--lookup") {
+        Some(path) => {
+            let zips = flag_value(args, "--zips")
+                .map(split_list)
+                .unwrap_or_default();
+            let records = match path.to_lowercase().ends_with(".csv") {
+                true => {
+                    if zips.is_empty() {
+                        table_fits(path);
+                    }
+                    one_per_zip(read_zip_look_up(path, &zips))
+                }
+                false => one_per_state(read_look_up(path)),
+            };
+            (path, records)
+        }
+        None => ("look_up.json", one_per_state(data)),
+    };
+    let records = filter_records(
+        records,
+        parse_flag(args, "--territories", Inclusion::Exclude),
+        parse_flag(args, "--noncontiguous", Inclusion::Include),
+    );
+    if records.len() > LOOKUP_LIMIT {
+        let msg = "Too many lookup records, pick zip codes with --zips";
+        error!("{:?}: {:?}: {:?}", msg, path, records.len());
+        panic!("{:?}: {:?}: {:?}", msg, path, records.len())
+    }
+    if let Err(invalid) = validate_look_up(&records) {
+        report_invalid(path, &invalid);
+    }
+    info!("Lookup: {:?} record(s) from {:?}", records.len(), path);
+    records
+}
+
+// function writes a json value, or anything that
+// serializes to one, to a file.
+pub fn write_json<T: Serialize + ?Sized>(path: &str, obj: &T) {
+    let text = match serde_json::to_string_pretty(obj) {
+        Ok(text) => text,
+        Err(e) => {
+            let msg = "Error serializing json";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    write_text(path, &text);
+}
+
+// function writes csv rows, the first row being the header.
+pub fn write_rows(path: &str, rows: &[Vec<String>]) {
+    let mut wtr = match Writer::from_path(path) {
+        Ok(wtr) => wtr,
+        Err(e) => {
+            let msg = "Error creating csv file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    for row in rows {
+        if let Err(e) = wtr.write_record(row) {
+            error!("Could not write row to {:?}: {:?}", path, e);
+            panic!("Could not write row to {:?}: {:?}", path, e);
+        }
+    }
+    if let Err(e) = wtr.flush() {
+        error!("Could not flush {:?}: {:?}", path, e);
+        panic!("Could not flush {:?}: {:?}", path, e);
+    }
+    info!("Success writing file {:?}", path);
+}
+
+// function writes a text file, e.g. a report or a
+// calendar.
+pub fn write_text(path: &str, text: &str) {
+    match fs::write(path, text) {
+        Ok(_) => info!("Success writing file {:?}", path),
+        Err(e) => {
+            let msg = "Error writing file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    }
+}
+
+// function adds the creation times every json output
+// carries, local and UTC, to a json object.
+pub fn stamp(mut obj: Value) -> Value {
+    if let Value::Object(map) = &mut obj {
+        let dt = format!("{}", Local::now().format("%a %b %e %T %Y"));
+        map.insert("time_created".to_owned(), json!(dt));
+        map.insert(
+            "time_created_utc".to_owned(),
+            json!(Utc::now().to_rfc3339()),
+        );
+    }
+    obj
+}
+
+// function writes the outputs of a command: the csv
+// rows and the json object, stamped with its creation
+// times.
+pub fn write_outputs(csv: &str, rows: &[Vec<String>], json: &str, obj: Value) {
+    write_rows(csv, rows);
+    write_json(json, &stamp(obj));
+}
+
+// function reads the speed and overhead flags shared
+// by the commands that deal with travel modes.
+pub fn modal_params(args: &[String]) -> ModalParams {
+    let defaults = ModalParams::default();
+    ModalParams {
+        drive_mph: parse_flag(args, "--drive-mph", defaults.drive_mph),
+        fly_mph: parse_flag(args, "--fly-mph", defaults.fly_mph),
+        airport_hours: parse_flag(args, "--airport-hours", defaults.airport_hours),
+        switch_hours: parse_flag(args, "--switch-hours", defaults.switch_hours),
+        road_factor: defaults.road_factor,
+    }
+}
+
+// function reads the airport table given by
+// `--airports`, airports.csv without the flag.
+pub fn read_airport_table(args: &[String]) -> Vec<Airport> {
+    read_airports(flag_value(args, "--airports").unwrap_or(AIRPORTS_CSV))
+}
+
+// function returns the positional argument after the
+// sub command, e.g. the route file of `cost route.json`.
+pub fn positional<'a>(args: &'a [String], default: &'a str) -> &'a str {
+    match args.get(2) {
+        Some(a) if !a.starts_with("--") => a.as_str(),
+        _ => default,
+    }
+}
+
+// function returns the precedence pairs of the
+// constraints file given by `--constraints`, none
+// without the flag.
+pub fn read_precedence(args: &[String], data: &[ObjLookUp]) -> Precedence {
+    match flag_value(args, "--constraints") {
+        Some(path) => from_groups(data, &read_constraints(path).precedence),
+        None => Precedence::default(),
+    }
+}
+
+// function logs and returns the precedence pairs
+// broken by a user supplied route.
+pub fn check_precedence(args: &[String], route: &[usize], data: &[ObjLookUp]) -> Vec<String> {
+    let broken = violations(route, &read_precedence(args, data), data);
+    for v in &broken {
+        error!("Precedence violated: {}", v);
+    }
+    broken
+}
+
+// function returns the required and optional stops of
+// the constraints file given by `--constraints`, none
+// without the flag.
+pub fn read_stops(args: &[String], data: &[ObjLookUp]) -> Stops {
+    match flag_value(args, "--constraints") {
+        Some(path) => from_constraints(data, &read_constraints(path)),
+        None => Stops::default(),
+    }
+}
+
+// function logs and returns the ways a user supplied
+// route breaks the stops.
+pub fn check_stops(args: &[String], route: &[usize], data: &[ObjLookUp]) -> Vec<String> {
+    let broken = stop_violations(route, &read_stops(args, data), data);
+    for v in &broken {
+        error!("Stops violated: {}", v);
+    }
+    broken
+}
+
+// function reads a route from a json file or, for a
+// csv, from the row with the given KEY.
+pub fn read_any_route(path: &str, key: &str) -> RouteFile {
+    match path.to_lowercase().ends_with(".csv") {
+        true => read_route_row(path, key),
+        false => read_route(path),
+    }
+}
+
+// function reads a solved route to travel: its lookup
+// indices and the mode of every leg. Legs without a
+// mode in the file are driven, except legs that cannot
+// be driven, which are always flown. Panics on a route
+// of fewer than two stops.
+pub fn read_legs(path: &str, data: &[ObjLookUp]) -> (Vec<usize>, Vec<Mode>) {
+    let file = read_route(path);
+    if file.route.len() < 2 {
+        let msg = "Expected a route of at least two stops";
+        error!("{:?}: {:?}: {:?}", msg, path, file.route);
+        panic!("{:?}: {:?}: {:?}", msg, path, file.route)
+    }
+    let codes: Vec<&str> = file.route.iter().map(|s| s.as_str()).collect();
+    let route = stop_indices(data, &codes);
+    let mut modes = match file.modes {
+        Some(modes) => modes,
+        None => vec![Mode::Drive; route.len() - 1],
+    };
+    force_flights(&route, &mut modes, data);
+    (route, modes)
+}
+
+// function parses "LAT,LON" into a coordinate within
+// the range of latitudes and longitudes.
+pub fn parse_point(text: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = text.split_once(',')?;
+    let lat = lat.trim().parse::<f64>().ok()?;
+    let lon = lon.trim().parse::<f64>().ok()?;
+    match lat.abs() <= 90.0 && lon.abs() <= 180.0 {
+        true => Some((lat, lon)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_a_zip_code_table_as_the_lookup() {
+        let data = look_up_source(
+            &args(&["--lookup", "codes.csv", "--zips", "43215,44114,43215"]),
+            Vec::new(),
+        );
+        let names: Vec<String> = (0..data.len()).map(|i| stop_name(&data, i)).collect();
+        assert_eq!(names, ["OH", "44114"]);
+        // the whole capitals table, both Hartford records
+        let data = look_up_source(&args(&["--lookup", "zip_codes.csv"]), Vec::new());
+        assert_eq!(data.iter().filter(|r| r.state == "CT").count(), 2);
+    }
+
+    #[test]
+    fn a_json_lookup_keeps_one_record_per_state() {
+        let data = look_up_source(&args(&["--lookup", "look_up.json"]), Vec::new());
+        assert_eq!(data.iter().filter(|r| r.state == "CT").count(), 1);
+        assert_eq!(data.len(), 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Zip code table is too large for a lookup")]
+    fn a_large_table_needs_zip_codes() {
+        look_up_source(&args(&["--lookup", "codes.csv"]), Vec::new());
+    }
+
+    #[test]
+    fn parses_a_point() {
+        assert_eq!(parse_point(" 41.59, -93.60"), Some((41.59, -93.6)));
+        assert_eq!(parse_point("LAT,LON"), None);
+        assert_eq!(parse_point("91,0"), None);
+        assert_eq!(parse_point("0,-181"), None);
+        assert_eq!(parse_point("41.59"), None);
+    }
+}
@@ -0,0 +1,71 @@
+// The compare sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_value, split_list},
+    command::{read_any_route, stamp, write_json, write_text, CYPHER_CSV},
+    compare::{compare as compare_routes, to_text},
+    lookup::{stop_indices, ObjLookUp},
+    route::RouteFile,
+};
+use log::{error, info};
+use serde_json::json;
+
+const COMPARE_TXT: &str = "compare.txt";
+const COMPARE_JSON: &str = "compare.json";
+
+// compare: show why two routes differ: the runs of
+// stops they share, the legs only one of them has,
+// per leg mile deltas and the cumulative miles.
+//   compare first [second]   route files, .json or .csv
+//                            (default: cypher.csv)
+//   --rows 0,1               KEY of the csv row of each
+//                            route (default: 0,1)
+// With a single csv, two of its rows are compared.
+pub fn compare(args: &[String], data: &[ObjLookUp]) {
+    let files: Vec<&str> = args
+        .iter()
+        .skip(2)
+        .take_while(|a| !a.starts_with("--"))
+        .map(|a| a.as_str())
+        .collect();
+    let (path_a, path_b) = match files.as_slice() {
+        [] => (CYPHER_CSV, CYPHER_CSV),
+        [one] => (*one, *one),
+        [a, b, ..] => (*a, *b),
+    };
+    let rows = split_list(flag_value(args, "--rows").unwrap_or("0,1"));
+    let (key_a, key_b) = match rows.as_slice() {
+        [a, b] => (*a, *b),
+        _ => {
+            let msg = "Expected --rows KEY,KEY";
+            error!("{:?}: {:?}", msg, rows);
+            panic!("{:?}: {:?}", msg, rows)
+        }
+    };
+    let to_indices = |file: RouteFile| -> Vec<usize> {
+        let codes: Vec<&str> = file.route.iter().map(|s| s.as_str()).collect();
+        stop_indices(data, &codes)
+    };
+    let first = to_indices(read_any_route(path_a, key_a));
+    let second = to_indices(read_any_route(path_b, key_b));
+
+    let report = compare_routes(&first, &second, data);
+    let text = to_text(&report);
+    write_text(COMPARE_TXT, &text);
+
+    let obj = json!({
+        "first_file": path_a,
+        "second_file": path_b,
+        "comparison": report,
+    });
+    write_json(COMPARE_JSON, &stamp(obj));
+    info!(
+        "Routes differ by {:?} mi with {:?} and {:?} leg(s) of their own, written to {:?} and {:?}",
+        report.delta_miles,
+        report.only_in_first.len(),
+        report.only_in_second.len(),
+        COMPARE_TXT,
+        COMPARE_JSON
+    );
+}
@@ -0,0 +1,303 @@
+pub mod command;
+
+use crate::{
+    lookup::{stop_name, ObjLookUp},
+    matrix::distance_matrix,
+};
+use serde::Serialize;
+use std::fmt::Write;
+
+// A leg found in one route only. Legs are compared
+// without direction, IA-NY is the same leg as NY-IA.
+#[derive(Serialize, Debug, Clone)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub miles: f64,
+}
+
+// The k-th leg of both routes side by side. A side is
+// None when that route has fewer legs.
+#[derive(Serialize, Debug, Clone)]
+pub struct LegDelta {
+    pub leg: usize,
+    pub first: Option<Edge>,
+    pub second: Option<Edge>,
+    pub delta: f64,
+}
+
+// Miles covered by both routes after k legs.
+#[derive(Serialize, Debug, Clone)]
+pub struct CurvePoint {
+    pub leg: usize,
+    pub first: Option<f64>,
+    pub second: Option<f64>,
+    pub delta: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Comparison {
+    pub first: Vec<String>,
+    pub second: Vec<String>,
+    pub first_miles: f64,
+    pub second_miles: f64,
+    pub delta_miles: f64,
+    // runs of two or more stops found in both routes
+    pub shared: Vec<Vec<String>>,
+    pub only_in_first: Vec<Edge>,
+    pub only_in_second: Vec<Edge>,
+    pub legs: Vec<LegDelta>,
+    pub curve: Vec<CurvePoint>,
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+fn same_leg(a: &[usize], b: &[usize]) -> bool {
+    (a[0] == b[0] && a[1] == b[1]) || (a[0] == b[1] && a[1] == b[0])
+}
+
+// function returns the maximal runs of consecutive
+// stops, two or more long, that appear in both routes
+// in the same order, longest first.
+pub fn shared_runs(first: &[usize], second: &[usize], data: &[ObjLookUp]) -> Vec<Vec<String>> {
+    let same = |i: usize, j: usize| first[i] == second[j];
+    let mut runs: Vec<Vec<String>> = Vec::new();
+    for i in 0..first.len() {
+        for j in 0..second.len() {
+            // only start where the run cannot grow left
+            if !same(i, j) || (i > 0 && j > 0 && same(i - 1, j - 1)) {
+                continue;
+            }
+            let mut len = 1;
+            while i + len < first.len() && j + len < second.len() && same(i + len, j + len) {
+                len += 1;
+            }
+            let run: Vec<String> = first[i..i + len]
+                .iter()
+                .map(|&s| stop_name(data, s))
+                .collect();
+            if len > 1 && !runs.contains(&run) {
+                runs.push(run);
+            }
+        }
+    }
+    runs.sort_by_key(|run| std::cmp::Reverse(run.len()));
+    runs
+}
+
+// function returns the legs of `route` that `other`
+// does not have.
+fn only_in(route: &[usize], other: &[usize], data: &[ObjLookUp], miles: &[Vec<f64>]) -> Vec<Edge> {
+    route
+        .windows(2)
+        .filter(|w| !other.windows(2).any(|o| same_leg(w, o)))
+        .map(|w| Edge {
+            from: stop_name(data, w[0]),
+            to: stop_name(data, w[1]),
+            miles: miles[w[0]][w[1]],
+        })
+        .collect()
+}
+
+// function compares two routes of lookup indices: the
+// runs they share, the legs only one of them has, the
+// miles of every leg side by side and the cumulative
+// miles after every leg.
+pub fn compare(first: &[usize], second: &[usize], data: &[ObjLookUp]) -> Comparison {
+    let miles = distance_matrix(data);
+    let leg = |route: &[usize], k: usize| -> Option<Edge> {
+        route.get(k + 1).map(|&to| Edge {
+            from: stop_name(data, route[k]),
+            to: stop_name(data, to),
+            miles: miles[route[k]][to],
+        })
+    };
+    let num = first.len().max(second.len()).saturating_sub(1);
+
+    let mut legs: Vec<LegDelta> = Vec::with_capacity(num);
+    let mut curve: Vec<CurvePoint> = Vec::with_capacity(num);
+    let (mut total_a, mut total_b) = (0.0, 0.0);
+    for k in 0..num {
+        let a = leg(first, k);
+        let b = leg(second, k);
+        let mi_a = a.as_ref().map_or(0.0, |e| e.miles);
+        let mi_b = b.as_ref().map_or(0.0, |e| e.miles);
+        total_a += mi_a;
+        total_b += mi_b;
+        let cum_a = a.as_ref().map(|_| round1(total_a));
+        let cum_b = b.as_ref().map(|_| round1(total_b));
+        curve.push(CurvePoint {
+            leg: k + 1,
+            first: cum_a,
+            second: cum_b,
+            delta: match (cum_a, cum_b) {
+                (Some(x), Some(y)) => Some(round1(y - x)),
+                _ => None,
+            },
+        });
+        legs.push(LegDelta {
+            leg: k + 1,
+            first: a,
+            second: b,
+            delta: round1(mi_b - mi_a),
+        });
+    }
+
+    let states =
+        |route: &[usize]| -> Vec<String> { route.iter().map(|&i| stop_name(data, i)).collect() };
+    Comparison {
+        first: states(first),
+        second: states(second),
+        first_miles: round1(total_a),
+        second_miles: round1(total_b),
+        delta_miles: round1(total_b - total_a),
+        shared: shared_runs(first, second, data),
+        only_in_first: only_in(first, second, data, &miles),
+        only_in_second: only_in(second, first, data, &miles),
+        legs,
+        curve,
+    }
+}
+
+// function renders a comparison as plain text, miles
+// with one decimal as in cypher.csv.
+pub fn to_text(c: &Comparison) -> String {
+    let mut out = String::new();
+    let edge = |e: &Option<Edge>| match e {
+        Some(e) => format!("{}-{} {:.1}", e.from, e.to, e.miles),
+        None => "-".to_owned(),
+    };
+    let value = |v: Option<f64>| match v {
+        Some(v) => format!("{:.1}", v),
+        None => "-".to_owned(),
+    };
+    let _ = writeln!(
+        out,
+        "FIRST:  {} ({:.1} mi)",
+        c.first.join(","),
+        c.first_miles
+    );
+    let _ = writeln!(
+        out,
+        "SECOND: {} ({:.1} mi)",
+        c.second.join(","),
+        c.second_miles
+    );
+    let _ = writeln!(out, "DELTA:  {:+.1} mi", c.delta_miles);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "SHARED RUNS:");
+    if c.shared.is_empty() {
+        let _ = writeln!(out, "  none");
+    }
+    for run in &c.shared {
+        let _ = writeln!(out, "  {}", run.join(","));
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "LEGS ONLY IN FIRST:");
+    for e in &c.only_in_first {
+        let _ = writeln!(out, "  {}-{} {:.1}", e.from, e.to, e.miles);
+    }
+    let _ = writeln!(out, "LEGS ONLY IN SECOND:");
+    for e in &c.only_in_second {
+        let _ = writeln!(out, "  {}-{} {:.1}", e.from, e.to, e.miles);
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "{:>4}  {:<16} {:<16} {:>8}  {:>8} {:>8} {:>8}",
+        "LEG", "FIRST", "SECOND", "DELTA", "CUM_1", "CUM_2", "CUM_DIFF"
+    );
+    for (l, p) in c.legs.iter().zip(&c.curve) {
+        let _ = writeln!(
+            out,
+            "{:>4}  {:<16} {:<16} {:>+8.1}  {:>8} {:>8} {:>8}",
+            l.leg,
+            edge(&l.first),
+            edge(&l.second),
+            l.delta,
+            value(p.first),
+            value(p.second),
+            value(p.delta)
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{read_look_up, state_index};
+
+    fn route(data: &[ObjLookUp], states: &[&str]) -> Vec<usize> {
+        states
+            .iter()
+            .map(|s| state_index(data, s).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_longest_shared_runs_first() {
+        let data = read_look_up("look_up.json");
+        let a = route(&data, &["IA", "NY", "WI", "TX", "CA", "DC"]);
+        let b = route(&data, &["IA", "TX", "CA", "IA", "NY", "WI", "DC"]);
+        assert_eq!(
+            shared_runs(&a, &b, &data),
+            [vec!["IA", "NY", "WI"], vec!["TX", "CA"]]
+        );
+        assert!(shared_runs(&a, &route(&data, &["DC", "CA"]), &data).is_empty());
+    }
+
+    #[test]
+    fn a_leg_is_the_same_in_either_direction() {
+        let data = read_look_up("look_up.json");
+        let miles = distance_matrix(&data);
+        let a = route(&data, &["IA", "NY", "WI", "DC"]);
+        let b = route(&data, &["IA", "WI", "NY", "DC"]);
+        let only: Vec<(String, String)> = only_in(&a, &b, &data, &miles)
+            .into_iter()
+            .map(|e| (e.from, e.to))
+            .collect();
+        assert_eq!(
+            only,
+            [
+                ("IA".to_owned(), "NY".to_owned()),
+                ("WI".to_owned(), "DC".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_routes_of_different_lengths() {
+        let data = read_look_up("look_up.json");
+        let miles = distance_matrix(&data);
+        let a = route(&data, &["IA", "NY", "DC"]);
+        let b = route(&data, &["IA", "WI", "NY", "DC"]);
+        let c = compare(&a, &b, &data);
+        assert_eq!(c.legs.len(), 3);
+        assert!(c.legs[2].first.is_none());
+        assert_eq!(c.curve[2].delta, None);
+        assert_eq!(c.curve[2].second, Some(c.second_miles));
+        let total: f64 = a.windows(2).map(|w| miles[w[0]][w[1]]).sum();
+        assert_eq!(c.first_miles, round1(total));
+        assert_eq!(c.delta_miles, round1(c.second_miles - c.first_miles));
+        assert_eq!(c.shared, [vec!["NY", "DC"]]);
+
+        let text = to_text(&c);
+        assert!(text.starts_with("FIRST:  IA,NY,DC ("));
+        assert!(text.contains("SHARED RUNS:\n  NY,DC\n"));
+        assert!(text.contains("LEGS ONLY IN FIRST:\n  IA-NY "));
+    }
+
+    #[test]
+    fn a_route_compared_with_itself_has_no_difference() {
+        let data = read_look_up("look_up.json");
+        let a = route(&data, &["IA", "NY", "WI", "DC"]);
+        let c = compare(&a, &a, &data);
+        assert_eq!(c.delta_miles, 0.0);
+        assert!(c.only_in_first.is_empty() && c.only_in_second.is_empty());
+        assert_eq!(c.shared, [vec!["IA", "NY", "WI", "DC"]]);
+        assert!(c.legs.iter().all(|l| l.delta == 0.0));
+    }
+}
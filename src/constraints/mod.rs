@@ -0,0 +1,94 @@
+use crate::precedence::Tier;
+use log::{error, info};
+use serde::Deserialize;
+use std::{collections::HashMap, fs};
+
+// A campaign event window for a state, e.g. "must be
+// in NH between Feb 1 and Feb 5". Times are local to
+// the state's capital, either "YYYY-MM-DDTHH:MM" or a
+// bare date "YYYY-MM-DD" (a close date means the end
+// of that day).
+#[derive(Deserialize, Debug, Clone)]
+pub struct WindowSpec {
+    pub state: String,
+    pub open: Option<String>,
+    pub close: Option<String>,
+    // hours spent campaigning, overrides service_hours
+    pub service_hours: Option<f64>,
+}
+
+// Contents of constraints.json.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Constraints {
+    // local time at the first capital, "YYYY-MM-DDTHH:MM"
+    pub start_time: Option<String>,
+    // default hours spent in each capital
+    pub service_hours: Option<f64>,
+    #[serde(default)]
+    pub windows: Vec<WindowSpec>,
+    // ordered groups, e.g. [["IA","NH","NV","SC"],["TX","CA"]]
+    // or pairs ["IA","NH"]; see precedence::from_groups
+    #[serde(default)]
+    pub precedence: Vec<Vec<Tier>>,
+    // states every route must visit, e.g. a hub ["DC"]
+    #[serde(default)]
+    pub required: Vec<String>,
+    // states a route may skip
+    #[serde(default)]
+    pub optional: Vec<String>,
+    // visits allowed per state, e.g. {"OH": 2, "DC": 2};
+    // a required state is visited that many times
+    #[serde(default)]
+    pub visits: HashMap<String, usize>,
+}
+
+// function reads and deserializes a constraints file.
+pub fn read_constraints(path: &str) -> Constraints {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            let msg = "Error opening constraints file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    match serde_json::from_str(&contents) {
+        Ok(constraints) => {
+            info!("Success deserialized {:?}", path);
+            constraints
+        }
+        Err(e) => {
+            let msg = "Failed to deserialize constraints file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_bundled_constraints() {
+        let cons = read_constraints("constraints.json");
+        assert_eq!(cons.start_time.as_deref(), Some("2024-01-15T08:00"));
+        assert_eq!(cons.windows.len(), 5);
+        assert_eq!(cons.windows[0].service_hours, Some(6.0));
+        assert_eq!(cons.windows[3].open, None);
+        assert_eq!(cons.precedence.len(), 2);
+        assert_eq!(cons.precedence[0][0].states(), ["IA", "NH", "NV", "SC"]);
+        assert_eq!(cons.precedence[1][1].states(), ["WI"]);
+        assert!(cons.required.is_empty() && cons.visits.is_empty());
+    }
+
+    #[test]
+    fn every_entry_is_optional() {
+        let cons: Constraints = serde_json::from_str("{}").unwrap();
+        assert!(cons.start_time.is_none() && cons.windows.is_empty());
+        let cons: Constraints =
+            serde_json::from_str(r#"{"required": ["DC"], "visits": {"DC": 2}}"#).unwrap();
+        assert_eq!(cons.required, ["DC"]);
+        assert_eq!(cons.visits["DC"], 2);
+    }
+}
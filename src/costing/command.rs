@@ -0,0 +1,153 @@
+// The cost sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::parse_flag,
+    command::{
+        check_precedence, check_stops, modal_params, positional, read_airport_table, read_legs,
+        write_outputs, MODAL_JSON,
+    },
+    costing::{cost_route, CostParams},
+    lookup::{stop_name, ObjLookUp},
+    weights::{per_100_miles, route_weight, stop_weights},
+};
+use log::{error, info};
+use serde_json::json;
+
+const COST_CSV: &str = "cost.csv";
+const COST_JSON: &str = "cost.json";
+
+// cost: budget a solved route leg by leg.
+//   cost [route.json]   route file (default: modal.json)
+//   --mpg, --fuel-price, --max-hours, --lodging, --per-diem,
+//   --airfare-base, --airfare-per-mile, --drive-mph, --fly-mph,
+//   --airport-hours, --switch-hours
+//   --airports file.csv      airport table (default: airports.csv)
+//   --constraints file.json  report broken precedence groups
+//                            and stops
+// Legs without a mode in the route file are driven,
+// except legs to or from AK, HI or a territory, which
+// are always flown. Flights are timed as in `modal`.
+pub fn cost(args: &[String], data: &[ObjLookUp]) {
+    let (route, modes) = read_legs(positional(args, MODAL_JSON), data);
+
+    let defaults = CostParams::default();
+    let params = CostParams {
+        mpg: parse_flag(args, "--mpg", defaults.mpg),
+        fuel_price: parse_flag(args, "--fuel-price", defaults.fuel_price),
+        max_hours_per_day: parse_flag(args, "--max-hours", defaults.max_hours_per_day),
+        lodging_per_night: parse_flag(args, "--lodging", defaults.lodging_per_night),
+        per_diem: parse_flag(args, "--per-diem", defaults.per_diem),
+        airfare_base: parse_flag(args, "--airfare-base", defaults.airfare_base),
+        airfare_per_mile: parse_flag(args, "--airfare-per-mile", defaults.airfare_per_mile),
+        fly_co2_per_mile: defaults.fly_co2_per_mile,
+    };
+    // travel days divide by the hours per day, fuel by mpg
+    let positive = |v: f64| v > 0.0 && v.is_finite();
+    if !positive(params.max_hours_per_day) || !positive(params.mpg) {
+        let msg = "Invalid cost limits";
+        error!(
+            "{:?}: max-hours {:?} mpg {:?}",
+            msg, params.max_hours_per_day, params.mpg
+        );
+        panic!(
+            "{:?}: max-hours {:?} mpg {:?}",
+            msg, params.max_hours_per_day, params.mpg
+        )
+    }
+    let airports = read_airport_table(args);
+    let report = cost_route(
+        &route,
+        &modes,
+        data,
+        &airports,
+        &modal_params(args),
+        &params,
+    );
+    let broken = check_precedence(args, &route, data);
+    let missed = check_stops(args, &route, data);
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "LEG",
+        "FROM",
+        "TO",
+        "MODE",
+        "MILES",
+        "HOURS",
+        "DAYS",
+        "FUEL",
+        "AIRFARE",
+        "CO2_KG",
+        "LODGING",
+        "PER_DIEM",
+        "TOTAL",
+        "ELECTORAL_VOTES",
+        "POPULATION",
+        "VOTES_PER_100_MILES",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    // weights first reached at the end of each leg
+    let weights = stop_weights(&route, data);
+    let reached = route_weight(&route, data, report.miles);
+    for (k, leg) in report.legs.iter().enumerate() {
+        let to = weights[k + 1];
+        rows.push(vec![
+            leg.leg.to_string(),
+            leg.from.clone(),
+            leg.to.clone(),
+            leg.mode.as_str().to_owned(),
+            format!("{:.1}", leg.miles),
+            format!("{:.1}", leg.hours),
+            String::new(),
+            format!("{:.2}", leg.fuel),
+            format!("{:.2}", leg.airfare),
+            format!("{:.1}", leg.co2_kg),
+            String::new(),
+            String::new(),
+            String::new(),
+            to.electoral_votes.to_string(),
+            to.population.to_string(),
+            format!("{:.1}", per_100_miles(to.electoral_votes as f64, leg.miles)),
+        ]);
+    }
+    rows.push(vec![
+        "TOTAL".to_owned(),
+        String::new(),
+        String::new(),
+        String::new(),
+        format!("{:.1}", report.miles),
+        format!("{:.1}", report.hours),
+        report.days.to_string(),
+        format!("{:.2}", report.fuel),
+        format!("{:.2}", report.airfare),
+        format!("{:.1}", report.co2_kg),
+        format!("{:.2}", report.lodging),
+        format!("{:.2}", report.per_diem),
+        format!("{:.2}", report.total),
+        reached.electoral_votes.to_string(),
+        reached.population.to_string(),
+        format!("{:.1}", reached.votes_per_100_miles),
+    ]);
+
+    // same header fields as output.json plus the report
+    let first = route[0];
+    let last = route[route.len() - 1];
+    let obj = json!({
+        "beginning_state": stop_name(data, first),
+        "beginning_zipcode": data[first].zip_code,
+        "ending_state": stop_name(data, last),
+        "ending_zipcode": data[last].zip_code,
+        "miles_between": report.miles.to_string(),
+        "precedence_violations": broken,
+        "stop_violations": missed,
+        "weights": reached,
+        "report": report,
+    });
+    write_outputs(COST_CSV, &rows, COST_JSON, obj);
+    info!(
+        "Route cost: ${:.2} over {:?} day(s) written to {:?} and {:?}",
+        report.total, report.days, COST_CSV, COST_JSON
+    );
+}
@@ -0,0 +1,274 @@
+pub mod command;
+
+use crate::{
+    lookup::{stop_name, ObjLookUp},
+    matrix::distance_matrix,
+    modal::{flight_hours, nearest_airports, Airport, ModalParams, Mode},
+};
+use serde::Serialize;
+
+// kilograms of CO2 from burning one gallon of gasoline
+pub const CO2_KG_PER_GALLON: f64 = 8.887;
+
+// Prices and limits used to budget a route.
+pub struct CostParams {
+    pub mpg: f64,
+    pub fuel_price: f64,
+    pub max_hours_per_day: f64,
+    pub lodging_per_night: f64,
+    pub per_diem: f64,
+    pub airfare_base: f64,
+    pub airfare_per_mile: f64,
+    // kilograms of CO2 per passenger mile flown
+    pub fly_co2_per_mile: f64,
+}
+
+impl Default for CostParams {
+    fn default() -> CostParams {
+        CostParams {
+            mpg: 25.0,
+            fuel_price: 3.50,
+            max_hours_per_day: 10.0,
+            lodging_per_night: 150.0,
+            per_diem: 60.0,
+            airfare_base: 75.0,
+            airfare_per_mile: 0.12,
+            fly_co2_per_mile: 0.2,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LegCost {
+    pub leg: usize,
+    pub from: String,
+    pub to: String,
+    pub mode: Mode,
+    pub miles: f64,
+    pub hours: f64,
+    pub fuel: f64,
+    pub airfare: f64,
+    pub co2_kg: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CostReport {
+    pub legs: Vec<LegCost>,
+    pub miles: f64,
+    pub hours: f64,
+    pub days: usize,
+    pub nights: usize,
+    pub fuel: f64,
+    pub airfare: f64,
+    pub lodging: f64,
+    pub per_diem: f64,
+    pub co2_kg: f64,
+    pub total: f64,
+}
+
+fn round(v: f64, places: i32) -> f64 {
+    let f = 10f64.powi(places);
+    (v * f).round() / f
+}
+
+// function returns the hours of the leg from lookup
+// record i to j. Driving uses road miles, flying is
+// timed as modal::flight_hours; airport is the
+// nearest_airports of the lookup.
+pub fn leg_hours(
+    i: usize,
+    j: usize,
+    mode: Mode,
+    miles: &[Vec<f64>],
+    airport: &[(usize, f64)],
+    airports: &[Airport],
+    modal: &ModalParams,
+) -> f64 {
+    match mode {
+        Mode::Drive => miles[i][j] * modal.road_factor / modal.drive_mph,
+        Mode::Fly => flight_hours(i, j, airport, airports, modal),
+    }
+}
+
+// function counts travel days for a list of leg hours.
+// A new day starts when the next leg would push the
+// day past max_hours; a leg longer than max_hours
+// spans several days on its own.
+pub fn travel_days(hours: &[f64], max_hours: f64) -> usize {
+    let mut days = 1;
+    let mut today = 0.0;
+    for &h in hours {
+        if today > 0.0 && today + h > max_hours {
+            days += 1;
+            today = 0.0;
+        }
+        let extra = ((today + h) / max_hours).ceil() as usize;
+        if extra > 1 {
+            days += extra - 1;
+            today = (today + h) - (extra - 1) as f64 * max_hours;
+        } else {
+            today += h;
+        }
+    }
+    days
+}
+
+// function budgets a solved route: fuel for the driven
+// legs, airfare for the flown legs, one night of lodging
+// between travel days, per-diem per day and CO2 per mode.
+// A leg that changes the mode adds the switch hours, as
+// in modal::best_modes.
+pub fn cost_route(
+    route: &[usize],
+    modes: &[Mode],
+    data: &[ObjLookUp],
+    airports: &[Airport],
+    modal: &ModalParams,
+    params: &CostParams,
+) -> CostReport {
+    let miles = distance_matrix(data);
+    let airport = nearest_airports(data, airports);
+    let mut legs: Vec<LegCost> = Vec::with_capacity(modes.len());
+
+    for (k, (w, &mode)) in route.windows(2).zip(modes).enumerate() {
+        let mi = miles[w[0]][w[1]];
+        let switch = match k > 0 && modes[k - 1] != mode {
+            true => modal.switch_hours,
+            false => 0.0,
+        };
+        let hours = switch + leg_hours(w[0], w[1], mode, &miles, &airport, airports, modal);
+        let (fuel, airfare, co2) = match mode {
+            Mode::Drive => {
+                let gallons = mi * modal.road_factor / params.mpg;
+                (
+                    gallons * params.fuel_price,
+                    0.0,
+                    gallons * CO2_KG_PER_GALLON,
+                )
+            }
+            Mode::Fly => (
+                0.0,
+                params.airfare_base + mi * params.airfare_per_mile,
+                mi * params.fly_co2_per_mile,
+            ),
+        };
+        legs.push(LegCost {
+            leg: k + 1,
+            from: stop_name(data, w[0]),
+            to: stop_name(data, w[1]),
+            mode,
+            miles: mi,
+            hours: round(hours, 1),
+            fuel: round(fuel, 2),
+            airfare: round(airfare, 2),
+            co2_kg: round(co2, 1),
+        });
+    }
+
+    let hours: Vec<f64> = legs.iter().map(|l| l.hours).collect();
+    let days = travel_days(&hours, params.max_hours_per_day);
+    let nights = days - 1;
+    let fuel: f64 = legs.iter().map(|l| l.fuel).sum();
+    let airfare: f64 = legs.iter().map(|l| l.airfare).sum();
+    let lodging = nights as f64 * params.lodging_per_night;
+    let per_diem = days as f64 * params.per_diem;
+
+    CostReport {
+        miles: round(legs.iter().map(|l| l.miles).sum(), 1),
+        hours: round(hours.iter().sum(), 1),
+        days,
+        nights,
+        fuel: round(fuel, 2),
+        airfare: round(airfare, 2),
+        lodging: round(lodging, 2),
+        per_diem: round(per_diem, 2),
+        co2_kg: round(legs.iter().map(|l| l.co2_kg).sum(), 1),
+        total: round(fuel + airfare + lodging + per_diem, 2),
+        legs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lookup::{read_look_up, state_index},
+        modal::{build_model, read_airports},
+    };
+
+    #[test]
+    fn a_leg_longer_than_a_day_spans_several_days() {
+        assert_eq!(travel_days(&[], 10.0), 1);
+        assert_eq!(travel_days(&[4.0, 4.0], 10.0), 1);
+        assert_eq!(travel_days(&[4.0, 4.0, 4.0], 10.0), 2);
+        assert_eq!(travel_days(&[10.0, 10.0], 10.0), 2);
+        assert_eq!(travel_days(&[25.0], 10.0), 3);
+        // the 12 hour leg starts a new day and runs
+        // into a third
+        assert_eq!(travel_days(&[9.0, 12.0], 10.0), 3);
+        assert_eq!(travel_days(&[25.0, 4.0], 10.0), 3);
+        assert_eq!(travel_days(&[25.0, 6.0], 10.0), 4);
+    }
+
+    #[test]
+    fn leg_hours_match_the_modal_model() {
+        let data = read_look_up("look_up.json");
+        let airports = read_airports("airports.csv");
+        let modal = ModalParams::default();
+        let model = build_model(&data, &airports, &modal);
+        let miles = distance_matrix(&data);
+        let airport = nearest_airports(&data, &airports);
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let (ia, il, hi) = (at("IA"), at("IL"), at("HI"));
+        let hours = |i, j, mode| leg_hours(i, j, mode, &miles, &airport, &airports, &modal);
+        assert_eq!(hours(ia, il, Mode::Drive), miles[ia][il] * 1.2 / 55.0);
+        assert_eq!(hours(ia, hi, Mode::Fly), model.fly[ia][hi]);
+        // the drives to and from the airports count
+        assert!(model.fly[ia][hi] > modal.airport_hours + miles[ia][hi] / modal.fly_mph);
+    }
+
+    #[test]
+    fn cost_adds_up_the_legs() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let route = [at("IA"), at("IL"), at("NY")];
+        let params = CostParams::default();
+        let modal = ModalParams::default();
+        let airports = read_airports("airports.csv");
+        let report = cost_route(
+            &route,
+            &[Mode::Drive, Mode::Fly],
+            &data,
+            &airports,
+            &modal,
+            &params,
+        );
+        assert_eq!(report.legs.len(), 2);
+        let (drive, fly) = (&report.legs[0], &report.legs[1]);
+        assert_eq!((drive.from.as_str(), fly.to.as_str()), ("IA", "NY"));
+        assert_eq!(drive.airfare, 0.0);
+        assert_eq!(fly.fuel, 0.0);
+        assert_eq!(
+            drive.fuel,
+            round(drive.miles * 1.2 / params.mpg * params.fuel_price, 2)
+        );
+        assert_eq!(
+            fly.airfare,
+            round(params.airfare_base + fly.miles * params.airfare_per_mile, 2)
+        );
+        // switching to the flight costs the switch hours
+        let model = build_model(&data, &airports, &modal);
+        assert_eq!(
+            fly.hours,
+            round(modal.switch_hours + model.fly[route[1]][route[2]], 1)
+        );
+        assert_eq!(report.nights, report.days - 1);
+        assert_eq!(
+            report.total,
+            round(
+                report.fuel + report.airfare + report.lodging + report.per_diem,
+                2
+            )
+        );
+    }
+}
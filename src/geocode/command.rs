@@ -0,0 +1,201 @@
+// The geocode and reverse sub commands of the binary;
+// the flags and output files they share are in
+// crate::command.
+
+use crate::{
+    cli::flag_value,
+    command::{parse_point, write_outputs, ZIP_TABLE},
+    geocode::{geocode as geocode_address, mark_shared_zips, reverse as reverse_geocode},
+    index::LookupIndex,
+    zips::read_zip_csv,
+};
+use log::{error, info, warn};
+use serde_json::json;
+use std::fs;
+
+const GEOCODE_CSV: &str = "geocode.csv";
+const GEOCODE_JSON: &str = "geocode.json";
+const REVERSE_CSV: &str = "reverse.csv";
+const REVERSE_JSON: &str = "reverse.json";
+
+// geocode: resolve venue addresses to the zip code
+// centroids of a zip code table, offline.
+//   geocode "Des Moines, IA" "1 Main St, Albany, NY 12224" ...
+//   --file venues.txt          one address per line
+//   --table file.csv           zip code table (default: codes.csv)
+// geocode.csv has the columns of the zip code table
+// first, so `--lookup geocode.csv` makes the venues the
+// stops of an instance, several per state if need be;
+// venues at the same zip code are one stop.
+pub fn geocode(args: &[String]) {
+    let table = flag_value(args, "--table").unwrap_or(ZIP_TABLE);
+    let mut inputs: Vec<String> = args
+        .iter()
+        .skip(2)
+        .take_while(|a| !a.starts_with("--"))
+        .cloned()
+        .collect();
+    if let Some(path) = flag_value(args, "--file") {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                let msg = "Error reading file";
+                error!("{:?}: {:?}: {:?}", msg, path, e);
+                panic!("{:?}: {:?}: {:?}", msg, path, e)
+            }
+        };
+        inputs.extend(
+            text.lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(|l| l.to_owned()),
+        );
+    }
+    if inputs.is_empty() {
+        let msg = "Expected addresses or --file";
+        error!("{:?}: {:?}", msg, args);
+        panic!("{:?}: {:?}", msg, args)
+    }
+
+    let index = LookupIndex::new(read_zip_csv(table));
+    let mut results: Vec<_> = inputs.iter().map(|a| geocode_address(&index, a)).collect();
+    mark_shared_zips(&mut results);
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "ZipCode",
+        "City",
+        "State",
+        "Latitude",
+        "Longitude",
+        "Classification",
+        "Population",
+        "INPUT",
+        "PRECISION",
+        "CONFIDENCE",
+        "AMBIGUOUS",
+        "WARNINGS",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    for g in &results {
+        let mut row: Vec<String> = match &g.best {
+            Some(b) => {
+                let rec = index.zip(&b.zip_code);
+                vec![
+                    b.zip_code.clone(),
+                    b.city.clone(),
+                    b.state.clone(),
+                    format!("{:.4}", b.latitude),
+                    format!("{:.4}", b.longitude),
+                    rec.map_or(String::new(), |r| r.classification.clone()),
+                    rec.map_or(0, |r| r.population).to_string(),
+                ]
+            }
+            // left blank, --lookup skips the row
+            None => vec![String::new(); 7],
+        };
+        row.extend(vec![
+            g.input.clone(),
+            g.precision.as_str().to_owned(),
+            format!("{:.2}", g.confidence),
+            g.ambiguous.to_string(),
+            g.warnings.join("; "),
+        ]);
+        rows.push(row);
+    }
+
+    let obj = json!({
+        "table": table,
+        "geocodes": results,
+    });
+    write_outputs(GEOCODE_CSV, &rows, GEOCODE_JSON, obj);
+    info!(
+        "Geocoded {:?} of {:?} address(es), {:?} ambiguous, written to {:?} and {:?}",
+        results.iter().filter(|g| g.best.is_some()).count(),
+        results.len(),
+        results.iter().filter(|g| g.ambiguous).count(),
+        GEOCODE_CSV,
+        GEOCODE_JSON
+    );
+}
+
+// reverse: find the zip code, city and state nearest to
+// coordinates, such as the points of a GPS trace.
+//   reverse 41.59,-93.60 42.65,-73.75 ...
+//   --file trace.csv           one LAT,LON per line; lines
+//                              that do not parse, such as
+//                              a header, are skipped
+//   --table file.csv           zip code table (default: codes.csv)
+pub fn reverse(args: &[String]) {
+    let table = flag_value(args, "--table").unwrap_or(ZIP_TABLE);
+    let mut inputs: Vec<String> = args
+        .iter()
+        .skip(2)
+        .take_while(|a| !a.starts_with("--"))
+        .cloned()
+        .collect();
+    if let Some(path) = flag_value(args, "--file") {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                let msg = "Error reading file";
+                error!("{:?}: {:?}: {:?}", msg, path, e);
+                panic!("{:?}: {:?}: {:?}", msg, path, e)
+            }
+        };
+        inputs.extend(text.lines().map(|l| l.to_owned()));
+    }
+    let mut points: Vec<(f64, f64)> = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        match parse_point(input) {
+            Some(point) => points.push(point),
+            None => warn!("Skipped coordinate {:?}, expected LAT,LON", input),
+        }
+    }
+    if points.is_empty() {
+        let msg = "Expected LAT,LON coordinates or --file";
+        error!("{:?}: {:?}", msg, args);
+        panic!("{:?}: {:?}", msg, args)
+    }
+
+    let index = LookupIndex::new(read_zip_csv(table));
+    let results: Vec<_> = points
+        .iter()
+        .filter_map(|&(lat, lon)| reverse_geocode(&index, lat, lon))
+        .collect();
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "LATITUDE",
+        "LONGITUDE",
+        "ZIP_CODE",
+        "CITY",
+        "STATE",
+        "MILES",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    for r in &results {
+        rows.push(vec![
+            r.latitude.to_string(),
+            r.longitude.to_string(),
+            r.zip_code.clone(),
+            r.city.clone(),
+            r.state.clone(),
+            format!("{:.1}", r.miles),
+        ]);
+    }
+
+    let obj = json!({
+        "table": table,
+        "points": results,
+    });
+    write_outputs(REVERSE_CSV, &rows, REVERSE_JSON, obj);
+    info!(
+        "Reverse geocoded {:?} point(s), written to {:?} and {:?}",
+        results.len(),
+        REVERSE_CSV,
+        REVERSE_JSON
+    );
+}
@@ -0,0 +1,541 @@
+pub mod command;
+
+use crate::index::{edit_distance, CityMatch, LookupIndex};
+use crate::zips::ZipRecord;
+use log::{info, warn};
+use serde::Serialize;
+
+// How closely a geocoded point locates the venue: the
+// centroid of its zip code or the mean of the zip code
+// centroids of its city.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    Zip,
+    City,
+    None,
+}
+
+impl Precision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Precision::Zip => "ZIP",
+            Precision::City => "CITY",
+            Precision::None => "NONE",
+        }
+    }
+}
+
+// The parts of an address string. The street is kept
+// but not used, coordinates are zip code level.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Address {
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+}
+
+// A place an address may refer to.
+#[derive(Serialize, Debug, Clone)]
+pub struct Candidate {
+    pub city: String,
+    pub state: String,
+    pub zip_code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub zip_codes: usize,
+    pub edits: usize,
+    pub confidence: f64,
+}
+
+// The result of geocoding one address. `confidence`
+// runs from 0 (not found) to 1 (zip code, city and
+// state all agree); `ambiguous` is set when several
+// places fit the address equally well, the first
+// candidate is then the most populous of them.
+#[derive(Serialize, Debug, Clone)]
+pub struct Geocode {
+    pub input: String,
+    pub address: Address,
+    pub best: Option<Candidate>,
+    pub precision: Precision,
+    pub confidence: f64,
+    pub ambiguous: bool,
+    pub candidates: Vec<Candidate>,
+    pub warnings: Vec<String>,
+}
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+fn is_zip(token: &str) -> bool {
+    let digits = token.split('-').next().unwrap_or("");
+    digits.len() == 5 && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_state(token: &str) -> bool {
+    token.len() == 2 && token.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+// function splits "city, ST", "city ST ZIP" or "street,
+// city, ST ZIP" into its parts. The state and zip code
+// are read from the end of the last comma separated
+// part; whatever is left of it, or else the part before
+// it, is the city.
+pub fn parse_address(input: &str) -> Address {
+    let mut parts: Vec<String> = input
+        .split(',')
+        .map(|p| {
+            p.replace('.', " ")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|p| !p.is_empty())
+        .collect();
+    let mut address = Address::default();
+    let last = match parts.pop() {
+        Some(last) => last,
+        None => return address,
+    };
+    let mut tokens: Vec<&str> = last.split_whitespace().collect();
+    if matches!(tokens.last(), Some(t) if is_zip(t)) {
+        address.zip_code = tokens.pop().map(|t| t[..5].to_owned());
+    }
+    // a lone word is a city, not a state
+    if (tokens.len() > 1 || !parts.is_empty()) && matches!(tokens.last(), Some(t) if is_state(t)) {
+        address.state = tokens.pop().map(|t| t.to_uppercase());
+    }
+    address.city = match tokens.is_empty() {
+        true => parts.pop(),
+        false => Some(tokens.join(" ")),
+    }
+    .map(|c| c.to_uppercase());
+    if !parts.is_empty() {
+        address.street = Some(parts.join(", "));
+    }
+    address
+}
+
+// function expands a leading ST, FT or MT the way the
+// zip code table spells city names.
+fn expand_city(city: &str) -> Option<String> {
+    let (first, rest) = city.split_once(' ')?;
+    let long = match first {
+        "ST" => "SAINT",
+        "FT" => "FORT",
+        "MT" => "MOUNT",
+        _ => return None,
+    };
+    Some(format!("{} {}", long, rest))
+}
+
+// function groups zip code matches by city and state
+// into candidates at the mean of their coordinates,
+// represented by their most populous zip code.
+fn group(found: &[CityMatch]) -> Vec<Candidate> {
+    let mut groups: Vec<Vec<&ZipRecord>> = Vec::new();
+    let mut edits: Vec<usize> = Vec::new();
+    for m in found.iter().filter(|m| m.record.located()) {
+        let key = (&m.record.city, &m.record.state);
+        match groups.iter().position(|g| (&g[0].city, &g[0].state) == key) {
+            Some(k) => groups[k].push(m.record),
+            None => {
+                groups.push(vec![m.record]);
+                edits.push(m.edits);
+            }
+        }
+    }
+    let mut candidates: Vec<(u64, Candidate)> = groups
+        .iter()
+        .zip(edits)
+        .map(|(g, edits)| {
+            let n = g.len() as f64;
+            let rep = g.iter().max_by_key(|r| r.population).unwrap_or(&g[0]);
+            let population: u64 = g.iter().map(|r| r.population).sum();
+            (
+                population,
+                Candidate {
+                    city: rep.city.clone(),
+                    state: rep.state.clone(),
+                    zip_code: rep.zip_code.clone(),
+                    latitude: g.iter().map(|r| r.latitude).sum::<f64>() / n,
+                    longitude: g.iter().map(|r| r.longitude).sum::<f64>() / n,
+                    zip_codes: g.len(),
+                    edits,
+                    confidence: city_confidence(edits),
+                },
+            )
+        })
+        .collect();
+    // best spelled first, then the larger place
+    candidates.sort_by(|a, b| a.1.edits.cmp(&b.1.edits).then(b.0.cmp(&a.0)));
+    candidates.into_iter().map(|(_, c)| c).collect()
+}
+
+// A city found by name is less precise than a zip code,
+// and every letter that had to be changed costs more.
+fn city_confidence(edits: usize) -> f64 {
+    (0.8 - 0.15 * edits as f64).max(0.2)
+}
+
+fn from_record(rec: &ZipRecord, confidence: f64) -> Candidate {
+    Candidate {
+        city: rec.city.clone(),
+        state: rec.state.clone(),
+        zip_code: rec.zip_code.clone(),
+        latitude: rec.latitude,
+        longitude: rec.longitude,
+        zip_codes: 1,
+        edits: 0,
+        confidence,
+    }
+}
+
+// function resolves an address by its zip code. The
+// confidence drops when the city or state given do not
+// agree with the zip code table.
+fn by_zip(index: &LookupIndex, address: &Address, warnings: &mut Vec<String>) -> Option<Candidate> {
+    let zip = address.zip_code.as_ref()?;
+    let rec = match index.zip(zip) {
+        Some(rec) => rec,
+        None => {
+            warnings.push(format!("zip code {} is not in the table", zip));
+            return None;
+        }
+    };
+    if !rec.located() {
+        warnings.push(format!("zip code {} has no coordinates", zip));
+        return None;
+    }
+    let mut confidence: f64 = 1.0;
+    if let Some(state) = &address.state {
+        if *state != rec.state {
+            warnings.push(format!(
+                "zip code {} is in {}, not {}",
+                zip, rec.state, state
+            ));
+            confidence -= 0.4;
+        }
+    }
+    if let Some(city) = &address.city {
+        let edits = edit_distance(city, &rec.city);
+        let expanded = expand_city(city).as_ref() == Some(&rec.city);
+        if edits > 0 && !expanded {
+            warnings.push(format!("zip code {} is in {}, not {}", zip, rec.city, city));
+            confidence -= match edits <= (city.chars().count() / 4).max(1) {
+                true => 0.1,
+                false => 0.3,
+            };
+        }
+    }
+    Some(from_record(rec, round2(confidence)))
+}
+
+// function resolves an address by city and, if given,
+// state.
+fn by_city(index: &LookupIndex, address: &Address) -> Vec<Candidate> {
+    let city = match &address.city {
+        Some(city) => city,
+        None => return Vec::new(),
+    };
+    let state = address.state.as_deref();
+    let mut found = index.city(city, state);
+    let exact = match found.first() {
+        Some(m) => m.edits == 0,
+        None => false,
+    };
+    if !exact {
+        if let Some(long) = expand_city(city) {
+            let expanded = index.city(&long, state);
+            if matches!(expanded.first(), Some(m) if m.edits == 0) {
+                found = expanded;
+            }
+        }
+    }
+    group(&found)
+}
+
+// The zip code closest to a coordinate.
+#[derive(Serialize, Debug, Clone)]
+pub struct Reverse {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub zip_code: String,
+    pub city: String,
+    pub state: String,
+    pub miles: f64,
+}
+
+// function returns the zip code, city and state whose
+// centroid is closest to a coordinate, with the miles
+// to it by haversine_dist. None for an empty table.
+pub fn reverse(index: &LookupIndex, lat: f64, lon: f64) -> Option<Reverse> {
+    let (rec, miles) = index.nearest(lat, lon, 1).into_iter().next()?;
+    info!(
+        "Reverse geocoded {:?} to {:?} {:?} {:?} ({:.1} mi)",
+        (lat, lon),
+        rec.zip_code,
+        rec.city,
+        rec.state,
+        miles
+    );
+    Some(Reverse {
+        latitude: lat,
+        longitude: lon,
+        zip_code: rec.zip_code.clone(),
+        city: rec.city.clone(),
+        state: rec.state.clone(),
+        miles,
+    })
+}
+
+// function geocodes an address string to the zip code
+// centroids of the table behind `index`. A known zip
+// code wins; otherwise the city (and state) is looked
+// up, allowing for small misspellings. Places that fit
+// equally well share the confidence between them.
+pub fn geocode(index: &LookupIndex, input: &str) -> Geocode {
+    let address = parse_address(input);
+    let mut warnings: Vec<String> = Vec::new();
+
+    let zip_hit = by_zip(index, &address, &mut warnings);
+    let mut candidates = by_city(index, &address);
+    let (best, precision) = match zip_hit {
+        Some(hit) => {
+            // the zip code's own city is not an alternative
+            candidates.retain(|c| !(c.city == hit.city && c.state == hit.state));
+            candidates.insert(0, hit.clone());
+            (Some(hit), Precision::Zip)
+        }
+        None => match candidates.first() {
+            Some(first) => (Some(first.clone()), Precision::City),
+            None => (None, Precision::None),
+        },
+    };
+
+    // ties for a city lookup: same spelling distance
+    let ties = match precision {
+        Precision::City => candidates
+            .iter()
+            .filter(|c| c.edits == candidates[0].edits)
+            .count(),
+        _ => 1,
+    };
+    let ambiguous = ties > 1;
+    let confidence = match &best {
+        Some(b) => round2(b.confidence / ties as f64),
+        None => 0.0,
+    };
+    if ambiguous {
+        let places: Vec<String> = candidates
+            .iter()
+            .take(ties.min(5))
+            .map(|c| format!("{}, {}", c.city, c.state))
+            .collect();
+        let more = match ties > 5 {
+            true => "; ...",
+            false => "",
+        };
+        warnings.push(format!(
+            "{} places fit: {}{}",
+            ties,
+            places.join("; "),
+            more
+        ));
+    }
+    if best.is_none() {
+        warnings.push("no city or zip code found".to_owned());
+    }
+    for w in &warnings {
+        warn!("Geocode {:?}: {}", input, w);
+    }
+    info!(
+        "Geocoded {:?} to {:?} ({:?}, confidence {:?})",
+        input,
+        best.as_ref().map(|b| (b.latitude, b.longitude)),
+        precision.as_str(),
+        confidence
+    );
+    Geocode {
+        input: input.to_owned(),
+        address,
+        best: best.map(|mut b| {
+            b.confidence = confidence;
+            b
+        }),
+        precision,
+        confidence,
+        ambiguous,
+        candidates,
+        warnings,
+    }
+}
+
+// function warns about addresses placed at the zip code
+// of an earlier address. Such venues share a centroid
+// and are a single stop when geocode.csv is the lookup,
+// which keeps one record per zip code.
+pub fn mark_shared_zips(results: &mut [Geocode]) {
+    for k in 0..results.len() {
+        let zip = match &results[k].best {
+            Some(b) => b.zip_code.clone(),
+            None => continue,
+        };
+        let earlier = results[..k]
+            .iter()
+            .position(|g| matches!(&g.best, Some(b) if b.zip_code == zip));
+        if let Some(j) = earlier {
+            let w = format!("same zip code as address {}, one stop as a lookup", j + 1);
+            warn!("Geocode {:?}: {}", results[k].input, w);
+            results[k].warnings.push(w);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::test_record;
+
+    fn record(
+        zip_code: &str,
+        city: &str,
+        state: &str,
+        lat: f64,
+        lon: f64,
+        population: u64,
+    ) -> ZipRecord {
+        ZipRecord {
+            city: city.to_owned(),
+            latitude: lat,
+            longitude: lon,
+            population,
+            ..ZipRecord::from(&test_record(zip_code, state))
+        }
+    }
+
+    fn index() -> LookupIndex {
+        LookupIndex::new(vec![
+            record("50309", "DES MOINES", "IA", 41.59, -93.62, 4000),
+            record("50319", "DES MOINES", "IA", 41.61, -93.60, 0),
+            record("62701", "SPRINGFIELD", "IL", 39.80, -89.65, 1000),
+            record("65806", "SPRINGFIELD", "MO", 37.20, -93.30, 9000),
+            record("63101", "SAINT LOUIS", "MO", 38.63, -90.19, 2000),
+            record("09002", "APO", "AE", 0.0, 0.0, 0),
+        ])
+    }
+
+    #[test]
+    fn parses_addresses() {
+        let a = parse_address("123 Main St., Des Moines, ia 50319-0001");
+        assert_eq!(a.street.as_deref(), Some("123 Main St"));
+        assert_eq!(a.city.as_deref(), Some("DES MOINES"));
+        assert_eq!(a.state.as_deref(), Some("IA"));
+        assert_eq!(a.zip_code.as_deref(), Some("50319"));
+
+        let a = parse_address("Springfield IL");
+        assert_eq!(
+            (a.city.as_deref(), a.state.as_deref()),
+            (Some("SPRINGFIELD"), Some("IL"))
+        );
+        assert_eq!(a.zip_code, None);
+        // a lone word is a city, not a state
+        assert_eq!(parse_address("Ia").city.as_deref(), Some("IA"));
+        assert_eq!(parse_address("Ia").state, None);
+        let a = parse_address("Des Moines, IA");
+        assert_eq!(
+            (a.city.as_deref(), a.state.as_deref()),
+            (Some("DES MOINES"), Some("IA"))
+        );
+        assert_eq!(parse_address(" , "), Address::default());
+    }
+
+    #[test]
+    fn a_zip_code_wins() {
+        let g = geocode(&index(), "Des Moines, IA 50319");
+        assert_eq!(g.precision, Precision::Zip);
+        assert_eq!(g.confidence, 1.0);
+        let best = g.best.unwrap();
+        assert_eq!((best.zip_code.as_str(), best.latitude), ("50319", 41.61));
+        assert!(!g.ambiguous && g.warnings.is_empty());
+
+        let g = geocode(&index(), "Springfield, IL 50319");
+        assert_eq!(g.confidence, 0.3);
+        assert_eq!(g.warnings.len(), 2);
+
+        let g = geocode(&index(), "APO AE 09002");
+        assert_eq!(g.precision, Precision::None);
+        assert!(g
+            .warnings
+            .contains(&"zip code 09002 has no coordinates".to_owned()));
+    }
+
+    #[test]
+    fn a_city_is_the_mean_of_its_zip_codes() {
+        let g = geocode(&index(), "Des Moines, IA");
+        assert_eq!(g.precision, Precision::City);
+        assert_eq!(g.confidence, 0.8);
+        let best = g.best.unwrap();
+        assert_eq!((best.zip_code.as_str(), best.zip_codes), ("50309", 2));
+        assert!((best.latitude - 41.60).abs() < 1e-9);
+
+        let g = geocode(&index(), "St. Louis, MO");
+        assert_eq!(g.best.unwrap().city, "SAINT LOUIS");
+        assert_eq!(g.confidence, 0.8);
+
+        let g = geocode(&index(), "Des Moinse, IA");
+        assert_eq!(g.best.unwrap().edits, 2);
+        assert_eq!(g.confidence, 0.5);
+    }
+
+    #[test]
+    fn places_that_fit_equally_share_the_confidence() {
+        let g = geocode(&index(), "Springfield");
+        assert!(g.ambiguous);
+        assert_eq!(g.confidence, 0.4);
+        assert_eq!(g.best.unwrap().state, "MO");
+        assert_eq!(
+            g.warnings,
+            ["2 places fit: SPRINGFIELD, MO; SPRINGFIELD, IL"]
+        );
+
+        let g = geocode(&index(), "Nowhere, ZZ");
+        assert!(g.best.is_none() && g.confidence == 0.0);
+        assert_eq!(g.warnings, ["no city or zip code found"]);
+    }
+
+    #[test]
+    fn marks_addresses_that_share_a_zip_code() {
+        let index = index();
+        let mut results: Vec<Geocode> =
+            ["Des Moines, IA 50309", "Springfield, IL", "Des Moines, IA"]
+                .iter()
+                .map(|a| geocode(&index, a))
+                .collect();
+        mark_shared_zips(&mut results);
+        assert!(results[0].warnings.is_empty() && results[1].warnings.is_empty());
+        assert_eq!(
+            results[2].warnings,
+            ["same zip code as address 1, one stop as a lookup"]
+        );
+    }
+
+    #[test]
+    fn reverse_finds_the_closest_zip_code() {
+        let index = index();
+        let r = reverse(&index, 39.0, -90.0).unwrap();
+        assert_eq!(
+            (r.zip_code.as_str(), r.city.as_str()),
+            ("63101", "SAINT LOUIS")
+        );
+        assert_eq!((r.latitude, r.longitude), (39.0, -90.0));
+        // the APO row has no coordinates and is never the answer
+        let r = reverse(&index, 0.0, 0.0).unwrap();
+        assert_ne!(r.zip_code, "09002");
+        let r = reverse(&index, 41.61, -93.60).unwrap();
+        assert_eq!((r.zip_code.as_str(), r.miles), ("50319", 0.0));
+        assert!(reverse(&LookupIndex::new(Vec::new()), 0.0, 0.0).is_none());
+    }
+}
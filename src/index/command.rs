@@ -0,0 +1,125 @@
+// The lookup sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_value, parse_flag},
+    command::{parse_point, write_outputs, ZIP_TABLE},
+    index::LookupIndex,
+    zips::{read_zip_csv, ZipRecord},
+};
+use log::{error, info};
+use serde_json::{json, Value};
+
+const LOOKUP_CSV: &str = "lookup.csv";
+const LOOKUP_JSON: &str = "lookup.json";
+
+// lookup: query a zip code table by zip code, by city
+// (case-insensitive, closest names when misspelled),
+// by state or by the nearest records to a coordinate.
+//   lookup --zip 50319
+//   lookup --city "des moines" [--state IA]
+//   lookup --state IA
+//   lookup --near 41.59,-93.60 [--count 5]
+//   --table file.csv           zip code table (default: codes.csv)
+pub fn lookup(args: &[String]) {
+    let table = flag_value(args, "--table").unwrap_or(ZIP_TABLE);
+    let index = LookupIndex::new(read_zip_csv(table));
+
+    // each hit with its MATCH column: miles for --near,
+    // edits for --city, empty otherwise
+    let (query, hits): (String, Vec<(&ZipRecord, String)>) =
+        if let Some(zip) = flag_value(args, "--zip") {
+            let hits = index.zip(zip).map(|r| (r, String::new()));
+            (format!("zip {}", zip), hits.into_iter().collect())
+        } else if let Some(city) = flag_value(args, "--city") {
+            let state = flag_value(args, "--state");
+            let hits = index
+                .city(city, state)
+                .into_iter()
+                .map(|m| (m.record, m.edits.to_string()))
+                .collect();
+            (format!("city {} {}", city, state.unwrap_or("")), hits)
+        } else if let Some(state) = flag_value(args, "--state") {
+            let hits = index
+                .state(state)
+                .into_iter()
+                .map(|r| (r, String::new()))
+                .collect();
+            (format!("state {}", state), hits)
+        } else if let Some(near) = flag_value(args, "--near") {
+            let (lat, lon) = match parse_point(near) {
+                Some(point) => point,
+                None => {
+                    let msg = "Expected --near LAT,LON";
+                    error!("{:?}: {:?}", msg, near);
+                    panic!("{:?}: {:?}", msg, near)
+                }
+            };
+            let count = parse_flag(args, "--count", 5usize);
+            let hits = index
+                .nearest(lat, lon, count)
+                .into_iter()
+                .map(|(r, miles)| (r, format!("{:.1}", miles)))
+                .collect();
+            (format!("near {}", near), hits)
+        } else {
+            let msg = "Expected one of --zip, --city, --state or --near";
+            error!("{:?}: {:?}", msg, args);
+            panic!("{:?}: {:?}", msg, args)
+        };
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "ZIP_CODE",
+        "CITY",
+        "STATE",
+        "LATITUDE",
+        "LONGITUDE",
+        "CLASSIFICATION",
+        "POPULATION",
+        "MATCH",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    for (r, m) in &hits {
+        rows.push(vec![
+            r.zip_code.clone(),
+            r.city.clone(),
+            r.state.clone(),
+            r.latitude.to_string(),
+            r.longitude.to_string(),
+            r.classification.clone(),
+            r.population.to_string(),
+            m.clone(),
+        ]);
+    }
+
+    let records: Vec<Value> = hits
+        .iter()
+        .map(|(r, m)| {
+            json!({
+                "zip_code": r.zip_code,
+                "city": r.city,
+                "state": r.state,
+                "latitude": r.latitude,
+                "longitude": r.longitude,
+                "classification": r.classification,
+                "population": r.population,
+                "match": m
+            })
+        })
+        .collect();
+    let obj = json!({
+        "table": table,
+        "query": query.trim(),
+        "records": records,
+    });
+    write_outputs(LOOKUP_CSV, &rows, LOOKUP_JSON, obj);
+    info!(
+        "Lookup {:?} found {:?} record(s), written to {:?} and {:?}",
+        query.trim(),
+        hits.len(),
+        LOOKUP_CSV,
+        LOOKUP_JSON
+    );
+}
@@ -0,0 +1,369 @@
+pub mod command;
+
+use crate::{distance::haversine_dist, lookup::ObjLookUp, zips::ZipRecord};
+use log::info;
+use std::{cmp::Ordering, collections::BinaryHeap, collections::HashMap};
+
+// In-memory index over zip code records: hash maps by
+// zip code, city and state, and a k-d tree over the
+// coordinates for nearest neighbour queries.
+pub struct LookupIndex {
+    pub records: Vec<ZipRecord>,
+    by_zip: HashMap<String, usize>,
+    by_city: HashMap<(String, String), Vec<usize>>,
+    by_state: HashMap<String, Vec<usize>>,
+    tree: KdTree,
+}
+
+// A city found by name; `edits` is 0 for a match
+// that only differs in case.
+#[derive(Debug, Clone)]
+pub struct CityMatch<'a> {
+    pub record: &'a ZipRecord,
+    pub edits: usize,
+}
+
+impl LookupIndex {
+    pub fn new(records: Vec<ZipRecord>) -> LookupIndex {
+        let mut by_zip: HashMap<String, usize> = HashMap::with_capacity(records.len());
+        let mut by_city: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        let mut by_state: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, rec) in records.iter().enumerate() {
+            // the first record of a zip code wins
+            by_zip.entry(rec.zip_code.clone()).or_insert(i);
+            by_city
+                .entry((rec.city.to_uppercase(), rec.state.to_uppercase()))
+                .or_default()
+                .push(i);
+            by_state
+                .entry(rec.state.to_uppercase())
+                .or_default()
+                .push(i);
+        }
+        let tree = KdTree::new(&records);
+        info!(
+            "Indexed {:?} record(s): {:?} cities in {:?} states",
+            records.len(),
+            by_city.len(),
+            by_state.len()
+        );
+        LookupIndex {
+            records,
+            by_zip,
+            by_city,
+            by_state,
+            tree,
+        }
+    }
+
+    // function indexes the records of look_up.json.
+    pub fn from_look_up(data: &[ObjLookUp]) -> LookupIndex {
+        LookupIndex::new(data.iter().map(ZipRecord::from).collect())
+    }
+
+    pub fn zip(&self, zip: &str) -> Option<&ZipRecord> {
+        self.by_zip.get(zip.trim()).map(|&i| &self.records[i])
+    }
+
+    // function returns every record of a state code,
+    // in table order, ignoring case.
+    pub fn state(&self, state: &str) -> Vec<&ZipRecord> {
+        match self.by_state.get(&state.trim().to_uppercase()) {
+            Some(idx) => idx.iter().map(|&i| &self.records[i]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // function returns the first record of a state, as
+    // lookup::state_index does.
+    pub fn first_in_state(&self, state: &str) -> Option<&ZipRecord> {
+        self.by_state
+            .get(&state.trim().to_uppercase())
+            .map(|idx| &self.records[idx[0]])
+    }
+
+    // function finds a city, optionally within a state,
+    // ignoring case. Without an exact match the closest
+    // names within a few edits are returned, best first.
+    pub fn city(&self, city: &str, state: Option<&str>) -> Vec<CityMatch<'_>> {
+        let name = city.trim().to_uppercase();
+        let state = state.map(|s| s.trim().to_uppercase());
+        let in_state = |key: &(String, String)| match &state {
+            Some(s) => &key.1 == s,
+            None => true,
+        };
+
+        let exact: Vec<CityMatch> = self
+            .by_city
+            .iter()
+            .filter(|(key, _)| key.0 == name && in_state(key))
+            .flat_map(|(_, idx)| idx.iter())
+            .map(|&i| CityMatch {
+                record: &self.records[i],
+                edits: 0,
+            })
+            .collect();
+        if !exact.is_empty() {
+            return sorted(exact);
+        }
+
+        let limit = (name.chars().count() / 4).max(1);
+        let mut fuzzy: Vec<CityMatch> = Vec::new();
+        for (key, idx) in self.by_city.iter().filter(|(key, _)| in_state(key)) {
+            let edits = edit_distance(&name, &key.0);
+            if edits <= limit {
+                fuzzy.extend(idx.iter().map(|&i| CityMatch {
+                    record: &self.records[i],
+                    edits,
+                }));
+            }
+        }
+        sorted(fuzzy)
+    }
+
+    // function returns the `count` records closest to a
+    // coordinate with their haversine miles, closest
+    // first. Records without coordinates are skipped.
+    pub fn nearest(&self, lat: f64, lon: f64, count: usize) -> Vec<(&ZipRecord, f64)> {
+        self.tree
+            .nearest(unit_vector(lat, lon), count)
+            .into_iter()
+            .map(|i| {
+                let rec = &self.records[i];
+                (rec, haversine_dist(lat, lon, rec.latitude, rec.longitude))
+            })
+            .collect()
+    }
+}
+
+fn sorted(mut found: Vec<CityMatch>) -> Vec<CityMatch> {
+    found.sort_by(|a, b| {
+        (a.edits, &a.record.state, &a.record.city, &a.record.zip_code).cmp(&(
+            b.edits,
+            &b.record.state,
+            &b.record.city,
+            &b.record.zip_code,
+        ))
+    });
+    found
+}
+
+// function returns the Levenshtein distance between
+// two names.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let swap = prev[j - 1] + (a[i - 1] != b[j - 1]) as usize;
+            row[j] = swap.min(prev[j] + 1).min(row[j - 1] + 1);
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+// Coordinates on the unit sphere, so the straight line
+// distance orders points like the great circle one
+// and longitudes wrap around.
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn dist2(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|k| (a[k] - b[k]).powi(2)).sum()
+}
+
+// A candidate neighbour, ordered by distance so the
+// heap keeps the farthest on top.
+struct Near(f64, usize);
+
+impl PartialEq for Near {
+    fn eq(&self, other: &Near) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Near {}
+
+impl PartialOrd for Near {
+    fn partial_cmp(&self, other: &Near) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Near {
+    fn cmp(&self, other: &Near) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// A k-d tree stored as a permutation of the records:
+// the subtree over order[lo..hi] has its root at the
+// middle and splits on axis depth % 3.
+struct KdTree {
+    points: Vec<[f64; 3]>,
+    order: Vec<usize>,
+}
+
+impl KdTree {
+    fn new(records: &[ZipRecord]) -> KdTree {
+        let points: Vec<[f64; 3]> = records
+            .iter()
+            .map(|r| unit_vector(r.latitude, r.longitude))
+            .collect();
+        // rows without coordinates are left out
+        let mut order: Vec<usize> = (0..points.len())
+            .filter(|&i| records[i].located())
+            .collect();
+        build(&points, &mut order, 0);
+        KdTree { points, order }
+    }
+
+    fn nearest(&self, target: [f64; 3], count: usize) -> Vec<usize> {
+        let mut heap: BinaryHeap<Near> = BinaryHeap::with_capacity(count + 1);
+        if count > 0 {
+            self.search(0, self.order.len(), 0, &target, count, &mut heap);
+        }
+        heap.into_sorted_vec().into_iter().map(|n| n.1).collect()
+    }
+
+    fn search(
+        &self,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        target: &[f64; 3],
+        count: usize,
+        heap: &mut BinaryHeap<Near>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let i = self.order[mid];
+        let d = dist2(&self.points[i], target);
+        if heap.len() < count {
+            heap.push(Near(d, i));
+        } else if matches!(heap.peek(), Some(far) if d < far.0) {
+            heap.pop();
+            heap.push(Near(d, i));
+        }
+
+        let axis = depth % 3;
+        let diff = target[axis] - self.points[i][axis];
+        let (near, far) = match diff < 0.0 {
+            true => ((lo, mid), (mid + 1, hi)),
+            false => ((mid + 1, hi), (lo, mid)),
+        };
+        self.search(near.0, near.1, depth + 1, target, count, heap);
+        // the far side only matters when the splitting
+        // plane is closer than the farthest kept point
+        let worst = match heap.len() < count {
+            true => f64::INFINITY,
+            false => heap.peek().map_or(f64::INFINITY, |n| n.0),
+        };
+        if diff * diff < worst {
+            self.search(far.0, far.1, depth + 1, target, count, heap);
+        }
+    }
+}
+
+fn build(points: &[[f64; 3]], order: &mut [usize], depth: usize) {
+    if order.len() < 2 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |&a, &b| points[a][axis].total_cmp(&points[b][axis]));
+    let (left, right) = order.split_at_mut(mid);
+    build(points, left, depth + 1);
+    build(points, &mut right[1..], depth + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lookup::test_record, random::XorShift, zips::read_zip_csv};
+
+    fn record(n: usize, lat: f64, lon: f64) -> ZipRecord {
+        ZipRecord {
+            latitude: lat,
+            longitude: lon,
+            ..ZipRecord::from(&test_record(&format!("{:05}", n), "XX"))
+        }
+    }
+
+    #[test]
+    fn nearest_matches_a_linear_scan() {
+        let mut rng = XorShift::new(42);
+        let mut records: Vec<ZipRecord> = (0..500)
+            .map(|n| record(n, rng.unit() * 180.0 - 90.0, rng.unit() * 360.0 - 180.0))
+            .collect();
+        // a row without coordinates is never returned
+        records.push(record(500, 0.0, 0.0));
+        let index = LookupIndex::new(records.clone());
+        for _ in 0..50 {
+            let (lat, lon) = (rng.unit() * 180.0 - 90.0, rng.unit() * 360.0 - 180.0);
+            let count = 1 + rng.below(8);
+            let target = unit_vector(lat, lon);
+            let mut scan: Vec<(f64, &str)> = records
+                .iter()
+                .filter(|r| r.located())
+                .map(|r| {
+                    (
+                        dist2(&unit_vector(r.latitude, r.longitude), &target),
+                        r.zip_code.as_str(),
+                    )
+                })
+                .collect();
+            scan.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let found: Vec<&str> = index
+                .nearest(lat, lon, count)
+                .iter()
+                .map(|(r, _)| r.zip_code.as_str())
+                .collect();
+            let expected: Vec<&str> = scan[..count].iter().map(|s| s.1).collect();
+            assert_eq!(found, expected);
+        }
+        assert!(index.nearest(0.0, 0.0, 0).is_empty());
+    }
+
+    #[test]
+    fn nearest_wraps_around_the_date_line() {
+        let records = vec![
+            record(1, 51.0, 179.5),
+            record(2, 51.0, 170.0),
+            record(3, 51.0, -170.0),
+        ];
+        let index = LookupIndex::new(records);
+        let found = index.nearest(51.0, -179.5, 1);
+        assert_eq!(found[0].0.zip_code, "00001");
+        assert!(found[0].1 < 50.0);
+    }
+
+    #[test]
+    fn finds_zip_codes_states_and_cities() {
+        let index = LookupIndex::new(read_zip_csv("zip_codes.csv"));
+        assert_eq!(index.zip(" 50319 ").unwrap().city, "DES MOINES");
+        assert!(index.zip("00000").is_none());
+        assert_eq!(index.state("ct").len(), 2);
+        assert_eq!(index.first_in_state("CT").unwrap().zip_code, "06106");
+        let found = index.city("des moines", Some("ia"));
+        assert_eq!((found.len(), found[0].edits), (1, 0));
+        let found = index.city("DES MOINSE", None);
+        assert_eq!(found[0].record.zip_code, "50319");
+        assert_eq!(found[0].edits, 2);
+        assert!(index.city("DES MOINES", Some("NY")).is_empty());
+    }
+
+    #[test]
+    fn counts_edits() {
+        assert_eq!(edit_distance("KITTEN", "SITTING"), 3);
+        assert_eq!(edit_distance("", "ABC"), 3);
+        assert_eq!(edit_distance("BOSTON", "BOSTON"), 0);
+    }
+}
@@ -1,4 +1,33 @@
+pub mod audit;
+pub mod capitals;
+pub mod cli;
+pub mod command;
+pub mod compare;
+pub mod constraints;
+pub mod costing;
 pub mod csv;
 pub mod distance;
+pub mod geocode;
+pub mod index;
+pub mod lookup;
+pub mod matrix;
+pub mod modal;
+pub mod mtsp;
+pub mod pairs;
+pub mod pareto;
 pub mod permutate;
+pub mod place;
+pub mod precedence;
+pub mod prize;
+pub mod random;
+pub mod route;
+pub mod schedule;
+pub mod solver;
+pub mod stops;
 pub mod stss;
+pub mod territory;
+pub mod timezone;
+pub mod validate;
+pub mod weights;
+pub mod windows;
+pub mod zips;
@@ -0,0 +1,308 @@
+use crate::place::LatLon;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Read};
+
+// Route endpoints when states.json names none.
+pub const DEFAULT_START: &str = "IA";
+pub const DEFAULT_END: &str = "DC";
+
+#[derive(Deserialize, Debug)]
+pub struct ObjStates {
+    pub from_state: String,
+    pub to_state: String,
+    // where the route starts and ends; an end equal to
+    // the start is a closed tour, a start without an end
+    // an open path
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+// function returns the route start and end named by a
+// states.json entry, IA and DC when it names neither.
+pub fn route_ends(states: &ObjStates) -> (String, Option<String>) {
+    match (&states.start, &states.end) {
+        (None, None) => (DEFAULT_START.to_owned(), Some(DEFAULT_END.to_owned())),
+        (Some(start), end) => (start.clone(), end.clone()),
+        (None, Some(end)) => {
+            let msg = "states.json has an end but no start";
+            error!("{:?}: {:?}", msg, end);
+            panic!("{:?}: {:?}", msg, end)
+        }
+    }
+}
+
+// A lookup record as read, every field text. The
+// aliases read the headers of codes.csv; see
+// place::Place for the validated record.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ObjLookUp {
+    #[serde(alias = "ZipCode")]
+    pub zip_code: String,
+    #[serde(alias = "City")]
+    pub city: String,
+    #[serde(alias = "State")]
+    pub state: String,
+    #[serde(alias = "Latitude")]
+    pub latitude: String,
+    #[serde(alias = "Longitude")]
+    pub longitude: String,
+    #[serde(alias = "Classification")]
+    pub classification: String,
+    #[serde(alias = "Population")]
+    pub population: String,
+}
+
+// function reads and deserializes a lookup file
+// such as look_up.json.
+pub fn read_look_up(path: &str) -> Vec<ObjLookUp> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            let msg = "There was a problem opening lookup file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+
+    let mut contents = String::new();
+    if let Err(e) = file.read_to_string(&mut contents) {
+        let msg = "Failed reading lookup file";
+        error!("{:?}: {:?}: {:?}", msg, path, e);
+        panic!("{:?}: {:?}: {:?}", msg, path, e)
+    }
+
+    match serde_json::from_str(&contents) {
+        Ok(data) => {
+            info!("Success deserialized {:?}", path);
+            data
+        }
+        Err(e) => {
+            let msg = "Failed to deserialize lookup file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    }
+}
+
+// function returns the (latitude, longitude)
+// of a lookup record as f64. Records are validated
+// when the lookup is read, see place::validate_look_up.
+pub fn lat_lon(rec: &ObjLookUp) -> (f64, f64) {
+    match LatLon::parse(&rec.latitude, &rec.longitude) {
+        Ok(p) => (p.lat, p.lon),
+        Err(e) => {
+            let msg = "Error converting coordinates to f64";
+            error!("{:?}: {:?}: {:?}", msg, rec.state, e);
+            panic!("{:?}: {:?}: {:?}", msg, rec.state, e);
+        }
+    }
+}
+
+// function returns the index of the first
+// lookup record for a state code.
+pub fn state_index(data: &[ObjLookUp], state: &str) -> Option<usize> {
+    data.iter().position(|rec| rec.state == state)
+}
+
+// function returns the records of a lookup with one
+// record per state: a later record of a state already
+// listed is dropped, e.g. the second Hartford zip code
+// of look_up.json, which lists the capitals.
+pub fn one_per_state(data: Vec<ObjLookUp>) -> Vec<ObjLookUp> {
+    let mut kept: Vec<ObjLookUp> = Vec::with_capacity(data.len());
+    for rec in data {
+        match kept.iter().any(|k| k.state == rec.state) {
+            true => info!(
+                "Dropped a second record of {:?}: {:?}",
+                rec.state, rec.zip_code
+            ),
+            false => kept.push(rec),
+        }
+    }
+    kept
+}
+
+// function returns the records of a lookup with one
+// record per zip code, dropping repeats.
+pub fn one_per_zip(data: Vec<ObjLookUp>) -> Vec<ObjLookUp> {
+    let mut kept: Vec<ObjLookUp> = Vec::with_capacity(data.len());
+    for rec in data {
+        match kept.iter().any(|k| k.zip_code == rec.zip_code) {
+            true => info!("Dropped a repeated zip code: {:?}", rec.zip_code),
+            false => kept.push(rec),
+        }
+    }
+    kept
+}
+
+// function returns true for a stop named by its 5
+// digit zip code rather than its state code.
+pub fn is_zip_name(name: &str) -> bool {
+    name.len() == 5 && name.chars().all(|c| c.is_ascii_digit())
+}
+
+// function returns the name of the stop at a lookup
+// record: the state code for the first record of its
+// state, the zip code for any later one. Routes are
+// written with these names and read back by
+// stop_index.
+pub fn stop_name(data: &[ObjLookUp], i: usize) -> String {
+    match state_index(data, &data[i].state) == Some(i) {
+        true => data[i].state.clone(),
+        false => data[i].zip_code.clone(),
+    }
+}
+
+// function returns the index of the lookup record a
+// stop name refers to: a zip code, or the first record
+// of a state code.
+pub fn stop_index(data: &[ObjLookUp], name: &str) -> Option<usize> {
+    match is_zip_name(name) {
+        true => data.iter().position(|rec| rec.zip_code == name),
+        false => state_index(data, name),
+    }
+}
+
+// function maps a list of stop names to lookup
+// indices and panics on an unknown stop.
+pub fn stop_indices(data: &[ObjLookUp], names: &[&str]) -> Vec<usize> {
+    names
+        .iter()
+        .map(|s| match stop_index(data, s) {
+            Some(i) => i,
+            None => {
+                let msg = "Stop not found in lookup";
+                error!("{:?}: {:?}", msg, s);
+                panic!("{:?}: {:?}", msg, s)
+            }
+        })
+        .collect()
+}
+
+// function returns the records a list of stops
+// covers, each once and in list order: a zip code
+// names its record, a state code every record of the
+// state. Panics on an unknown stop.
+pub fn stop_records(data: &[ObjLookUp], names: &[&str]) -> Vec<usize> {
+    let mut idx: Vec<usize> = Vec::new();
+    for name in names {
+        let found: Vec<usize> = match is_zip_name(name) {
+            true => stop_indices(data, &[name]),
+            false => (0..data.len())
+                .filter(|&i| data[i].state == *name)
+                .collect(),
+        };
+        if found.is_empty() {
+            let msg = "Stop not found in lookup";
+            error!("{:?}: {:?}", msg, name);
+            panic!("{:?}: {:?}", msg, name)
+        }
+        for i in found {
+            if !idx.contains(&i) {
+                idx.push(i);
+            }
+        }
+    }
+    idx
+}
+
+// function returns a lookup record for tests: a zip
+// code of a state at 0,0 with no city or population.
+#[cfg(test)]
+pub fn test_record(zip_code: &str, state: &str) -> ObjLookUp {
+    ObjLookUp {
+        zip_code: zip_code.to_owned(),
+        city: String::new(),
+        state: state.to_owned(),
+        latitude: "0".to_owned(),
+        longitude: "0".to_owned(),
+        classification: String::new(),
+        population: "0".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(json: &str) -> ObjStates {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn route_ends_default_to_iowa_and_dc() {
+        let s = states(r#"{"from_state": "IA", "to_state": "NY"}"#);
+        assert_eq!(route_ends(&s), ("IA".to_owned(), Some("DC".to_owned())));
+    }
+
+    #[test]
+    fn route_ends_follow_states_json() {
+        let s = states(r#"{"from_state": "IA", "to_state": "NY", "start": "CA", "end": "ME"}"#);
+        assert_eq!(route_ends(&s), ("CA".to_owned(), Some("ME".to_owned())));
+        // a start alone is an open path
+        let s = states(r#"{"from_state": "IA", "to_state": "NY", "start": "TX"}"#);
+        assert_eq!(route_ends(&s), ("TX".to_owned(), None));
+    }
+
+    #[test]
+    #[should_panic(expected = "states.json has an end but no start")]
+    fn an_end_needs_a_start() {
+        let s = states(r#"{"from_state": "IA", "to_state": "NY", "end": "DC"}"#);
+        route_ends(&s);
+    }
+
+    // IA, OH (Columbus), a second OH record and DC
+    fn data() -> Vec<ObjLookUp> {
+        vec![
+            test_record("50319", "IA"),
+            test_record("43215", "OH"),
+            test_record("44114", "OH"),
+            test_record("20004", "DC"),
+        ]
+    }
+
+    #[test]
+    fn names_later_records_by_zip_code() {
+        let data = data();
+        let names: Vec<String> = (0..data.len()).map(|i| stop_name(&data, i)).collect();
+        assert_eq!(names, ["IA", "OH", "44114", "DC"]);
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(stop_index(&data, name), Some(i));
+        }
+        // the zip code of a first record finds it too
+        assert_eq!(stop_index(&data, "43215"), Some(1));
+        assert_eq!(stop_index(&data, "NY"), None);
+        assert!(is_zip_name("00501") && !is_zip_name("OH") && !is_zip_name("4321"));
+    }
+
+    #[test]
+    fn a_state_code_names_every_record() {
+        let data = data();
+        assert_eq!(stop_records(&data, &["DC", "OH"]), [3, 1, 2]);
+        assert_eq!(stop_records(&data, &["44114", "OH"]), [2, 1]);
+        assert_eq!(stop_indices(&data, &["OH", "44114"]), [1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stop not found in lookup")]
+    fn an_unknown_stop_is_refused() {
+        stop_records(&data(), &["12224"]);
+    }
+
+    #[test]
+    fn keeps_one_record_per_state_or_zip_code() {
+        let mut records = data();
+        records.push(test_record("43215", "OH"));
+        let zips: Vec<String> = one_per_zip(records.clone())
+            .into_iter()
+            .map(|r| r.zip_code)
+            .collect();
+        assert_eq!(zips, ["50319", "43215", "44114", "20004"]);
+        let zips: Vec<String> = one_per_state(records)
+            .into_iter()
+            .map(|r| r.zip_code)
+            .collect();
+        assert_eq!(zips, ["50319", "43215", "20004"]);
+    }
+}
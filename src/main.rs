@@ -4,6 +4,23 @@
 * FILE: main.rs                                                                 *
 *                                                                               *
 * USAGE: redis [-h]                                                             *
+*        read_json [pareto] [--states IA,NY,...] [--matrix NAME=file.csv]       *
+*        read_json [modal] [--states IA,NY,...] [--airports file.csv]           *
+*        read_json [cost] [route.json]                                          *
+*        read_json [schedule] [route.json] [--start 2024-01-15T08:00]           *
+*        read_json [windows] [constraints.json] [--states IA,NY,...]            *
+*        read_json [mtsp] [--depots IA,DC] [--objective total|minmax]           *
+*        read_json [prize] [--miles N | --days N] [--prize votes|population]    *
+*        read_json [validate] [route.json|route.csv] [--row KEY]                *
+*        read_json [compare] [first] [second] [--rows KEY,KEY]                  *
+*        read_json [lookup] [--zip Z | --city C [--state ST] | --near LAT,LON]  *
+*        read_json [geocode] ["street, city, ST ZIP" ...] [--file venues.txt]   *
+*        read_json [reverse] [LAT,LON ...] [--file trace.csv]                   *
+*        read_json [capitals] [--out look_up.json] [--far MILES]                *
+*        read_json [audit] [--json look_up.json] [--csv zip_codes.csv]          *
+*        read_json [distances] [--states IA,NY,DC]                              *
+*        read_json [...] [--lookup codes.csv --zips 50319,12224,...]            *
+*        read_json [...] [--territories include] [--noncontiguous exclude]      *
 *                                                                               *
 * DESCRIPTION: The haversine formula, an equation important in                  *
 *              navigation, is used here to determine the                        *
@@ -65,6 +82,51 @@
 * REVISION MADE: Added csv module to library. Added error                       *
 *                checking functionality. Corrected spacing                      *
 *                in README file and add CB imgage. Added rusted_cypher          *                                                                                  #
+* REVISION DATE-TIME: 20261019-05:36                                            *
+* REVISION MADE: Moved lookup structs to a library module. Added                *
+*                sub commands and the pareto (multi objective) mode.            *
+* REVISION DATE-TIME: 20261019-05:38                                            *
+* REVISION MADE: Added the modal (drive or fly per leg) sub command.            *
+* REVISION DATE-TIME: 20261019-05:39                                            *
+* REVISION MADE: Added the cost sub command (fuel, lodging, CO2).               *
+* REVISION DATE-TIME: 20261019-05:41                                            *
+* REVISION MADE: Added the schedule sub command (JSON & iCalendar).             *
+* REVISION DATE-TIME: 20261019-05:42                                            *
+* REVISION MADE: Time zone of each capital in output.json; UTC in the log.      *
+* REVISION DATE-TIME: 20261019-05:55                                            *
+* REVISION MADE: Added the windows sub command (campaign time windows).         *
+* REVISION DATE-TIME: 20261019-05:59                                            *
+* REVISION MADE: Route start and end come from states.json, not IA/DC.          *
+* REVISION DATE-TIME: 20261019-06:00                                            *
+* REVISION MADE: Closed tours enumerate each loop once ((n-1)!/2).              *
+* REVISION DATE-TIME: 20261019-06:01                                            *
+* REVISION MADE: Added the mtsp (several travelers) sub command.                *
+* REVISION DATE-TIME: 20261019-06:07                                            *
+* REVISION MADE: Added the prize (most prize within a budget) sub command.      *
+* REVISION DATE-TIME: 20261019-06:12                                            *
+* REVISION MADE: Added the validate (check a hand-built route) sub command.     *
+* REVISION DATE-TIME: 20261019-06:13                                            *
+* REVISION MADE: Added the compare (route diff report) sub command.             *
+* REVISION DATE-TIME: 20261019-06:14                                            *
+* REVISION MADE: Lookup records from a zip code table (--lookup, --zips).       *
+* REVISION DATE-TIME: 20261019-06:19                                            *
+* REVISION MADE: Added the lookup sub command; an index replaces lookup scans.  *
+* REVISION DATE-TIME: 20261019-06:21                                            *
+* REVISION MADE: Added the geocode (offline address to coordinates) command.    *
+* REVISION DATE-TIME: 20261019-06:21                                            *
+* REVISION MADE: Added the reverse (coordinates to nearest zip code) command.   *
+* REVISION DATE-TIME: 20261019-06:23                                            *
+* REVISION MADE: Added the capitals (regenerate the lookup) sub command.        *
+* REVISION DATE-TIME: 20261019-06:25                                            *
+* REVISION MADE: Typed lookup records (place module); all bad rows reported.    *
+* REVISION DATE-TIME: 20261019-06:27                                            *
+* REVISION MADE: Added the audit (lookup and zip file quality) sub command.     *
+* REVISION DATE-TIME: 20261019-06:32                                            *
+* REVISION MADE: Territories as stops (--territories); flight-only legs.        *
+* REVISION DATE-TIME: 20261019-06:34                                            *
+* REVISION MADE: Every states.json pair (state, zip or city) to pairs.json.     *
+* REVISION DATE-TIME: 20261019-06:35                                            *
+* REVISION MADE: Added the distances (pairwise table export) sub command.       *
 *********************************************************************************
 */
 
@@ -89,36 +151,21 @@ use log4rs::{
     filter::threshold::ThresholdFilter,
 };
 use permutohedron::Heap;
-use serde::Deserialize;
 use serde_json::json;
 use std::{env, f64, fs, fs::File, io::Read, string::String};
 //use rusted_cypher::GraphClient;
 
 use rj::{
+    command,
     csv::{path_exists, write_csv, Location},
     distance::haversine_dist as distance,
+    index::LookupIndex,
+    lookup::{route_ends, state_index, stop_indices, ObjLookUp, ObjStates},
+    pairs::Endpoint,
+    precedence::{allows, check_ends},
     stss::{title, vec_row},
 };
 
-#[derive(Deserialize, Debug)]
-struct ObjStates {
-    from_state: String,
-    to_state: String,
-}
-
-#[derive(Deserialize, Debug)]
-struct ObjLookUp {
-    zip_code: String,
-    city: String,
-    state: String,
-    latitude: String,
-    longitude: String,
-    classification: String,
-    population: String,
-}
-
-const IA: &str = "IA";
-const DC: &str = "DC";
 const LOG_PATH: &str = "log/path.log";
 const CYPHER_CSV: &str = "/cypher.csv";
 
@@ -135,7 +182,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Logging to log file.
     let logfile = match FileAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
-            "{d(%Y-%m-%d %H:%M:%S)} Line:{L} {h([{l}])} - {m}{n}",
+            "{d(%Y-%m-%d %H:%M:%S %Z)} {d(%Y-%m-%dT%H:%M:%SZ)(utc)} Line:{L} {h([{l}])} - {m}{n}",
         )))
         .build(LOG_PATH)
     {
@@ -284,6 +331,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Route endpoints: IA to DC unless states.json names
+    // a start and optionally an end.
+    let (start_state, end_state) = route_ends(&data_states[0]);
+    let end_state: Option<&str> = end_state.as_deref();
+    info!(
+        "Route start: {:?} end: {:?}",
+        start_state,
+        end_state.unwrap_or("open")
+    );
+
+    // The audit checks the lookup files itself, before
+    // they are read and validated below.
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("audit") {
+        command::audit(&args);
+        return Ok(());
+    }
+
     // Lookup table &
     // Error(2) check for presence of file/directoery
     let mut file_look_up = match File::open("look_up.json") {
@@ -327,81 +392,60 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    let num = data_look_up.len();
-
-    // Initialize these values so that they'll be
-    // in scope for the haversine_dist function
-    let mut lon1: f64 = 1.0;
-    let mut lat1: f64 = 1.0;
-    let mut lon2: f64 = 1.0;
-    let mut lat2: f64 = 1.0;
-    let mut from_zipcode: String = "0".to_string();
-    let mut to_zipcode: String = "0".to_string();
-    info!("Initialize variables for output.json");
-
-    // Loop through lookup file to verify input.
-    for x in 0..num {
-        match &data_look_up[x].state {
-            // unreachable pattern so impose a guard
-            a if a == from_state => {
-                lon1 = match data_look_up[x].longitude.parse::<f64>() {
-                    Ok(lon1) => lon1,
-                    Err(e) => {
-                        let msg = "Error converting lon1 to f64";
-                        error!("{:?}: {:?}", msg, e);
-                        panic!("{:?}: {:?}", msg, e);
-                    }
-                };
-
-                lat1 = match data_look_up[x].latitude.parse::<f64>() {
-                    Ok(lat1) => lat1,
-                    Err(e) => {
-                        let msg = "Error converting lat1 to f64";
-                        error!("{:?}: {:?}", msg, e);
-                        panic!("{:?}: {:?}", msg, e);
-                    }
-                };
-
-                from_zipcode = match data_look_up[x].zip_code.parse::<String>() {
-                    Ok(from_zipcode) => from_zipcode,
-                    Err(e) => {
-                        let msg = "Error converting from_zipcode to String";
-                        error!("{:?}: {:?}", msg, e);
-                        panic!("{:?}: {:?}", msg, e);
-                    }
-                };
+    // Optional lookup source, e.g. `--lookup codes.csv
+    // --zips 50319,12224` builds the instance from any
+    // zip codes instead of look_up.json.
+    let data_look_up = command::look_up_source(&args, data_look_up);
+
+    // Optional sub command, e.g. `read_json pareto`.
+    // Run it against the lookup data and exit.
+    if let Some(cmd) = args.get(1).filter(|a| !a.starts_with("--")) {
+        info!("Sub command: {:?}", &args[1..]);
+        match cmd.as_str() {
+            "pareto" => command::pareto(&args, &data_look_up, &start_state, end_state),
+            "modal" => command::modal(&args, &data_look_up, &start_state, end_state),
+            "cost" => command::cost(&args, &data_look_up),
+            "schedule" => command::schedule(&args, &data_look_up),
+            "windows" => command::windows(&args, &data_look_up, &start_state, end_state),
+            "mtsp" => command::mtsp(&args, &data_look_up, &start_state, end_state),
+            "prize" => command::prize(&args, &data_look_up, &start_state, end_state),
+            "validate" => command::validate(&args, &data_look_up, &start_state, end_state),
+            "compare" => command::compare(&args, &data_look_up),
+            "lookup" => command::lookup(&args),
+            "geocode" => command::geocode(&args),
+            "reverse" => command::reverse(&args),
+            "capitals" => command::capitals(&args),
+            "distances" => command::distances(&args, &data_look_up),
+            _ => {
+                let msg = "Unknown sub command";
+                error!("{:?}: {:?}", msg, cmd);
+                panic!("{:?}: {:?}", msg, cmd)
             }
-            // unreachable pattern so impose a guard
-            b if b == to_state => {
-                lon2 = match data_look_up[x].longitude.parse::<f64>() {
-                    Ok(lon2) => lon2,
-                    Err(e) => {
-                        let msg = "Error converting _lon2 to f64";
-                        error!("{:?}: {:?}", msg, e);
-                        panic!("{:?}: {:?}", msg, e);
-                    }
-                };
-                lat2 = match data_look_up[x].latitude.parse::<f64>() {
-                    Ok(lat2) => lat2,
-                    Err(e) => {
-                        let msg = "Error converting lat2 to f64";
-                        error!("{:?}: {:?}", msg, e);
-                        panic!("{:?}: {:?}", msg, e);
-                    }
-                };
-                to_zipcode = match data_look_up[x].zip_code.parse::<String>() {
-                    Ok(to_zipcode) => to_zipcode,
-                    Err(e) => {
-                        let msg = "Error converting to_zipcode to String";
-                        error!("{:?}: {:?}", msg, e);
-                        panic!("{:?}: {:?}", msg, e);
-                    }
-                };
-            }
-            _ => {}
         }
+        return Ok(());
     }
 
+    // Hash map and k-d tree index over the lookup
+    // records, used in place of scanning them.
+    let index = LookupIndex::from_look_up(&data_look_up);
+
+    // Every states.json entry is resolved, by state code,
+    // zip code or city, and written to pairs.json;
+    // output.json keeps the first of them.
+    let now = Utc::now();
+    let pairs = command::pairs(&args, &data_states, &index, now);
+    let (from, to) = (&pairs[0].from, &pairs[0].to);
+
+    // coordinates and zip code of the beginning and
+    // ending points; 1.0 and "0" when one is not found
+    let ends = |end: &Endpoint| match (end.latitude, end.longitude) {
+        (Some(lat), Some(lon)) => (lat, lon, end.zip_code.clone(), end.state.clone()),
+        _ => (1.0, 1.0, "0".to_string(), end.input.clone()),
+    };
+    let (lat1, lon1, from_zipcode, from_state) = ends(from);
+    let (lat2, lon2, to_zipcode, to_state) = ends(to);
+    info!("Initialize variables for output.json");
+
     let d: f64 = distance(lat1, lon1, lat2, lon2); // mod function (src/distance/mod.rs)
     info!(
         "json object: Distance from {} to {}: {:.1} mi ",
@@ -410,13 +454,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let dt = format!("{}", Local::now().format("%a %b %e %T %Y"));
 
+    // creation time in UTC and local to each end
+    let (from_zone, from_time) = (&from.time_zone, &from.local_time);
+    let (to_zone, to_time) = (&to.time_zone, &to.local_time);
+    info!(
+        "Created {:?} UTC: {:?} {:?}, {:?} {:?}",
+        now.to_rfc3339(),
+        from_state,
+        from_time,
+        to_state,
+        to_time
+    );
+
     let obj = json!({
-        "beginning_state":from_state.to_string(),
+        "beginning_state":from_state,
         "beginning_zipcode":from_zipcode,
-        "ending_state":to_state.to_string(),
+        "beginning_time_zone":from_zone,
+        "beginning_local_time":from_time,
+        "ending_state":to_state,
         "ending_zipcode":to_zipcode,
+        "ending_time_zone":to_zone,
+        "ending_local_time":to_time,
         "miles_between":d.to_string(),
-        "time_created":dt
+        "time_created":dt,
+        "time_created_utc":now.to_rfc3339()
     });
     trace!("Initialize json object: {:?}", &obj);
 
@@ -440,22 +501,51 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Haversine is finished
     // Permutation begins
 
-    let num = data_look_up.len() - 47; // Don't allow all 51 entries to be permutated.
+    // lookup indices of the route endpoints
+    let first = stop_indices(&data_look_up, &[&start_state])[0];
+    let last = end_state.map(|e| stop_indices(&data_look_up, &[e])[0]);
+
+    let num = data_look_up.len().min(4); // Don't allow all 51 entries to be permutated.
     let mut data = Vec::with_capacity(num);
-    debug!("Number of states to iterate through w/o IA & DC: {:?}", num);
+    debug!(
+        "Number of states to iterate through w/o start & end: {:?}",
+        num
+    );
 
-    for x in 0..num {
-        match data_look_up[x].state != IA && // omit Iowa && DC
-                data_look_up[x].state != DC
+    for (x, record) in data_look_up.iter().enumerate().take(num) {
+        match x != first && Some(x) != last // omit the start && end
         {
-            true => data.push(&data_look_up[x].state),
+            true => data.push(&record.state),
             false => {}
         }
     }
 
-    let heap = Heap::new(&mut data);
+    // Orderings that break a precedence group of
+    // `--constraints` are skipped.
+    let prec = command::read_precedence(&args, &data_look_up);
+    let route_of = |p: &[&String]| -> Vec<usize> {
+        let mut route = vec![first];
+        route.extend(p.iter().filter_map(|s| state_index(&data_look_up, s)));
+        // the return of a closed tour is not a visit
+        route.extend(last.filter(|&l| l != first));
+        route
+    };
+    let middle: Vec<usize> = data
+        .iter()
+        .filter_map(|s| state_index(&data_look_up, s))
+        .collect();
+    check_ends(first, &middle, last, &prec, &data_look_up);
+
+    // A closed tour and its reverse are the same loop,
+    // keep the orderings whose first state sorts before
+    // their last: (n-1)!/2 tours. Precedence pairs are
+    // direction dependent, so with any pair every
+    // ordering is kept.
+    let closed = end_state == Some(start_state.as_str()) && prec.pairs.is_empty();
+    let heap = Heap::new(&mut data)
+        .filter(|p| !closed || p.len() < 2 || p[0] < p[p.len() - 1])
+        .filter(|p| allows(&route_of(p), &prec));
     let mut perm: Vec<&str> = Vec::new();
-    let data_len = data_look_up.len();
 
     for (iv, data) in heap.enumerate() {
         info!("Begin outer loop for heap");
@@ -478,32 +568,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             // match the position of
             // each vector item
-            match x {
-                0 => {
-                    sum = 0.0;
-                    // empty vector. clear previous entries
-                    perm.clear();
-                    let mut a = vec![IA]; // IA is first entry
-                    perm.append(&mut a);
-                    perm.push(_c); // insert first state
-                }
-                a if a == (nbr - 1) => {
-                    perm.push(_c); // at vector end, insert
-                                   // last state and then DC
-                    let mut b = vec![DC];
-                    perm.append(&mut b);
-                }
-                _ => {
-                    // else, insert states
-                    perm.push(_c);
-                }
+            if x == 0 {
+                sum = 0.0;
+                // empty vector. clear previous entries
+                perm.clear();
+                perm.push(&start_state); // start is first entry
+            }
+            perm.push(_c); // insert states
+            if x == nbr - 1 {
+                // at vector end, insert the end
+                // unless the path is open
+                perm.extend(end_state);
             }
 
             // next step is to lookup the
             // longitute and latitude of eadh
             // state in the vector to compute
             // final distance
-            if let DC = perm[perm.len() - 1] {
+            if x == nbr - 1 {
                 let numbr = perm.len();
 
                 for i in 1..numbr {
@@ -511,49 +593,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         // match guard for
                         // the expression
                         a if a > 0 => {
-                            for ii in 0..data_len {
-                                match &data_look_up[ii].state {
-                                    // Shift left to get previous value.
-                                    a if a == perm[i - 1] => {
-                                        // extract & error check longitude & latitude
-                                        _lon1 = match data_look_up[ii].longitude.parse::<f64>() {
-                                            Ok(_lon1) => _lon1,
-                                            Err(e) => {
-                                                let msg = "Error converting _lon1 to f64";
-                                                error!("{:?}: {:?}", msg, e);
-                                                panic!("{:?}: {:?}", msg, e);
-                                            }
-                                        };
-
-                                        _lat1 = match data_look_up[ii].latitude.parse::<f64>() {
-                                            Ok(_lat1) => _lat1,
-                                            Err(e) => {
-                                                let msg = "Error converting _lat1 to f64";
-                                                error!("{:?}: {:?}", msg, e);
-                                                panic!("{:?}: {:?}", msg, e);
-                                            }
-                                        };
-                                    }
-                                    b if b == perm[i] => {
-                                        _lon2 = match data_look_up[ii].longitude.parse::<f64>() {
-                                            Ok(_lon2) => _lon2,
-                                            Err(e) => {
-                                                let msg = "Error converting _lon2 to f64";
-                                                error!("{:?}: {:?}", msg, e);
-                                                panic!("{:?}: {:?}", msg, e);
-                                            }
-                                        };
-                                        _lat2 = match data_look_up[ii].latitude.parse::<f64>() {
-                                            Ok(_lat2) => _lat2,
-                                            Err(e) => {
-                                                let msg = "Error converting _lat2 to f64";
-                                                error!("{:?}: {:?}", msg, e);
-                                                panic!("{:?}: {:?}", msg, e);
-                                            }
-                                        };
-                                    }
-                                    _ => {}
-                                }
+                            // Shift left to get previous value.
+                            if let Some(rec) = index.first_in_state(perm[i - 1]) {
+                                _lat1 = rec.latitude;
+                                _lon1 = rec.longitude;
+                            }
+                            if let Some(rec) = index.first_in_state(perm[i]) {
+                                _lat2 = rec.latitude;
+                                _lon2 = rec.longitude;
                             }
 
                             let d2 = distance(_lat1, _lon1, _lat2, _lon2); // mod function (src/distance/mod.rs)
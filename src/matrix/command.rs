@@ -0,0 +1,49 @@
+// The distances sub command of the binary; the flags
+// and output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_value, split_list},
+    command::{stamp, write_json, write_rows, write_text},
+    lookup::{stop_records, ObjLookUp},
+    matrix::{distance_table, long_rows, to_markdown, wide_rows},
+};
+use log::info;
+use serde_json::json;
+
+const DISTANCES_CSV: &str = "distances.csv";
+const DISTANCES_LONG_CSV: &str = "distances_long.csv";
+const DISTANCES_JSON: &str = "distances.json";
+const DISTANCES_MD: &str = "distances.md";
+
+// distances: export the haversine miles between the
+// capitals as a wide csv (also a --matrix file), a long
+// csv with a row per pair, json and a Markdown table.
+//   --states IA,NY,...         stops of the table, state or
+//                              zip codes, in this order
+//                              (default: all, in lookup order)
+pub fn distances(args: &[String], data: &[ObjLookUp]) {
+    let idx = match flag_value(args, "--states") {
+        Some(list) => stop_records(data, &split_list(list)),
+        None => (0..data.len()).collect(),
+    };
+    let table = distance_table(data, &idx);
+
+    write_rows(DISTANCES_CSV, &wide_rows(&table));
+    write_rows(DISTANCES_LONG_CSV, &long_rows(&table));
+    write_text(DISTANCES_MD, &to_markdown(&table));
+
+    let obj = json!({
+        "states": table.states,
+        "miles": table.miles,
+    });
+    write_json(DISTANCES_JSON, &stamp(obj));
+    info!(
+        "{:?}x{:?} distance table written to {:?}, {:?}, {:?} and {:?}",
+        table.states.len(),
+        table.states.len(),
+        DISTANCES_CSV,
+        DISTANCES_LONG_CSV,
+        DISTANCES_JSON,
+        DISTANCES_MD
+    );
+}
@@ -0,0 +1,323 @@
+pub mod command;
+
+use crate::{
+    distance::haversine_dist,
+    lookup::{is_zip_name, lat_lon, stop_name, ObjLookUp},
+};
+use log::{error, info};
+use serde::Serialize;
+use std::fmt::Write;
+
+// Road miles are longer than great circle miles.
+// Multiply haversine miles by this to estimate driving.
+pub const ROAD_FACTOR: f64 = 1.2;
+// Average driving speed in miles per hour.
+pub const DRIVE_MPH: f64 = 55.0;
+
+// function builds the square matrix of haversine
+// miles between every pair of lookup records.
+pub fn distance_matrix(data: &[ObjLookUp]) -> Vec<Vec<f64>> {
+    let coords: Vec<(f64, f64)> = data.iter().map(lat_lon).collect();
+    let num = coords.len();
+    let mut matrix = vec![vec![0.0; num]; num];
+
+    for i in 0..num {
+        for j in (i + 1)..num {
+            let d = haversine_dist(coords[i].0, coords[i].1, coords[j].0, coords[j].1);
+            matrix[i][j] = d;
+            matrix[j][i] = d;
+        }
+    }
+    matrix
+}
+
+// function estimates driving hours from a
+// haversine mileage matrix.
+pub fn drive_hours_matrix(miles: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    miles
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|m| ((m * ROAD_FACTOR / DRIVE_MPH) * 10.0).round() / 10.0)
+                .collect()
+        })
+        .collect()
+}
+
+// function sums a route's legs using a matrix.
+pub fn route_total(route: &[usize], matrix: &[Vec<f64>]) -> f64 {
+    let sum: f64 = route.windows(2).map(|w| matrix[w[0]][w[1]]).sum();
+    (sum * 10.0).round() / 10.0
+}
+
+// function reads a wide cost matrix csv. The first
+// row holds the stop names as column labels and the
+// first column of every other row holds the row label.
+// A zip code labels its own record, a state code every
+// record of the state not labelled by its zip code.
+// The result is laid out over the lookup records so it
+// can be indexed the same way as distance_matrix.
+// Pairs missing from the file are left as NaN, see
+// missing_pairs.
+pub fn read_matrix_csv(path: &str, data: &[ObjLookUp]) -> Vec<Vec<f64>> {
+    let mut rdr = match csv::Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening matrix csv";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+
+    let labels: Vec<String> = match rdr.headers() {
+        Ok(h) => h.iter().skip(1).map(|s| s.trim().to_owned()).collect(),
+        Err(e) => {
+            let msg = "Error reading matrix csv header";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+
+    let num = data.len();
+    let mut matrix = vec![vec![f64::NAN; num]; num];
+    let covers = |label: &str, i: usize| match is_zip_name(label) {
+        true => data[i].zip_code == label,
+        false => data[i].state == label && !labels.contains(&data[i].zip_code),
+    };
+
+    for result in rdr.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let msg = "Error reading matrix csv row";
+                error!("{:?}: {:?}: {:?}", msg, path, e);
+                panic!("{:?}: {:?}: {:?}", msg, path, e)
+            }
+        };
+        let from = record.get(0).unwrap_or("").trim();
+
+        for (col, to) in labels.iter().enumerate() {
+            let cell = record.get(col + 1).unwrap_or("").trim();
+            if cell.is_empty() {
+                continue;
+            }
+            let value = match cell.parse::<f64>() {
+                Ok(value) if value.is_finite() => value,
+                Ok(value) => {
+                    let msg = "Matrix value is not a finite number";
+                    error!("{:?}: {:?} {:?}->{:?}: {:?}", msg, path, from, to, value);
+                    panic!("{:?}: {:?} {:?}->{:?}: {:?}", msg, path, from, to, value)
+                }
+                Err(e) => {
+                    let msg = "Error converting matrix value to f64";
+                    error!("{:?}: {:?} {:?}->{:?}: {:?}", msg, path, from, to, e);
+                    panic!("{:?}: {:?} {:?}->{:?}: {:?}", msg, path, from, to, e)
+                }
+            };
+            for i in (0..num).filter(|&i| covers(from, i)) {
+                for j in (0..num).filter(|&j| covers(to, j)) {
+                    matrix[i][j] = value;
+                }
+            }
+        }
+    }
+
+    // the diagonal may be left out of the file
+    for (i, row) in matrix.iter_mut().enumerate() {
+        if row[i].is_nan() {
+            row[i] = 0.0;
+        }
+    }
+    info!("Success read matrix csv {:?}", path);
+    matrix
+}
+
+// function lists the (from, to) stop pairs of a
+// matrix that have no value.
+pub fn missing_pairs(matrix: &[Vec<f64>], stops: &[usize]) -> Vec<(usize, usize)> {
+    let mut missing: Vec<(usize, usize)> = Vec::new();
+    for &i in stops {
+        for &j in stops {
+            if matrix[i][j].is_nan() {
+                missing.push((i, j));
+            }
+        }
+    }
+    missing
+}
+
+// function returns true when the cost between every
+// two stops is the same in both directions.
+pub fn is_symmetric(matrix: &[Vec<f64>], stops: &[usize]) -> bool {
+    stops
+        .iter()
+        .all(|&i| stops.iter().all(|&j| matrix[i][j] == matrix[j][i]))
+}
+
+// Haversine miles between stops, one row and one
+// column per stop name (lookup::stop_name), in the
+// order of `states`.
+#[derive(Serialize, Debug, Clone)]
+pub struct DistanceTable {
+    pub states: Vec<String>,
+    pub miles: Vec<Vec<f64>>,
+}
+
+// function builds the distance table of the given
+// lookup records, e.g. lookup::stop_records of a
+// list of states.
+pub fn distance_table(data: &[ObjLookUp], idx: &[usize]) -> DistanceTable {
+    let records: Vec<ObjLookUp> = idx.iter().map(|&i| data[i].clone()).collect();
+    DistanceTable {
+        states: idx.iter().map(|&i| stop_name(data, i)).collect(),
+        miles: distance_matrix(&records),
+    }
+}
+
+// function lays a table out as wide csv rows: a header
+// of state codes and a row per state, as read back by
+// read_matrix_csv.
+pub fn wide_rows(table: &DistanceTable) -> Vec<Vec<String>> {
+    let mut header = vec!["STATE".to_owned()];
+    header.extend(table.states.iter().cloned());
+    let mut rows = vec![header];
+    for (state, row) in table.states.iter().zip(&table.miles) {
+        let mut cells = vec![state.clone()];
+        cells.extend(row.iter().map(|m| format!("{:.1}", m)));
+        rows.push(cells);
+    }
+    rows
+}
+
+// function lays a table out as long csv rows, one per
+// ordered pair of different states.
+pub fn long_rows(table: &DistanceTable) -> Vec<Vec<String>> {
+    let mut rows = vec![vec!["FROM".to_owned(), "TO".to_owned(), "MILES".to_owned()]];
+    for (i, from) in table.states.iter().enumerate() {
+        for (j, to) in table.states.iter().enumerate() {
+            if i != j {
+                rows.push(vec![
+                    from.clone(),
+                    to.clone(),
+                    format!("{:.1}", table.miles[i][j]),
+                ]);
+            }
+        }
+    }
+    rows
+}
+
+// function renders a table as a Markdown table, miles
+// right aligned.
+pub fn to_markdown(table: &DistanceTable) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "| STATE | {} |", table.states.join(" | "));
+    let _ = writeln!(out, "|---|{}", "---:|".repeat(table.states.len()));
+    for (state, row) in table.states.iter().zip(&table.miles) {
+        let cells: Vec<String> = row.iter().map(|m| format!("{:.1}", m)).collect();
+        let _ = writeln!(out, "| {} | {} |", state, cells.join(" | "));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{read_look_up, state_index};
+
+    #[test]
+    fn distance_matrix_is_symmetric_with_a_zero_diagonal() {
+        let data = read_look_up("look_up.json");
+        let matrix = distance_matrix(&data);
+        let all: Vec<usize> = (0..data.len()).collect();
+        assert!(is_symmetric(&matrix, &all));
+        assert!(all.iter().all(|&i| matrix[i][i] == 0.0));
+        assert!(missing_pairs(&matrix, &all).is_empty());
+    }
+
+    #[test]
+    fn is_symmetric_only_looks_at_the_stops() {
+        let matrix = vec![
+            vec![0.0, 1.0, 7.0],
+            vec![1.0, 0.0, 2.0],
+            vec![9.0, 2.0, 0.0],
+        ];
+        assert!(is_symmetric(&matrix, &[0, 1]));
+        assert!(is_symmetric(&matrix, &[1, 2]));
+        assert!(!is_symmetric(&matrix, &[0, 1, 2]));
+    }
+
+    #[test]
+    fn a_closed_tour_costs_the_same_both_ways() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let matrix = distance_matrix(&data);
+        let tour = [at("IA"), at("NY"), at("TX"), at("CA"), at("IA")];
+        let mut back = tour;
+        back.reverse();
+        assert_eq!(route_total(&tour, &matrix), route_total(&back, &matrix));
+        let legs: f64 = tour.windows(2).map(|w| matrix[w[0]][w[1]]).sum();
+        assert_eq!(route_total(&tour, &matrix), (legs * 10.0).round() / 10.0);
+        assert_eq!(drive_hours_matrix(&[vec![0.0, 110.0]]), [vec![0.0, 2.4]]);
+    }
+
+    fn small_table() -> DistanceTable {
+        DistanceTable {
+            states: vec!["IA".to_owned(), "NE".to_owned()],
+            miles: vec![vec![0.0, 165.04], vec![165.04, 0.0]],
+        }
+    }
+
+    #[test]
+    fn distance_table_names_later_records_by_zip() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let second_ct = data.iter().rposition(|rec| rec.state == "CT").unwrap();
+        assert_ne!(second_ct, at("CT"));
+        let table = distance_table(&data, &[at("IA"), at("CT"), second_ct]);
+        assert_eq!(
+            table.states,
+            vec![
+                "IA".to_owned(),
+                "CT".to_owned(),
+                data[second_ct].zip_code.clone()
+            ]
+        );
+        let matrix = distance_matrix(&data);
+        assert_eq!(table.miles[0][1], matrix[at("IA")][at("CT")]);
+        assert_eq!(table.miles[2][0], matrix[second_ct][at("IA")]);
+        assert!(is_symmetric(&table.miles, &[0, 1, 2]));
+    }
+
+    #[test]
+    fn wide_and_long_rows() {
+        let table = small_table();
+        assert_eq!(
+            wide_rows(&table),
+            vec![
+                vec!["STATE", "IA", "NE"],
+                vec!["IA", "0.0", "165.0"],
+                vec!["NE", "165.0", "0.0"],
+            ]
+        );
+        assert_eq!(
+            long_rows(&table),
+            vec![
+                vec!["FROM", "TO", "MILES"],
+                vec!["IA", "NE", "165.0"],
+                vec!["NE", "IA", "165.0"],
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_right_aligns_the_miles() {
+        assert_eq!(
+            to_markdown(&small_table()),
+            "| STATE | IA | NE |\n\
+             |---|---:|---:|\n\
+             | IA | 0.0 | 165.0 |\n\
+             | NE | 165.0 | 0.0 |\n"
+        );
+    }
+}
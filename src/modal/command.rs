@@ -0,0 +1,102 @@
+// The modal sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    command::{
+        modal_params, read_airport_table, read_precedence, read_stops, route_stops, write_outputs,
+        MODAL_JSON,
+    },
+    lookup::{stop_name, ObjLookUp},
+    matrix::{distance_matrix, route_total},
+    modal::{best_modes, build_model, fastest_matrix, Mode},
+    precedence::check_ends,
+    solver::solve,
+    stops::{apart, repeat_legs, REPEAT_PENALTY},
+};
+use log::info;
+use serde_json::{json, Value};
+
+const MODAL_CSV: &str = "modal.csv";
+
+// modal: pick the visiting order and, per leg, whether
+// to drive or fly, minimizing total hours. Legs to or
+// from AK, HI or a territory can only be flown.
+//   --states IA,NY,...    states to visit (default: all)
+//   --airports file.csv   airport table (default: airports.csv)
+//   --drive-mph, --fly-mph, --airport-hours, --switch-hours
+//   --constraints file.json  precedence groups and stops
+pub fn modal(args: &[String], data: &[ObjLookUp], start: &str, end: Option<&str>) {
+    let stops = read_stops(args, data);
+    let (first, middle, last) = route_stops(args, data, start, end, &stops);
+    let prec = read_precedence(args, data);
+    check_ends(first, &middle, last, &prec, data);
+    let airports = read_airport_table(args);
+
+    let params = modal_params(args);
+    let model = build_model(data, &airports, &params);
+
+    // keep the visits of a revisited state apart
+    let seed = apart(&fastest_matrix(&model));
+    let (route, _) = solve(first, &middle, last, &seed, &prec, |r| {
+        best_modes(r, &model).1 + repeat_legs(r) as f64 * REPEAT_PENALTY
+    });
+    let (legs, total) = best_modes(&route, &model);
+    let miles = distance_matrix(data);
+
+    let mut rows: Vec<Vec<String>> = vec![vec![
+        "LEG".to_owned(),
+        "FROM".to_owned(),
+        "TO".to_owned(),
+        "MODE".to_owned(),
+        "FROM_AIRPORT".to_owned(),
+        "TO_AIRPORT".to_owned(),
+        "MILES".to_owned(),
+        "HOURS".to_owned(),
+    ]];
+    let mut json_legs: Vec<Value> = Vec::new();
+    for (k, leg) in legs.iter().enumerate() {
+        let (from_air, to_air) = match leg.mode {
+            Mode::Fly => (
+                airports[model.airport[leg.from].0].code.clone(),
+                airports[model.airport[leg.to].0].code.clone(),
+            ),
+            Mode::Drive => (String::new(), String::new()),
+        };
+        let mi = miles[leg.from][leg.to];
+        rows.push(vec![
+            (k + 1).to_string(),
+            stop_name(data, leg.from),
+            stop_name(data, leg.to),
+            leg.mode.as_str().to_owned(),
+            from_air.clone(),
+            to_air.clone(),
+            format!("{:.1}", mi),
+            format!("{:.1}", leg.hours),
+        ]);
+        json_legs.push(json!({
+            "leg": k + 1,
+            "from": stop_name(data, leg.from),
+            "to": stop_name(data, leg.to),
+            "mode": leg.mode,
+            "from_airport": from_air,
+            "to_airport": to_air,
+            "miles": mi,
+            "hours": (leg.hours * 10.0).round() / 10.0,
+        }));
+    }
+
+    let obj = json!({
+        "route": route.iter().map(|&i| stop_name(data, i)).collect::<Vec<String>>(),
+        "closed_tour": last == Some(first),
+        "legs": json_legs,
+        "total_hours": (total * 10.0).round() / 10.0,
+        "total_miles": route_total(&route, &miles),
+    });
+    write_outputs(MODAL_CSV, &rows, MODAL_JSON, obj);
+    info!(
+        "Multi-modal route: {:?} hour(s) written to {:?} and {:?}",
+        (total * 10.0).round() / 10.0,
+        MODAL_CSV,
+        MODAL_JSON
+    );
+}
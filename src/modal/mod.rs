@@ -0,0 +1,359 @@
+pub mod command;
+
+use crate::{
+    distance::haversine_dist,
+    lookup::{lat_lon, ObjLookUp},
+    territory::flight_only,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Airport {
+    pub code: String,
+    pub name: String,
+    pub city: String,
+    pub state: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Mode {
+    Drive,
+    Fly,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Drive => "DRIVE",
+            Mode::Fly => "FLY",
+        }
+    }
+}
+
+// Speeds and overheads of the multi-modal cost model.
+// Every cost is in hours.
+pub struct ModalParams {
+    pub drive_mph: f64,
+    pub fly_mph: f64,
+    // check-in, security, boarding and taxi per flight
+    pub airport_hours: f64,
+    // returning or renting a car when the mode changes
+    pub switch_hours: f64,
+    // road miles per haversine mile
+    pub road_factor: f64,
+}
+
+impl Default for ModalParams {
+    fn default() -> ModalParams {
+        ModalParams {
+            drive_mph: 55.0,
+            fly_mph: 450.0,
+            airport_hours: 2.5,
+            switch_hours: 1.0,
+            road_factor: 1.2,
+        }
+    }
+}
+
+// Per-leg hours for each mode, laid out over the
+// lookup records like matrix::distance_matrix.
+pub struct ModalModel {
+    pub drive: Vec<Vec<f64>>,
+    pub fly: Vec<Vec<f64>>,
+    pub switch_hours: f64,
+    // nearest airport (index into the airport table)
+    // and the miles to it for each lookup record
+    pub airport: Vec<(usize, f64)>,
+}
+
+// One leg of a solved multi-modal route.
+#[derive(Serialize, Debug, Clone)]
+pub struct Leg {
+    pub from: usize,
+    pub to: usize,
+    pub mode: Mode,
+    pub hours: f64,
+}
+
+// function reads the airport table (airports.csv).
+pub fn read_airports(path: &str) -> Vec<Airport> {
+    let mut rdr = match csv::Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening airport table";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let mut airports: Vec<Airport> = Vec::new();
+    for result in rdr.deserialize() {
+        match result {
+            Ok(airport) => airports.push(airport),
+            Err(e) => {
+                let msg = "Error reading airport table row";
+                error!("{:?}: {:?}: {:?}", msg, path, e);
+                panic!("{:?}: {:?}: {:?}", msg, path, e)
+            }
+        }
+    }
+    info!("Success read {:?} airports from {:?}", airports.len(), path);
+    airports
+}
+
+// function returns the index of the closest airport
+// to a coordinate and the haversine miles to it.
+pub fn nearest_airport(lat: f64, lon: f64, airports: &[Airport]) -> (usize, f64) {
+    let mut best = (0, f64::INFINITY);
+    for (i, a) in airports.iter().enumerate() {
+        let d = haversine_dist(lat, lon, a.latitude, a.longitude);
+        if d < best.1 {
+            best = (i, d);
+        }
+    }
+    best
+}
+
+// function returns the closest airport of every lookup
+// record and the haversine miles to it.
+pub fn nearest_airports(data: &[ObjLookUp], airports: &[Airport]) -> Vec<(usize, f64)> {
+    if airports.is_empty() {
+        let msg = "Airport table is empty";
+        error!("{:?}", msg);
+        panic!("{:?}", msg)
+    }
+    data.iter()
+        .map(lat_lon)
+        .map(|(lat, lon)| nearest_airport(lat, lon, airports))
+        .collect()
+}
+
+// function returns the hours of a flight between two
+// lookup records: drive to the nearest airport, airport
+// overhead, fly airport to airport, drive from the
+// airport. airport is the nearest_airports of the lookup.
+pub fn flight_hours(
+    i: usize,
+    j: usize,
+    airport: &[(usize, f64)],
+    airports: &[Airport],
+    params: &ModalParams,
+) -> f64 {
+    let access = |mi: f64| mi * params.road_factor / params.drive_mph;
+    let (a, b) = (&airports[airport[i].0], &airports[airport[j].0]);
+    let air = haversine_dist(a.latitude, a.longitude, b.latitude, b.longitude);
+    access(airport[i].1) + params.airport_hours + air / params.fly_mph + access(airport[j].1)
+}
+
+// function builds the drive and fly hour matrices, a
+// flight timed by flight_hours. Two capitals sharing an
+// airport cannot fly.
+pub fn build_model(data: &[ObjLookUp], airports: &[Airport], params: &ModalParams) -> ModalModel {
+    let coords: Vec<(f64, f64)> = data.iter().map(lat_lon).collect();
+    let airport = nearest_airports(data, airports);
+
+    let num = data.len();
+    let mut drive = vec![vec![0.0; num]; num];
+    let mut fly = vec![vec![0.0; num]; num];
+    for i in 0..num {
+        for j in 0..num {
+            if i == j {
+                continue;
+            }
+            let miles = haversine_dist(coords[i].0, coords[i].1, coords[j].0, coords[j].1);
+            // no road to or from AK, HI and the territories
+            drive[i][j] = match flight_only(data, i, j) {
+                true => f64::INFINITY,
+                false => miles * params.road_factor / params.drive_mph,
+            };
+
+            fly[i][j] = match airports[airport[i].0].code == airports[airport[j].0].code {
+                true => f64::INFINITY,
+                false => flight_hours(i, j, &airport, airports, params),
+            };
+        }
+    }
+    ModalModel {
+        drive,
+        fly,
+        switch_hours: params.switch_hours,
+        airport,
+    }
+}
+
+// function picks the mode of every leg of a fixed
+// route, accounting for the switch penalty, with a
+// two state dynamic program. Returns the legs and
+// the total hours.
+pub fn best_modes(route: &[usize], model: &ModalModel) -> (Vec<Leg>, f64) {
+    let legs = route.len().saturating_sub(1);
+    if legs == 0 {
+        return (Vec::new(), 0.0);
+    }
+    let modes = [Mode::Drive, Mode::Fly];
+    let leg_hours = |k: usize, m: usize| -> f64 {
+        let (i, j) = (route[k], route[k + 1]);
+        match modes[m] {
+            Mode::Drive => model.drive[i][j],
+            Mode::Fly => model.fly[i][j],
+        }
+    };
+
+    // best[k][m]: cheapest hours for legs 0..=k with leg k in mode m
+    let mut best = vec![[f64::INFINITY; 2]; legs];
+    let mut prev = vec![[0usize; 2]; legs];
+    best[0] = [leg_hours(0, 0), leg_hours(0, 1)];
+    for k in 1..legs {
+        for m in 0..2 {
+            for p in 0..2 {
+                let switch = if p == m { 0.0 } else { model.switch_hours };
+                let c = best[k - 1][p] + switch + leg_hours(k, m);
+                if c < best[k][m] {
+                    best[k][m] = c;
+                    prev[k][m] = p;
+                }
+            }
+        }
+    }
+
+    let mut m = if best[legs - 1][0] <= best[legs - 1][1] {
+        0
+    } else {
+        1
+    };
+    let total = best[legs - 1][m];
+    let mut chosen = vec![Mode::Drive; legs];
+    for k in (0..legs).rev() {
+        chosen[k] = modes[m];
+        m = prev[k][m];
+    }
+
+    let legs: Vec<Leg> = chosen
+        .iter()
+        .enumerate()
+        .map(|(k, &mode)| Leg {
+            from: route[k],
+            to: route[k + 1],
+            mode,
+            hours: leg_hours(k, if mode == Mode::Drive { 0 } else { 1 }),
+        })
+        .collect();
+    (legs, total)
+}
+
+// function returns, for every pair, the faster of
+// driving and flying. Used to seed the route search.
+pub fn fastest_matrix(model: &ModalModel) -> Vec<Vec<f64>> {
+    model
+        .drive
+        .iter()
+        .zip(&model.fly)
+        .map(|(d, f)| d.iter().zip(f).map(|(a, b)| a.min(*b)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::read_look_up;
+
+    // four stops on a line: driving is cheap between
+    // neighbours, flying cheap between the far ends
+    fn model(switch_hours: f64) -> ModalModel {
+        let drive = vec![
+            vec![0.0, 2.0, 8.0, 12.0],
+            vec![2.0, 0.0, 6.0, 10.0],
+            vec![8.0, 6.0, 0.0, 2.0],
+            vec![12.0, 10.0, 2.0, 0.0],
+        ];
+        let fly = vec![
+            vec![0.0, 5.0, 5.0, 5.5],
+            vec![5.0, 0.0, 4.0, 5.0],
+            vec![5.0, 4.0, 0.0, 5.0],
+            vec![5.5, 5.0, 5.0, 0.0],
+        ];
+        ModalModel {
+            drive,
+            fly,
+            switch_hours,
+            airport: vec![(0, 0.0); 4],
+        }
+    }
+
+    // the cheapest of every assignment of modes to legs
+    fn every_assignment(route: &[usize], model: &ModalModel) -> f64 {
+        let legs = route.len() - 1;
+        let mut best = f64::INFINITY;
+        for bits in 0..(1usize << legs) {
+            let mut total = 0.0;
+            for k in 0..legs {
+                let (i, j) = (route[k], route[k + 1]);
+                let fly = bits >> k & 1 == 1;
+                total += if fly {
+                    model.fly[i][j]
+                } else {
+                    model.drive[i][j]
+                };
+                if k > 0 && fly != (bits >> (k - 1) & 1 == 1) {
+                    total += model.switch_hours;
+                }
+            }
+            best = best.min(total);
+        }
+        best
+    }
+
+    #[test]
+    fn best_modes_matches_every_assignment() {
+        for switch in [0.0, 1.0, 10.0] {
+            let m = model(switch);
+            for route in [
+                vec![0, 1, 2, 3],
+                vec![0, 2, 1, 3],
+                vec![3, 0, 1],
+                vec![1, 3, 0, 2],
+            ] {
+                let (legs, total) = best_modes(&route, &m);
+                assert_eq!(legs.len(), route.len() - 1);
+                assert_eq!(total, every_assignment(&route, &m));
+            }
+        }
+    }
+
+    #[test]
+    fn switching_modes_costs_the_penalty() {
+        // the middle leg flies without a penalty, and
+        // the whole route drives with a large one
+        let (legs, total) = best_modes(&[0, 1, 2, 3], &model(0.0));
+        let modes: Vec<Mode> = legs.iter().map(|l| l.mode).collect();
+        assert_eq!(modes, vec![Mode::Drive, Mode::Fly, Mode::Drive]);
+        assert_eq!(total, 8.0);
+        let (legs, total) = best_modes(&[0, 1, 2, 3], &model(10.0));
+        assert!(legs.iter().all(|l| l.mode == Mode::Drive));
+        assert_eq!(total, 10.0);
+        assert_eq!(best_modes(&[2], &model(1.0)).1, 0.0);
+    }
+
+    #[test]
+    fn fastest_matrix_takes_the_faster_mode() {
+        let fast = fastest_matrix(&model(1.0));
+        assert_eq!(fast[0][3], 5.5);
+        assert_eq!(fast[0][1], 2.0);
+    }
+
+    #[test]
+    fn flights_reach_hawaii_and_driving_does_not() {
+        let data = read_look_up("look_up.json");
+        let airports = read_airports("airports.csv");
+        let m = build_model(&data, &airports, &ModalParams::default());
+        let at = |state: &str| data.iter().position(|r| r.state == state).unwrap();
+        let (hi, ia, ny) = (at("HI"), at("IA"), at("NY"));
+        assert!(m.drive[ia][hi].is_infinite());
+        assert!(m.fly[ia][hi].is_finite());
+        assert!(m.drive[ia][ny].is_finite());
+        assert_eq!(airports[m.airport[ia].0].state, "IA");
+    }
+}
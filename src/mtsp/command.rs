@@ -0,0 +1,121 @@
+// The mtsp sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_value, has_flag, split_list},
+    command::{read_precedence, write_outputs},
+    lookup::{stop_indices, stop_name, stop_records, ObjLookUp},
+    matrix::distance_matrix,
+    mtsp::{solve as share_states, Objective as MtspObjective, EXACT_LIMIT as MTSP_EXACT_LIMIT},
+};
+use log::{error, info};
+use serde_json::{json, Value};
+
+const MTSP_CSV: &str = "mtsp.csv";
+const MTSP_JSON: &str = "mtsp.json";
+
+// mtsp: share the states between several travelers,
+// each covering its own route from a depot.
+//   --depots IA,DC              one depot per traveler
+//                               (default: start and end, or
+//                               the start alone for an open
+//                               path or a closed tour)
+//   --states IA,NY,...          states to visit (default: all)
+//   --objective total|minmax    total miles or longest route
+//   --return                    travelers return to their depot
+// The states are shared out without an order between
+// travelers, so precedence groups in `--constraints`
+// are refused.
+pub fn mtsp(args: &[String], data: &[ObjLookUp], start: &str, end: Option<&str>) {
+    let depots: Vec<usize> = match flag_value(args, "--depots") {
+        Some(list) => stop_indices(data, &split_list(list)),
+        None => match end {
+            Some(end) if end != start => stop_indices(data, &[start, end]),
+            _ => stop_indices(data, &[start]),
+        },
+    };
+    if depots.is_empty() {
+        let msg = "Expected at least one depot in --depots";
+        error!("{:?}: {:?}", msg, flag_value(args, "--depots"));
+        panic!("{:?}: {:?}", msg, flag_value(args, "--depots"))
+    }
+    let prec = read_precedence(args, data);
+    if !prec.pairs.is_empty() {
+        let msg = "mtsp does not keep precedence groups, drop them from --constraints";
+        error!("{:?}: {:?} pair(s)", msg, prec.pairs.len());
+        panic!("{:?}: {:?} pair(s)", msg, prec.pairs.len())
+    }
+    let objective = match flag_value(args, "--objective").unwrap_or("total") {
+        "total" => MtspObjective::Total,
+        "minmax" => MtspObjective::MinMax,
+        other => {
+            let msg = "Expected --objective total or minmax";
+            error!("{:?}: {:?}", msg, other);
+            panic!("{:?}: {:?}", msg, other)
+        }
+    };
+    let closed = has_flag(args, "--return");
+
+    let candidates: Vec<usize> = match flag_value(args, "--states") {
+        Some(list) => stop_records(data, &split_list(list)),
+        None => (0..data.len()).collect(),
+    };
+    // the depots are not stops to share
+    let middle: Vec<usize> = candidates
+        .into_iter()
+        .filter(|i| !depots.contains(i))
+        .collect();
+    info!(
+        "mTSP: {:?} traveler(s), {:?} states, objective {:?}",
+        depots.len(),
+        middle.len(),
+        objective.as_str()
+    );
+
+    let miles = distance_matrix(data);
+    let plan = share_states(&depots, &middle, closed, &miles, objective);
+
+    let mut rows: Vec<Vec<String>> = vec![["TRAVELER", "DEPOT", "LEG", "FROM", "TO", "MILES"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()];
+    let mut travelers: Vec<Value> = Vec::new();
+    for (t, route) in plan.routes.iter().enumerate() {
+        for (k, w) in route.windows(2).enumerate() {
+            rows.push(vec![
+                (t + 1).to_string(),
+                stop_name(data, depots[t]),
+                (k + 1).to_string(),
+                stop_name(data, w[0]),
+                stop_name(data, w[1]),
+                format!("{:.1}", miles[w[0]][w[1]]),
+            ]);
+        }
+        travelers.push(json!({
+            "traveler": t + 1,
+            "depot": stop_name(data, depots[t]),
+            "route": route.iter().map(|&i| stop_name(data, i)).collect::<Vec<String>>(),
+            "states": route.len() - 1 - closed as usize,
+            "miles": plan.miles[t],
+        }));
+    }
+
+    let total: f64 = plan.miles.iter().sum();
+    let longest = plan.miles.iter().cloned().fold(0.0, f64::max);
+    let obj = json!({
+        "objective": objective.as_str(),
+        "exact": middle.len() <= MTSP_EXACT_LIMIT,
+        "closed_tour": closed,
+        "travelers": travelers,
+        "total_miles": (total * 10.0).round() / 10.0,
+        "longest_miles": longest,
+    });
+    write_outputs(MTSP_CSV, &rows, MTSP_JSON, obj);
+    info!(
+        "mTSP plan of {:?} mi (longest {:?} mi) written to {:?} and {:?}",
+        (total * 10.0).round() / 10.0,
+        longest,
+        MTSP_CSV,
+        MTSP_JSON
+    );
+}
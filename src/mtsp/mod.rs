@@ -0,0 +1,431 @@
+pub mod command;
+
+use crate::{
+    matrix::route_total,
+    precedence::Precedence,
+    solver::{full_route, local_search, nearest_order},
+};
+use log::{debug, info};
+use permutohedron::Heap;
+
+// Instances with at most this many states to share
+// out are searched exhaustively.
+pub const EXACT_LIMIT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Objective {
+    // sum of every traveler's miles
+    Total,
+    // miles of the longest route
+    MinMax,
+}
+
+impl Objective {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Objective::Total => "TOTAL",
+            Objective::MinMax => "MINMAX",
+        }
+    }
+}
+
+// One route per traveler. routes[t] starts at the
+// depot of traveler t and returns there when `closed`.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub routes: Vec<Vec<usize>>,
+    pub miles: Vec<f64>,
+}
+
+// function returns a traveler's route for its share.
+fn traveler_route(depot: usize, share: &[usize], closed: bool) -> Vec<usize> {
+    let end = match closed {
+        true => Some(depot),
+        false => None,
+    };
+    full_route(depot, share, end)
+}
+
+fn miles_of(route: &[usize], matrix: &[Vec<f64>]) -> f64 {
+    route.windows(2).map(|w| matrix[w[0]][w[1]]).sum()
+}
+
+// function scores the miles of every route.
+pub fn score(miles: &[f64], objective: Objective) -> f64 {
+    match objective {
+        Objective::Total => miles.iter().sum(),
+        Objective::MinMax => miles.iter().cloned().fold(0.0, f64::max),
+    }
+}
+
+fn plan_of(routes: Vec<Vec<usize>>, matrix: &[Vec<f64>]) -> Plan {
+    let miles = routes.iter().map(|r| route_total(r, matrix)).collect();
+    Plan { routes, miles }
+}
+
+// function splits an ordering of the states into one
+// consecutive share per traveler, in traveler order,
+// minimizing the objective (dynamic program over the
+// cut positions).
+pub fn split(
+    order: &[usize],
+    depots: &[usize],
+    closed: bool,
+    matrix: &[Vec<f64>],
+    objective: Objective,
+) -> Vec<Vec<usize>> {
+    let num = order.len();
+    let k = depots.len();
+    // best[t][i]: best score of the first t travelers
+    // covering order[..i]
+    let mut best = vec![vec![f64::INFINITY; num + 1]; k + 1];
+    let mut cut = vec![vec![0usize; num + 1]; k + 1];
+    best[0][0] = 0.0;
+    for t in 1..=k {
+        for i in 0..=num {
+            for j in 0..=i {
+                if best[t - 1][j].is_infinite() {
+                    continue;
+                }
+                let route = traveler_route(depots[t - 1], &order[j..i], closed);
+                let m = miles_of(&route, matrix);
+                let c = match objective {
+                    Objective::Total => best[t - 1][j] + m,
+                    Objective::MinMax => best[t - 1][j].max(m),
+                };
+                if c < best[t][i] {
+                    best[t][i] = c;
+                    cut[t][i] = j;
+                }
+            }
+        }
+    }
+    let mut shares: Vec<Vec<usize>> = vec![Vec::new(); k];
+    let mut i = num;
+    for t in (1..=k).rev() {
+        let j = cut[t][i];
+        shares[t - 1] = order[j..i].to_vec();
+        i = j;
+    }
+    shares
+}
+
+// function tries every ordering of the states and, for
+// each, every way of cutting it into one share per
+// traveler.
+pub fn exact(
+    depots: &[usize],
+    middle: &[usize],
+    closed: bool,
+    matrix: &[Vec<f64>],
+    objective: Objective,
+) -> Plan {
+    let mut data = middle.to_vec();
+    let mut best_routes = split(&data, depots, closed, matrix, objective)
+        .iter()
+        .enumerate()
+        .map(|(t, s)| traveler_route(depots[t], s, closed))
+        .collect::<Vec<Vec<usize>>>();
+    let mut best = score(
+        &best_routes
+            .iter()
+            .map(|r| miles_of(r, matrix))
+            .collect::<Vec<f64>>(),
+        objective,
+    );
+
+    if data.len() > 1 {
+        let heap = Heap::new(&mut data);
+        for (iv, perm) in heap.enumerate() {
+            let routes: Vec<Vec<usize>> = split(&perm, depots, closed, matrix, objective)
+                .iter()
+                .enumerate()
+                .map(|(t, s)| traveler_route(depots[t], s, closed))
+                .collect();
+            let miles: Vec<f64> = routes.iter().map(|r| miles_of(r, matrix)).collect();
+            let c = score(&miles, objective);
+            debug!("mtsp exact #{:?}: {:?} {:?}", iv, routes, c);
+            if c < best {
+                best = c;
+                best_routes = routes;
+            }
+        }
+    }
+    info!("mTSP exact best: {:?}", best);
+    plan_of(best_routes, matrix)
+}
+
+// function improves every route on its own with
+// 2-opt and relocate moves.
+fn improve_routes(routes: &mut [Vec<usize>], closed: bool, matrix: &[Vec<f64>]) {
+    let none = Precedence::default();
+    for route in routes.iter_mut() {
+        let (better, _) = local_search(route.clone(), !closed, &none, |r| miles_of(r, matrix));
+        *route = better;
+    }
+}
+
+// function builds a giant route through every state,
+// splits it between the travelers (forwards and
+// backwards) and then moves single states between
+// routes while the objective improves.
+pub fn heuristic(
+    depots: &[usize],
+    middle: &[usize],
+    closed: bool,
+    matrix: &[Vec<f64>],
+    objective: Objective,
+) -> Plan {
+    // a short open path from the first depot, split as
+    // it is and reversed
+    let none = Precedence::default();
+    let seed = full_route(
+        depots[0],
+        &nearest_order(depots[0], middle, matrix, &none),
+        None,
+    );
+    let (giant, _) = local_search(seed, true, &none, |r| miles_of(r, matrix));
+    let mut reversed = giant[1..].to_vec();
+    reversed.reverse();
+    let mut routes: Vec<Vec<usize>> = Vec::new();
+    let mut best = f64::INFINITY;
+    for order in [&giant[1..], &reversed[..]].iter() {
+        let mut trial: Vec<Vec<usize>> = split(order, depots, closed, matrix, objective)
+            .iter()
+            .enumerate()
+            .map(|(t, s)| traveler_route(depots[t], s, closed))
+            .collect();
+        improve_routes(&mut trial, closed, matrix);
+        let miles: Vec<f64> = trial.iter().map(|r| miles_of(r, matrix)).collect();
+        if score(&miles, objective) < best {
+            best = score(&miles, objective);
+            routes = trial;
+        }
+    }
+
+    let fixed = closed as usize;
+    let mut miles: Vec<f64> = routes.iter().map(|r| miles_of(r, matrix)).collect();
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for a in 0..routes.len() {
+            let mut i = 1;
+            while i < routes[a].len() - fixed {
+                let s = routes[a].remove(i);
+                let from_miles = miles_of(&routes[a], matrix);
+                // cheapest spot in every other route
+                let mut choice: Option<(usize, usize, f64)> = None;
+                for b in (0..routes.len()).filter(|&b| b != a) {
+                    for pos in 1..=(routes[b].len() - fixed) {
+                        routes[b].insert(pos, s);
+                        let mut trial = miles.clone();
+                        trial[a] = from_miles;
+                        trial[b] = miles_of(&routes[b], matrix);
+                        let c = score(&trial, objective);
+                        routes[b].remove(pos);
+                        let better = match choice {
+                            Some((_, _, x)) => c < x,
+                            None => c < best - 1e-9,
+                        };
+                        if better {
+                            choice = Some((b, pos, c));
+                        }
+                    }
+                }
+                match choice {
+                    Some((b, pos, c)) => {
+                        routes[b].insert(pos, s);
+                        miles[a] = from_miles;
+                        miles[b] = miles_of(&routes[b], matrix);
+                        best = c;
+                        improved = true;
+                    }
+                    None => {
+                        routes[a].insert(i, s);
+                        i += 1;
+                    }
+                }
+            }
+        }
+        if improved {
+            improve_routes(&mut routes, closed, matrix);
+            miles = routes.iter().map(|r| miles_of(r, matrix)).collect();
+            best = score(&miles, objective);
+        }
+        debug!("mtsp heuristic pass: {:?}", best);
+    }
+    info!("mTSP heuristic best: {:?}", best);
+    plan_of(routes, matrix)
+}
+
+// function shares the states between the travelers:
+// exhaustive search for small instances, otherwise
+// the split and exchange heuristic.
+pub fn solve(
+    depots: &[usize],
+    middle: &[usize],
+    closed: bool,
+    matrix: &[Vec<f64>],
+    objective: Objective,
+) -> Plan {
+    match middle.len() {
+        a if a <= EXACT_LIMIT => exact(depots, middle, closed, matrix, objective),
+        _ => heuristic(depots, middle, closed, matrix, objective),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random::XorShift, solver::brute_force};
+
+    fn random_matrix(rng: &mut XorShift, num: usize) -> Vec<Vec<f64>> {
+        (0..num)
+            .map(|i| {
+                (0..num)
+                    .map(|j| match i == j {
+                        true => 0.0,
+                        false => (rng.below(1000) + 1) as f64,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn plan_score(routes: &[Vec<usize>], matrix: &[Vec<f64>], objective: Objective) -> f64 {
+        let miles: Vec<f64> = routes.iter().map(|r| miles_of(r, matrix)).collect();
+        score(&miles, objective)
+    }
+
+    // every way of cutting order into k consecutive
+    // shares, as the k + 1 cut positions
+    fn all_cuts(num: usize, k: usize) -> Vec<Vec<usize>> {
+        let mut cuts = vec![vec![0]];
+        for _ in 1..k {
+            cuts = cuts
+                .iter()
+                .flat_map(|c| (c[c.len() - 1]..=num).map(move |j| [&c[..], &[j]].concat()))
+                .collect();
+        }
+        cuts.iter().map(|c| [&c[..], &[num]].concat()).collect()
+    }
+
+    // every assignment of the states to the travelers,
+    // each share in its best order
+    fn brute_force_plan(
+        depots: &[usize],
+        middle: &[usize],
+        closed: bool,
+        matrix: &[Vec<f64>],
+        objective: Objective,
+    ) -> f64 {
+        let k = depots.len();
+        let mut best = f64::INFINITY;
+        for code in 0..k.pow(middle.len() as u32) {
+            let mut shares: Vec<Vec<usize>> = vec![Vec::new(); k];
+            let mut c = code;
+            for &s in middle {
+                shares[c % k].push(s);
+                c /= k;
+            }
+            let miles: Vec<f64> = shares
+                .iter()
+                .zip(depots)
+                .map(|(share, &depot)| {
+                    let end = match closed {
+                        true => Some(depot),
+                        false => None,
+                    };
+                    let (route, _) = brute_force(depot, share, end, &Precedence::default(), |r| {
+                        miles_of(r, matrix)
+                    });
+                    miles_of(&route, matrix)
+                })
+                .collect();
+            best = best.min(score(&miles, objective));
+        }
+        best
+    }
+
+    #[test]
+    fn scores_the_total_or_the_longest_route() {
+        assert_eq!(score(&[3.0, 5.0, 1.0], Objective::Total), 9.0);
+        assert_eq!(score(&[3.0, 5.0, 1.0], Objective::MinMax), 5.0);
+    }
+
+    #[test]
+    fn split_finds_the_best_cuts() {
+        assert_eq!(all_cuts(2, 2), [[0, 0, 2], [0, 1, 2], [0, 2, 2]]);
+        let mut rng = XorShift::new(35);
+        for _ in 0..30 {
+            let k = 1 + rng.below(3);
+            let num = rng.below(7);
+            let matrix = random_matrix(&mut rng, num + k);
+            let depots: Vec<usize> = (num..num + k).collect();
+            let mut order: Vec<usize> = (0..num).collect();
+            rng.shuffle(&mut order);
+            for &closed in &[true, false] {
+                for &objective in &[Objective::Total, Objective::MinMax] {
+                    let shares = split(&order, &depots, closed, &matrix, objective);
+                    assert_eq!(shares.concat(), order);
+                    let routes = |cut: &[usize]| -> Vec<Vec<usize>> {
+                        (0..k)
+                            .map(|t| traveler_route(depots[t], &order[cut[t]..cut[t + 1]], closed))
+                            .collect()
+                    };
+                    let best = all_cuts(num, k)
+                        .iter()
+                        .map(|c| plan_score(&routes(c), &matrix, objective))
+                        .fold(f64::INFINITY, f64::min);
+                    let found: Vec<Vec<usize>> = shares
+                        .iter()
+                        .enumerate()
+                        .map(|(t, s)| traveler_route(depots[t], s, closed))
+                        .collect();
+                    assert!((plan_score(&found, &matrix, objective) - best).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_matches_every_assignment() {
+        let mut rng = XorShift::new(135);
+        for _ in 0..10 {
+            let k = 2 + rng.below(2);
+            let num = 1 + rng.below(5);
+            let matrix = random_matrix(&mut rng, num + k);
+            let depots: Vec<usize> = (num..num + k).collect();
+            let middle: Vec<usize> = (0..num).collect();
+            for &closed in &[true, false] {
+                for &objective in &[Objective::Total, Objective::MinMax] {
+                    let plan = exact(&depots, &middle, closed, &matrix, objective);
+                    let best = brute_force_plan(&depots, &middle, closed, &matrix, objective);
+                    assert!((plan_score(&plan.routes, &matrix, objective) - best).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn heuristic_visits_every_state_once_from_its_depot() {
+        let mut rng = XorShift::new(235);
+        let num = 14;
+        let matrix = random_matrix(&mut rng, num + 3);
+        let depots = [num, num + 1, num + 2];
+        let middle: Vec<usize> = (0..num).collect();
+        for &closed in &[true, false] {
+            let plan = solve(&depots, &middle, closed, &matrix, Objective::MinMax);
+            let mut visited: Vec<usize> = Vec::new();
+            for (route, &depot) in plan.routes.iter().zip(&depots) {
+                assert_eq!(route[0], depot);
+                assert_eq!(closed, route.len() > 1 && route[route.len() - 1] == depot);
+                let fixed = 1 + closed as usize;
+                visited.extend_from_slice(&route[1..route.len() + 1 - fixed]);
+            }
+            visited.sort_unstable();
+            assert_eq!(visited, middle);
+            assert_eq!(plan.miles.len(), 3);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// The pairs sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::flag_value,
+    command::{write_json, write_rows, ZIP_TABLE},
+    csv::path_exists,
+    index::LookupIndex,
+    lookup::ObjStates,
+    pairs::{distances as pair_distances, is_state_code, PairResult, Sources},
+    zips::read_zip_csv,
+};
+use chrono::prelude::*;
+use log::info;
+
+const PAIRS_CSV: &str = "pairs.csv";
+const PAIRS_JSON: &str = "pairs.json";
+
+// pairs: the distance of every states.json entry, run
+// with the default route. An end is a state code, a
+// zip code or a city name ("SPRINGFIELD, IL"); zip codes
+// and cities not in the lookup are found in the zip
+// code table.
+//   --table file.csv           zip code table (default: codes.csv)
+pub fn pairs(
+    args: &[String],
+    pairs: &[ObjStates],
+    index: &LookupIndex,
+    now: DateTime<Utc>,
+) -> Vec<PairResult> {
+    let table_path = flag_value(args, "--table").unwrap_or(ZIP_TABLE);
+    // the table is only read for a zip code or city
+    let wanted = pairs
+        .iter()
+        .any(|p| !is_state_code(&p.from_state) || !is_state_code(&p.to_state));
+    let table = match wanted && path_exists(table_path) {
+        true => Some(LookupIndex::new(read_zip_csv(table_path))),
+        false => None,
+    };
+    let sources = Sources {
+        look_up: index,
+        look_up_name: flag_value(args, "--lookup").unwrap_or("look_up.json"),
+        table: table.as_ref(),
+        table_name: table_path,
+    };
+    let results = pair_distances(pairs, &sources, now);
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "PAIR",
+        "FROM",
+        "FROM_KIND",
+        "FROM_STATE",
+        "FROM_ZIP",
+        "TO",
+        "TO_KIND",
+        "TO_STATE",
+        "TO_ZIP",
+        "MILES",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    for r in &results {
+        rows.push(vec![
+            r.pair.to_string(),
+            r.from.input.clone(),
+            r.from.kind.as_str().to_owned(),
+            r.from.state.clone(),
+            r.from.zip_code.clone(),
+            r.to.input.clone(),
+            r.to.kind.as_str().to_owned(),
+            r.to.state.clone(),
+            r.to.zip_code.clone(),
+            r.miles.map(|m| format!("{:.1}", m)).unwrap_or_default(),
+        ]);
+    }
+    write_rows(PAIRS_CSV, &rows);
+    write_json(PAIRS_JSON, &results);
+    info!(
+        "{:?} pair(s), {:?} not found, written to {:?} and {:?}",
+        results.len(),
+        results.iter().filter(|r| r.miles.is_none()).count(),
+        PAIRS_CSV,
+        PAIRS_JSON
+    );
+    results
+}
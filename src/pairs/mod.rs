@@ -0,0 +1,361 @@
+pub mod command;
+
+use crate::{
+    distance::haversine_dist,
+    geocode::{geocode, Geocode, Precision},
+    index::LookupIndex,
+    lookup::ObjStates,
+    timezone::{local_string, zone_at},
+};
+use chrono::prelude::*;
+use log::{error, info, warn};
+use serde::Serialize;
+
+// How an endpoint of states.json was found: by state
+// code (the first lookup record of the state), by zip
+// code or by city name.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    State,
+    Zip,
+    City,
+    None,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::State => "STATE",
+            Kind::Zip => "ZIP",
+            Kind::City => "CITY",
+            Kind::None => "NONE",
+        }
+    }
+}
+
+// One end of a pair as resolved. `source` names the
+// records it was found in; the coordinates are None
+// when it was not found.
+#[derive(Serialize, Debug, Clone)]
+pub struct Endpoint {
+    pub input: String,
+    pub kind: Kind,
+    pub source: String,
+    pub state: String,
+    pub city: String,
+    pub zip_code: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub confidence: f64,
+    pub time_zone: String,
+    pub local_time: String,
+    pub warnings: Vec<String>,
+}
+
+// The distance between the two ends of a states.json
+// entry; `pair` counts the entries from 1.
+#[derive(Serialize, Debug, Clone)]
+pub struct PairResult {
+    pub pair: usize,
+    pub from: Endpoint,
+    pub to: Endpoint,
+    pub miles: Option<f64>,
+    pub time_created: String,
+    pub time_created_utc: String,
+}
+
+// The records endpoints are resolved against: the
+// lookup, then a zip code table if one was loaded.
+pub struct Sources<'a> {
+    pub look_up: &'a LookupIndex,
+    pub look_up_name: &'a str,
+    pub table: Option<&'a LookupIndex>,
+    pub table_name: &'a str,
+}
+
+// function returns true for an input that names a
+// state by its 2 letter code.
+pub fn is_state_code(input: &str) -> bool {
+    let input = input.trim();
+    input.len() == 2 && input.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn unresolved(input: &str, source: &str, warnings: Vec<String>) -> Endpoint {
+    Endpoint {
+        input: input.to_owned(),
+        kind: Kind::None,
+        source: source.to_owned(),
+        state: String::new(),
+        city: String::new(),
+        zip_code: String::new(),
+        latitude: None,
+        longitude: None,
+        confidence: 0.0,
+        time_zone: String::new(),
+        local_time: String::new(),
+        warnings,
+    }
+}
+
+fn from_geocode(found: Geocode, source: &str) -> Endpoint {
+    let best = match found.best {
+        Some(best) => best,
+        None => return unresolved(&found.input, source, found.warnings),
+    };
+    Endpoint {
+        input: found.input,
+        kind: match found.precision {
+            Precision::Zip => Kind::Zip,
+            Precision::City => Kind::City,
+            Precision::None => Kind::None,
+        },
+        source: source.to_owned(),
+        state: best.state,
+        city: best.city,
+        zip_code: best.zip_code,
+        latitude: Some(best.latitude),
+        longitude: Some(best.longitude),
+        confidence: found.confidence,
+        time_zone: String::new(),
+        local_time: String::new(),
+        warnings: found.warnings,
+    }
+}
+
+// function resolves one endpoint. A state code is the
+// first lookup record of the state, as output.json has
+// always used; a zip code or city name is geocoded
+// against the lookup and, unless that finds the exact
+// zip code or city, against the zip code table. The
+// geocoder logs its own warnings.
+pub fn resolve(input: &str, sources: &Sources, now: DateTime<Utc>) -> Endpoint {
+    let mut end = match is_state_code(input) {
+        true => match sources.look_up.first_in_state(input) {
+            Some(rec) => Endpoint {
+                input: input.to_owned(),
+                kind: Kind::State,
+                source: sources.look_up_name.to_owned(),
+                state: rec.state.clone(),
+                city: rec.city.clone(),
+                zip_code: rec.zip_code.clone(),
+                latitude: Some(rec.latitude),
+                longitude: Some(rec.longitude),
+                confidence: 1.0,
+                time_zone: String::new(),
+                local_time: String::new(),
+                warnings: Vec::new(),
+            },
+            None => {
+                warn!("State not found in lookup: {:?}", input);
+                unresolved(
+                    input,
+                    sources.look_up_name,
+                    vec![format!("state {} is not in the lookup", input.trim())],
+                )
+            }
+        },
+        false => {
+            let local = geocode(sources.look_up, input);
+            let exact = matches!(&local.best, Some(b) if b.edits == 0);
+            match sources.table {
+                Some(table) if !exact => {
+                    let wide = geocode(table, input);
+                    match wide.best.is_some() {
+                        true => from_geocode(wide, sources.table_name),
+                        false => from_geocode(local, sources.look_up_name),
+                    }
+                }
+                _ => from_geocode(local, sources.look_up_name),
+            }
+        }
+    };
+    if let (Some(lat), Some(lon)) = (end.latitude, end.longitude) {
+        if let Some(zone) = zone_at(&end.state, lat, lon) {
+            end.time_zone = zone.name.to_owned();
+            end.local_time = local_string(zone, now);
+        }
+    }
+    end
+}
+
+// function resolves both ends of every states.json
+// entry and returns the miles between them by
+// haversine_dist, None when an end was not found.
+// Panics on an entry with an empty end.
+pub fn distances(pairs: &[ObjStates], sources: &Sources, now: DateTime<Utc>) -> Vec<PairResult> {
+    let dt = format!("{}", Local::now().format("%a %b %e %T %Y"));
+    let mut results: Vec<PairResult> = Vec::with_capacity(pairs.len());
+    for (k, pair) in pairs.iter().enumerate() {
+        if pair.from_state.trim().is_empty() || pair.to_state.trim().is_empty() {
+            let msg = "Empty from_state or to_state in states.json";
+            error!("{:?}: {:?}", msg, k + 1);
+            panic!("{:?}: {:?}", msg, k + 1)
+        }
+        let from = resolve(&pair.from_state, sources, now);
+        let to = resolve(&pair.to_state, sources, now);
+        let miles = match (from.latitude, from.longitude, to.latitude, to.longitude) {
+            (Some(lat1), Some(lon1), Some(lat2), Some(lon2)) => {
+                Some(haversine_dist(lat1, lon1, lat2, lon2))
+            }
+            _ => None,
+        };
+        info!(
+            "Pair {:?}: {:?} {:?} to {:?} {:?}: {:?} mi",
+            k + 1,
+            pair.from_state,
+            from.zip_code,
+            pair.to_state,
+            to.zip_code,
+            miles.map(|m| (m * 10.0).round() / 10.0)
+        );
+        results.push(PairResult {
+            pair: k + 1,
+            from,
+            to,
+            miles,
+            time_created: dt.clone(),
+            time_created_utc: now.to_rfc3339(),
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lookup::test_record, zips::ZipRecord};
+
+    fn record(zip_code: &str, city: &str, state: &str, lat: f64, lon: f64) -> ZipRecord {
+        ZipRecord {
+            city: city.to_owned(),
+            latitude: lat,
+            longitude: lon,
+            ..ZipRecord::from(&test_record(zip_code, state))
+        }
+    }
+
+    fn look_up() -> LookupIndex {
+        LookupIndex::new(vec![
+            record("50319", "DES MOINES", "IA", 41.6006, -93.6087),
+            record("62701", "SPRINGFIELD", "IL", 39.80, -89.65),
+        ])
+    }
+
+    fn table() -> LookupIndex {
+        LookupIndex::new(vec![
+            record("50319", "DES MOINES", "IA", 41.6006, -93.6087),
+            record("62701", "SPRINGFIELD", "IL", 39.80, -89.65),
+            record("90210", "BEVERLY HILLS", "CA", 34.09, -118.41),
+        ])
+    }
+
+    fn pair(from: &str, to: &str) -> ObjStates {
+        ObjStates {
+            from_state: from.to_owned(),
+            to_state: to.to_owned(),
+            start: None,
+            end: None,
+        }
+    }
+
+    fn winter() -> DateTime<Utc> {
+        Utc.ymd(2024, 1, 15).and_hms(18, 0, 0)
+    }
+
+    #[test]
+    fn state_codes_are_two_letters() {
+        assert!(is_state_code("IA"));
+        assert!(is_state_code(" ia "));
+        assert!(!is_state_code("I"));
+        assert!(!is_state_code("IOWA"));
+        assert!(!is_state_code("50"));
+        assert!(!is_state_code(""));
+    }
+
+    #[test]
+    fn a_state_code_is_the_first_lookup_record() {
+        let (look_up, table) = (look_up(), table());
+        let sources = Sources {
+            look_up: &look_up,
+            look_up_name: "look_up.json",
+            table: Some(&table),
+            table_name: "zip_codes.csv",
+        };
+        let end = resolve("IA", &sources, winter());
+        assert_eq!(end.kind, Kind::State);
+        assert_eq!(end.source, "look_up.json");
+        assert_eq!(
+            (end.city.as_str(), end.zip_code.as_str()),
+            ("DES MOINES", "50319")
+        );
+        assert_eq!(end.confidence, 1.0);
+        assert_eq!(end.time_zone, "America/Chicago");
+        assert_eq!(end.local_time, "2024-01-15 12:00:00 CST");
+        assert!(end.warnings.is_empty());
+
+        let end = resolve("ZZ", &sources, winter());
+        assert_eq!(end.kind, Kind::None);
+        assert_eq!((end.latitude, end.longitude), (None, None));
+        assert_eq!(end.warnings, vec!["state ZZ is not in the lookup"]);
+        assert_eq!(end.time_zone, "");
+    }
+
+    #[test]
+    fn the_zip_table_is_asked_only_when_the_lookup_misses() {
+        let (look_up, table) = (look_up(), table());
+        let sources = Sources {
+            look_up: &look_up,
+            look_up_name: "look_up.json",
+            table: Some(&table),
+            table_name: "zip_codes.csv",
+        };
+        let end = resolve("62701", &sources, winter());
+        assert_eq!((end.kind, end.source.as_str()), (Kind::Zip, "look_up.json"));
+
+        let end = resolve("90210", &sources, winter());
+        assert_eq!(end.kind, Kind::Zip);
+        assert_eq!(end.source, "zip_codes.csv");
+        assert_eq!(end.state, "CA");
+        assert_eq!(end.time_zone, "America/Los_Angeles");
+
+        let alone = Sources {
+            table: None,
+            ..sources
+        };
+        let end = resolve("90210", &alone, winter());
+        assert_eq!(end.kind, Kind::None);
+        assert_eq!(end.source, "look_up.json");
+        assert_eq!(end.latitude, None);
+    }
+
+    #[test]
+    fn distances_are_haversine_between_found_ends() {
+        let (look_up, table) = (look_up(), table());
+        let sources = Sources {
+            look_up: &look_up,
+            look_up_name: "look_up.json",
+            table: Some(&table),
+            table_name: "zip_codes.csv",
+        };
+        let results = distances(&[pair("IA", "62701"), pair("IA", "ZZ")], &sources, winter());
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].pair, results[1].pair), (1, 2));
+        let miles = haversine_dist(41.6006, -93.6087, 39.80, -89.65);
+        assert!((results[0].miles.unwrap() - miles).abs() < 1e-9);
+        assert_eq!(results[1].miles, None);
+        assert_eq!(results[0].time_created_utc, winter().to_rfc3339());
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty from_state or to_state")]
+    fn an_empty_end_panics() {
+        let look_up = look_up();
+        let sources = Sources {
+            look_up: &look_up,
+            look_up_name: "look_up.json",
+            table: None,
+            table_name: "",
+        };
+        distances(&[pair("IA", " ")], &sources, winter());
+    }
+}
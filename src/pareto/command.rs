@@ -0,0 +1,137 @@
+// The pareto sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_values, parse_flag},
+    command::{
+        modal_params, read_airport_table, read_precedence, read_stops, route_stops, write_outputs,
+    },
+    lookup::{stop_name, ObjLookUp},
+    matrix::{distance_matrix, missing_pairs, read_matrix_csv},
+    pareto::{pareto_front, Nsga2, Objective, EXACT_LIMIT},
+    precedence::check_ends,
+    solver::full_route,
+    stops::{apart, has_repeats},
+    stss::{title_objectives, vec_row_objectives},
+    territory::travel_hours_matrix,
+};
+use log::{error, info};
+use serde_json::{json, Value};
+
+const PARETO_CSV: &str = "pareto.csv";
+const PARETO_JSON: &str = "pareto.json";
+
+// pareto: multi objective routes.
+//   --states IA,NY,...       states to visit (default: all)
+//   --matrix NAME=file.csv   extra cost matrix, repeatable
+//   --population, --generations, --seed   NSGA-II settings
+//   --constraints file.json  precedence groups and stops
+// Miles and estimated driving hours are always objectives;
+// legs to or from AK, HI or a territory are flown
+// (--airports, --fly-mph, --airport-hours).
+pub fn pareto(args: &[String], data: &[ObjLookUp], start: &str, end: Option<&str>) {
+    let stops = read_stops(args, data);
+    let (first, middle, last) = route_stops(args, data, start, end, &stops);
+    let prec = read_precedence(args, data);
+    check_ends(first, &middle, last, &prec, data);
+
+    let miles = distance_matrix(data);
+    let hours = travel_hours_matrix(&miles, data, &read_airport_table(args), &modal_params(args));
+    let mut objectives = vec![
+        Objective {
+            name: "MILES".to_owned(),
+            matrix: miles,
+        },
+        Objective {
+            name: "HOURS".to_owned(),
+            matrix: hours,
+        },
+    ];
+
+    let mut stops = middle.clone();
+    stops.push(first);
+    stops.extend(last);
+    for spec in flag_values(args, "--matrix") {
+        let (name, path) = match spec.find('=') {
+            Some(i) => (&spec[..i], &spec[i + 1..]),
+            None => {
+                let msg = "Expected --matrix NAME=file.csv";
+                error!("{:?}: {:?}", msg, spec);
+                panic!("{:?}: {:?}", msg, spec)
+            }
+        };
+        let matrix = read_matrix_csv(path, data);
+        if let Some(&(i, j)) = missing_pairs(&matrix, &stops).first() {
+            let msg = "Matrix csv is missing a stop pair";
+            let (from, to) = (stop_name(data, i), stop_name(data, j));
+            error!("{:?}: {:?} {:?}->{:?}", msg, path, from, to);
+            panic!("{:?}: {:?} {:?}->{:?}", msg, path, from, to)
+        }
+        objectives.push(Objective {
+            name: name.to_owned(),
+            matrix,
+        });
+    }
+
+    // keep the visits of a revisited state apart
+    if has_repeats(&middle) {
+        for o in objectives.iter_mut() {
+            o.matrix = apart(&o.matrix);
+        }
+    }
+
+    let defaults = Nsga2::default();
+    let params = Nsga2 {
+        population: parse_flag(args, "--population", defaults.population),
+        generations: parse_flag(args, "--generations", defaults.generations),
+        mutation: parse_flag(args, "--mutation", defaults.mutation),
+        seed: parse_flag(args, "--seed", defaults.seed),
+    };
+
+    let front = pareto_front(first, &middle, last, &objectives, &prec, &params);
+    let names: Vec<String> = objectives.iter().map(|o| o.name.to_uppercase()).collect();
+
+    // csv: one row per non-dominated route
+    let mut rows: Vec<Vec<String>> = vec![title_objectives(
+        full_route(first, &middle, last).len(),
+        &names,
+    )];
+    for (iv, sol) in front.iter().enumerate() {
+        let names: Vec<String> = sol.route.iter().map(|&i| stop_name(data, i)).collect();
+        rows.push(vec_row_objectives(
+            iv,
+            &sol.values,
+            names.iter().map(String::as_str).collect(),
+        ));
+    }
+
+    // json: the same routes with named objective values
+    let routes: Vec<Value> = front
+        .iter()
+        .enumerate()
+        .map(|(iv, sol)| {
+            let mut values = serde_json::Map::new();
+            for (name, v) in names.iter().zip(&sol.values) {
+                values.insert(name.clone(), json!(v));
+            }
+            json!({
+                "key": iv,
+                "route": sol.route.iter().map(|&i| stop_name(data, i)).collect::<Vec<String>>(),
+                "values": values,
+            })
+        })
+        .collect();
+    let obj = json!({
+        "objectives": names,
+        "exact": middle.len() <= EXACT_LIMIT,
+        "closed_tour": last == Some(first),
+        "routes": routes,
+    });
+    write_outputs(PARETO_CSV, &rows, PARETO_JSON, obj);
+    info!(
+        "Pareto front of {:?} route(s) written to {:?} and {:?}",
+        front.len(),
+        PARETO_CSV,
+        PARETO_JSON
+    );
+}
@@ -0,0 +1,491 @@
+pub mod command;
+
+use crate::{
+    matrix::{is_symmetric, route_total},
+    precedence::{allows, repair, Precedence},
+    random::XorShift,
+    solver::{first_of_mirror, full_route, nearest_order},
+};
+use log::{debug, info};
+use permutohedron::Heap;
+use std::cmp::Ordering;
+
+// Instances with at most this many states between
+// the start and the end are enumerated exactly (8! routes).
+pub const EXACT_LIMIT: usize = 8;
+
+// A named cost matrix, e.g. miles or hours.
+pub struct Objective {
+    pub name: String,
+    pub matrix: Vec<Vec<f64>>,
+}
+
+// A route with one value per objective.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub route: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+// NSGA-II settings for large instances.
+pub struct Nsga2 {
+    pub population: usize,
+    pub generations: usize,
+    pub mutation: f64,
+    pub seed: u64,
+}
+
+impl Default for Nsga2 {
+    fn default() -> Nsga2 {
+        Nsga2 {
+            population: 100,
+            generations: 500,
+            mutation: 0.2,
+            seed: 2020,
+        }
+    }
+}
+
+// function returns the total of a route
+// for every objective.
+pub fn evaluate(route: &[usize], objectives: &[Objective]) -> Vec<f64> {
+    objectives
+        .iter()
+        .map(|o| route_total(route, &o.matrix))
+        .collect()
+}
+
+// a dominates b when it is no worse in every
+// objective and better in at least one.
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
+
+// function returns the exact or heuristic pareto front
+// depending on the number of states between start and end.
+pub fn pareto_front(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    objectives: &[Objective],
+    prec: &Precedence,
+    params: &Nsga2,
+) -> Vec<Solution> {
+    match middle.len() {
+        a if a <= EXACT_LIMIT => exact_front(start, middle, end, objectives, prec),
+        _ => nsga2(start, middle, end, objectives, prec, params),
+    }
+}
+
+// function enumerates every ordering of middle that
+// honors the precedence pairs and keeps the
+// non-dominated routes. A closed tour with symmetric
+// objectives and no precedence pairs skips the mirror
+// image of every tour.
+pub fn exact_front(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    objectives: &[Objective],
+    prec: &Precedence,
+) -> Vec<Solution> {
+    let mut front: Vec<Solution> = Vec::new();
+    let mut data = middle.to_vec();
+
+    if data.len() < 2 {
+        let route = full_route(start, &data, end);
+        let values = evaluate(&route, objectives);
+        return vec![Solution { route, values }];
+    }
+
+    let mut stops = middle.to_vec();
+    stops.push(start);
+    let mirrored = end == Some(start)
+        && prec.pairs.is_empty()
+        && objectives.iter().all(|o| is_symmetric(&o.matrix, &stops));
+
+    let heap = Heap::new(&mut data);
+    for (iv, perm) in heap.enumerate() {
+        if !allows(&perm, prec) || (mirrored && !first_of_mirror(&perm)) {
+            continue;
+        }
+        let route = full_route(start, &perm, end);
+        let values = evaluate(&route, objectives);
+        debug!("pareto exact #{:?}: {:?} {:?}", iv, route, values);
+        insert_front(&mut front, Solution { route, values });
+    }
+    sort_front(&mut front);
+    info!("Exact pareto front: {:?} route(s)", front.len());
+    front
+}
+
+// function searches the pareto front with NSGA-II:
+// order crossover, inversion mutation, non-dominated
+// sorting and crowding distance. Children that break a
+// precedence pair are repaired before evaluation.
+pub fn nsga2(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    objectives: &[Objective],
+    prec: &Precedence,
+    params: &Nsga2,
+) -> Vec<Solution> {
+    let mut rng = XorShift::new(params.seed);
+    let size = params.population.max(4);
+
+    // seed one nearest neighbour route per objective,
+    // the rest of the population is random.
+    let mut pop: Vec<Vec<usize>> = objectives
+        .iter()
+        .map(|o| nearest_order(start, middle, &o.matrix, prec))
+        .collect();
+    while pop.len() < size {
+        let mut perm = middle.to_vec();
+        rng.shuffle(&mut perm);
+        pop.push(repair(&perm, prec).unwrap_or(perm));
+    }
+    let mut values: Vec<Vec<f64>> = pop
+        .iter()
+        .map(|p| evaluate(&full_route(start, p, end), objectives))
+        .collect();
+
+    for gen in 0..params.generations {
+        let (rank, crowd) = rank_and_crowding(&values);
+
+        // binary tournament on (rank, crowding)
+        let pick = |rng: &mut XorShift| -> usize {
+            let a = rng.below(pop.len());
+            let b = rng.below(pop.len());
+            match (rank[a], rank[b]) {
+                (x, y) if x < y => a,
+                (x, y) if x > y => b,
+                _ if crowd[a] >= crowd[b] => a,
+                _ => b,
+            }
+        };
+
+        let mut children: Vec<Vec<usize>> = Vec::with_capacity(size);
+        while children.len() < size {
+            let a = pick(&mut rng);
+            let b = pick(&mut rng);
+            let mut child = order_crossover(&pop[a], &pop[b], &mut rng);
+            if rng.unit() < params.mutation {
+                invert(&mut child, &mut rng);
+            }
+            children.push(repair(&child, prec).unwrap_or(child));
+        }
+
+        for child in children {
+            values.push(evaluate(&full_route(start, &child, end), objectives));
+            pop.push(child);
+        }
+
+        // keep the best `size` of parents and children
+        let fronts = non_dominated_sort(&values);
+        let mut keep: Vec<usize> = Vec::with_capacity(size);
+        for front in fronts {
+            if keep.len() + front.len() <= size {
+                keep.extend(front);
+            } else {
+                let dist = crowding(&values, &front);
+                let mut order: Vec<usize> = (0..front.len()).collect();
+                order.sort_by(|&x, &y| dist[y].total_cmp(&dist[x]));
+                for o in order.into_iter().take(size - keep.len()) {
+                    keep.push(front[o]);
+                }
+            }
+            if keep.len() == size {
+                break;
+            }
+        }
+        pop = keep.iter().map(|&k| pop[k].clone()).collect();
+        values = keep.iter().map(|&k| values[k].clone()).collect();
+        debug!("nsga2 generation {:?}", gen);
+    }
+
+    let mut front: Vec<Solution> = Vec::new();
+    for (perm, vals) in pop.iter().zip(values) {
+        let route = full_route(start, perm, end);
+        insert_front(
+            &mut front,
+            Solution {
+                route,
+                values: vals,
+            },
+        );
+    }
+    sort_front(&mut front);
+    info!("NSGA-II pareto front: {:?} route(s)", front.len());
+    front
+}
+
+// function adds a solution to a front unless it is
+// dominated or already present, dropping the routes
+// it dominates.
+fn insert_front(front: &mut Vec<Solution>, cand: Solution) {
+    if front
+        .iter()
+        .any(|s| s.values == cand.values || dominates(&s.values, &cand.values))
+    {
+        return;
+    }
+    front.retain(|s| !dominates(&cand.values, &s.values));
+    front.push(cand);
+}
+
+// Sorted by total_cmp so a NaN cost cannot panic.
+fn sort_front(front: &mut [Solution]) {
+    front.sort_by(|a, b| {
+        a.values
+            .iter()
+            .zip(&b.values)
+            .map(|(x, y)| x.total_cmp(y))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+// function splits the population into fronts.
+// fronts[0] holds the non-dominated individuals.
+fn non_dominated_sort(values: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let num = values.len();
+    let mut beaten_by = vec![0usize; num];
+    let mut beats: Vec<Vec<usize>> = vec![Vec::new(); num];
+    let mut fronts: Vec<Vec<usize>> = vec![Vec::new()];
+
+    for p in 0..num {
+        for q in 0..num {
+            if dominates(&values[p], &values[q]) {
+                beats[p].push(q);
+            } else if dominates(&values[q], &values[p]) {
+                beaten_by[p] += 1;
+            }
+        }
+        if beaten_by[p] == 0 {
+            fronts[0].push(p);
+        }
+    }
+
+    let mut i = 0;
+    while !fronts[i].is_empty() {
+        let mut next: Vec<usize> = Vec::new();
+        for &p in &fronts[i] {
+            for &q in &beats[p] {
+                beaten_by[q] -= 1;
+                if beaten_by[q] == 0 {
+                    next.push(q);
+                }
+            }
+        }
+        i += 1;
+        fronts.push(next);
+    }
+    fronts.pop(); // last front is always empty
+    fronts
+}
+
+// function returns the crowding distance of each
+// member of a front, in front order.
+fn crowding(values: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let num = front.len();
+    let mut dist = vec![0.0; num];
+    if num < 3 {
+        return vec![f64::INFINITY; num];
+    }
+    let width = values[front[0]].len();
+    // one column per objective over the members of the front
+    let cols = (0..width).map(|m| front.iter().map(|&f| values[f][m]).collect::<Vec<f64>>());
+    for col in cols {
+        let mut order: Vec<usize> = (0..num).collect();
+        order.sort_by(|&a, &b| col[a].total_cmp(&col[b]));
+        let lo = col[order[0]];
+        let hi = col[order[num - 1]];
+        dist[order[0]] = f64::INFINITY;
+        dist[order[num - 1]] = f64::INFINITY;
+        if hi - lo > 0.0 {
+            for k in 1..(num - 1) {
+                dist[order[k]] += (col[order[k + 1]] - col[order[k - 1]]) / (hi - lo);
+            }
+        }
+    }
+    dist
+}
+
+// function returns (rank, crowding) for every individual.
+fn rank_and_crowding(values: &[Vec<f64>]) -> (Vec<usize>, Vec<f64>) {
+    let mut rank = vec![0usize; values.len()];
+    let mut crowd = vec![0.0; values.len()];
+    for (r, front) in non_dominated_sort(values).iter().enumerate() {
+        for (k, d) in crowding(values, front).into_iter().enumerate() {
+            rank[front[k]] = r;
+            crowd[front[k]] = d;
+        }
+    }
+    (rank, crowd)
+}
+
+// order crossover (OX): copy a slice of a and fill
+// the remaining positions in the order of b.
+fn order_crossover(a: &[usize], b: &[usize], rng: &mut XorShift) -> Vec<usize> {
+    let num = a.len();
+    if num < 2 {
+        return a.to_vec();
+    }
+    let mut i = rng.below(num);
+    let mut j = rng.below(num);
+    if i > j {
+        std::mem::swap(&mut i, &mut j);
+    }
+    let slice = &a[i..=j];
+    let mut rest = b.iter().filter(|x| !slice.contains(x));
+    let mut child = Vec::with_capacity(num);
+    for k in 0..num {
+        match k {
+            a if a >= i && a <= j => child.push(slice[k - i]),
+            _ => child.push(*rest.next().unwrap()),
+        }
+    }
+    child
+}
+
+// mutation: reverse a random slice.
+fn invert(v: &mut [usize], rng: &mut XorShift) {
+    if v.len() < 2 {
+        return;
+    }
+    let mut i = rng.below(v.len());
+    let mut j = rng.below(v.len());
+    if i > j {
+        std::mem::swap(&mut i, &mut j);
+    }
+    v[i..=j].reverse();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_matrix(rng: &mut XorShift, num: usize) -> Vec<Vec<f64>> {
+        (0..num)
+            .map(|i| {
+                (0..num)
+                    .map(|j| match i == j {
+                        true => 0.0,
+                        false => (rng.below(1000) + 1) as f64,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn objectives(rng: &mut XorShift, num: usize) -> Vec<Objective> {
+        ["MILES", "HOURS"]
+            .iter()
+            .map(|name| Objective {
+                name: name.to_string(),
+                matrix: random_matrix(rng, num),
+            })
+            .collect()
+    }
+
+    // every ordering of the middle, as full routes
+    fn all_routes(start: usize, middle: &[usize], end: Option<usize>) -> Vec<Vec<usize>> {
+        let mut data = middle.to_vec();
+        Heap::new(&mut data)
+            .map(|p| full_route(start, &p, end))
+            .collect()
+    }
+
+    #[test]
+    fn dominates_needs_one_strictly_better_objective() {
+        assert!(dominates(&[1.0, 2.0], &[1.0, 3.0]));
+        assert!(!dominates(&[1.0, 3.0], &[1.0, 3.0]));
+        assert!(!dominates(&[1.0, 4.0], &[2.0, 3.0]));
+        assert!(!dominates(&[f64::NAN, 1.0], &[2.0, 2.0]));
+    }
+
+    #[test]
+    fn exact_front_contains_the_brute_force_optimum() {
+        let mut rng = XorShift::new(7);
+        for _ in 0..20 {
+            let num = 3 + rng.below(5);
+            let objs = objectives(&mut rng, num + 2);
+            let middle: Vec<usize> = (1..=num).collect();
+            let end = Some(num + 1);
+            let front = exact_front(0, &middle, end, &objs, &Precedence::default());
+
+            let values: Vec<Vec<f64>> = all_routes(0, &middle, end)
+                .iter()
+                .map(|r| evaluate(r, &objs))
+                .collect();
+            for k in 0..objs.len() {
+                let best = values.iter().map(|v| v[k]).fold(f64::INFINITY, f64::min);
+                assert!(front.iter().any(|s| s.values[k] == best));
+            }
+            // no route beats a route of the front
+            for s in &front {
+                assert_eq!(s.values, evaluate(&s.route, &objs));
+                assert!(!values.iter().any(|v| dominates(v, &s.values)));
+            }
+        }
+    }
+
+    #[test]
+    fn closed_tour_front_skips_mirror_images() {
+        let mut rng = XorShift::new(11);
+        let mut objs = objectives(&mut rng, 5);
+        for o in objs.iter_mut() {
+            for i in 0..5 {
+                for j in 0..i {
+                    o.matrix[i][j] = o.matrix[j][i];
+                }
+            }
+        }
+        let front = exact_front(0, &[1, 2, 3, 4], Some(0), &objs, &Precedence::default());
+        for s in &front {
+            let mut mirror = s.route.clone();
+            mirror.reverse();
+            assert!(mirror == s.route || !front.iter().any(|t| t.route == mirror));
+        }
+    }
+
+    #[test]
+    fn nsga2_front_is_non_dominated() {
+        let mut rng = XorShift::new(3);
+        let objs = objectives(&mut rng, 12);
+        let middle: Vec<usize> = (1..11).collect();
+        let params = Nsga2 {
+            population: 40,
+            generations: 50,
+            ..Nsga2::default()
+        };
+        let front = nsga2(0, &middle, Some(11), &objs, &Precedence::default(), &params);
+        assert!(!front.is_empty());
+        for s in &front {
+            assert_eq!(s.route.len(), 12);
+            assert_eq!((s.route[0], s.route[11]), (0, 11));
+            let mut stops = s.route[1..11].to_vec();
+            stops.sort_unstable();
+            assert_eq!(stops, middle);
+            assert!(!front.iter().any(|t| dominates(&t.values, &s.values)));
+        }
+    }
+
+    #[test]
+    fn sort_front_orders_a_nan_last() {
+        let solution = |values: Vec<f64>| Solution {
+            route: Vec::new(),
+            values,
+        };
+        let mut front = vec![
+            solution(vec![f64::NAN, 1.0]),
+            solution(vec![2.0, 1.0]),
+            solution(vec![1.0, 5.0]),
+        ];
+        sort_front(&mut front);
+        assert_eq!(front[0].values, vec![1.0, 5.0]);
+        assert_eq!(front[1].values, vec![2.0, 1.0]);
+        assert!(front[2].values[0].is_nan());
+    }
+}
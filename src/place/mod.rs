@@ -0,0 +1,472 @@
+use crate::{
+    distance::haversine_dist,
+    lookup::{read_look_up, ObjLookUp},
+};
+use log::{error, info};
+use serde::{Serialize, Serializer};
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+// A coordinate in degrees, latitude within -90..90 and
+// longitude within -180..180.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LatLon {
+    pub fn new(lat: f64, lon: f64) -> Result<LatLon, String> {
+        if !lat.is_finite() || lat.abs() > 90.0 {
+            return Err(format!("latitude {} is out of range", lat));
+        }
+        if !lon.is_finite() || lon.abs() > 180.0 {
+            return Err(format!("longitude {} is out of range", lon));
+        }
+        Ok(LatLon { lat, lon })
+    }
+
+    // function parses the latitude and longitude strings
+    // of a lookup record.
+    pub fn parse(lat: &str, lon: &str) -> Result<LatLon, String> {
+        let lat = lat
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("latitude {:?} is not a number", lat))?;
+        let lon = lon
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("longitude {:?} is not a number", lon))?;
+        LatLon::new(lat, lon)
+    }
+
+    // function returns the miles to another coordinate
+    // by haversine_dist.
+    pub fn miles_to(&self, other: &LatLon) -> f64 {
+        haversine_dist(self.lat, self.lon, other.lat, other.lon)
+    }
+}
+
+// A 5 digit zip code, kept as text so the leading
+// zeros of New England zip codes survive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ZipCode(String);
+
+impl ZipCode {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ZipCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ZipCode, String> {
+        let s = s.trim();
+        match s.len() == 5 && s.chars().all(|c| c.is_ascii_digit()) {
+            true => Ok(ZipCode(s.to_owned())),
+            false => Err(format!("zip code {:?} is not 5 digits", s)),
+        }
+    }
+}
+
+impl fmt::Display for ZipCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for ZipCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+// The postal codes of the 50 states, DC, the
+// territories, the freely associated states and the
+// armed forces, all found in codes.csv.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StateCode {
+    AL,
+    AK,
+    AZ,
+    AR,
+    CA,
+    CO,
+    CT,
+    DE,
+    FL,
+    GA,
+    HI,
+    ID,
+    IL,
+    IN,
+    IA,
+    KS,
+    KY,
+    LA,
+    ME,
+    MD,
+    MA,
+    MI,
+    MN,
+    MS,
+    MO,
+    MT,
+    NE,
+    NV,
+    NH,
+    NJ,
+    NM,
+    NY,
+    NC,
+    ND,
+    OH,
+    OK,
+    OR,
+    PA,
+    RI,
+    SC,
+    SD,
+    TN,
+    TX,
+    UT,
+    VT,
+    VA,
+    WA,
+    WV,
+    WI,
+    WY,
+    DC,
+    AS,
+    GU,
+    MP,
+    PR,
+    VI,
+    FM,
+    MH,
+    PW,
+    AA,
+    AE,
+    AP,
+}
+
+use StateCode::*;
+
+// The codes in the order of the StateCode variants.
+const STATE_CODES: [&str; 62] = [
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "FL", "GA", "HI", "ID", "IL", "IN", "IA", "KS",
+    "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ", "NM", "NY",
+    "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT", "VT", "VA", "WA", "WV",
+    "WI", "WY", "DC", "AS", "GU", "MP", "PR", "VI", "FM", "MH", "PW", "AA", "AE", "AP",
+];
+
+impl StateCode {
+    pub const ALL: [StateCode; 62] = [
+        AL, AK, AZ, AR, CA, CO, CT, DE, FL, GA, HI, ID, IL, IN, IA, KS, KY, LA, ME, MD, MA, MI, MN,
+        MS, MO, MT, NE, NV, NH, NJ, NM, NY, NC, ND, OH, OK, OR, PA, RI, SC, SD, TN, TX, UT, VT, VA,
+        WA, WV, WI, WY, DC, AS, GU, MP, PR, VI, FM, MH, PW, AA, AE, AP,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        STATE_CODES[*self as usize]
+    }
+
+    // function returns true for the 50 states.
+    pub fn is_state(&self) -> bool {
+        (*self as usize) < (DC as usize)
+    }
+
+    // function returns true for the territories with
+    // no electoral votes: AS, GU, MP, PR and VI.
+    pub fn is_territory(&self) -> bool {
+        matches!(self, AS | GU | MP | PR | VI)
+    }
+}
+
+impl FromStr for StateCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StateCode, String> {
+        let code = s.trim().to_uppercase();
+        STATE_CODES
+            .iter()
+            .position(|st| *st == code)
+            .map(|i| StateCode::ALL[i])
+            .ok_or_else(|| format!("state {:?} is not a state code", s))
+    }
+}
+
+impl fmt::Display for StateCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A validated lookup record.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Place {
+    pub zip_code: ZipCode,
+    pub city: String,
+    pub state: StateCode,
+    pub location: LatLon,
+    pub classification: String,
+    pub population: u64,
+}
+
+impl TryFrom<&ObjLookUp> for Place {
+    type Error = Vec<String>;
+
+    // Every field is checked, so the error lists all of
+    // the problems of the record.
+    fn try_from(rec: &ObjLookUp) -> Result<Place, Vec<String>> {
+        let mut errors: Vec<String> = Vec::new();
+        let zip_code = rec.zip_code.parse::<ZipCode>().map_err(|e| errors.push(e));
+        let state = rec.state.parse::<StateCode>().map_err(|e| errors.push(e));
+        let location = LatLon::parse(&rec.latitude, &rec.longitude).map_err(|e| errors.push(e));
+        // look_up.json may leave the population blank
+        let population = match rec.population.trim() {
+            "" => Ok(0),
+            p => p
+                .parse::<u64>()
+                .map_err(|_| errors.push(format!("population {:?} is not a whole number", p))),
+        };
+        if rec.city.trim().is_empty() {
+            errors.push("city is empty".to_owned());
+        }
+        match (zip_code, state, location, population) {
+            (Ok(zip_code), Ok(state), Ok(location), Ok(population)) if errors.is_empty() => {
+                Ok(Place {
+                    zip_code,
+                    city: rec.city.trim().to_owned(),
+                    state,
+                    location,
+                    classification: rec.classification.clone(),
+                    population,
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+impl From<&Place> for ObjLookUp {
+    fn from(place: &Place) -> ObjLookUp {
+        ObjLookUp {
+            zip_code: place.zip_code.to_string(),
+            city: place.city.clone(),
+            state: place.state.to_string(),
+            latitude: place.location.lat.to_string(),
+            longitude: place.location.lon.to_string(),
+            classification: place.classification.clone(),
+            population: place.population.to_string(),
+        }
+    }
+}
+
+// The problems of one record; `row` counts the records
+// from 1, a csv header not included.
+#[derive(Serialize, Debug, Clone)]
+pub struct RowError {
+    pub row: usize,
+    pub zip_code: String,
+    pub errors: Vec<String>,
+}
+
+// function validates lookup records and returns them
+// as places, or every invalid record with all of its
+// problems.
+pub fn validate_look_up(data: &[ObjLookUp]) -> Result<Vec<Place>, Vec<RowError>> {
+    let mut places: Vec<Place> = Vec::with_capacity(data.len());
+    let mut invalid: Vec<RowError> = Vec::new();
+    for (i, rec) in data.iter().enumerate() {
+        match Place::try_from(rec) {
+            Ok(place) => places.push(place),
+            Err(errors) => invalid.push(RowError {
+                row: i + 1,
+                zip_code: rec.zip_code.clone(),
+                errors,
+            }),
+        }
+    }
+    match invalid.is_empty() {
+        true => Ok(places),
+        false => Err(invalid),
+    }
+}
+
+// function loads places from a lookup file shaped like
+// look_up.json or a zip code table (.csv) such as
+// codes.csv, reading every row before reporting the
+// invalid ones.
+pub fn load_places(path: &str) -> Result<Vec<Place>, Vec<RowError>> {
+    if !path.to_lowercase().ends_with(".csv") {
+        return validate_look_up(&read_look_up(path));
+    }
+    let mut rdr = match csv::Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening zip code table";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let mut records: Vec<ObjLookUp> = Vec::new();
+    // the row number of each record read
+    let mut rows: Vec<usize> = Vec::new();
+    let mut invalid: Vec<RowError> = Vec::new();
+    for (i, result) in rdr.deserialize::<ObjLookUp>().enumerate() {
+        match result {
+            Ok(rec) => {
+                records.push(rec);
+                rows.push(i + 1);
+            }
+            Err(e) => invalid.push(RowError {
+                row: i + 1,
+                zip_code: String::new(),
+                errors: vec![e.to_string()],
+            }),
+        }
+    }
+    match validate_look_up(&records) {
+        Ok(places) if invalid.is_empty() => Ok(places),
+        Ok(_) => Err(invalid),
+        Err(found) => {
+            invalid.extend(found.into_iter().map(|r| RowError {
+                row: rows[r.row - 1],
+                ..r
+            }));
+            invalid.sort_by_key(|r| r.row);
+            Err(invalid)
+        }
+    }
+}
+
+// function logs every invalid record and panics once,
+// with their count.
+pub fn report_invalid(path: &str, invalid: &[RowError]) -> ! {
+    for row in invalid {
+        error!(
+            "Invalid lookup record {:?} ({:?}) of {:?}: {}",
+            row.row,
+            row.zip_code,
+            path,
+            row.errors.join("; ")
+        );
+    }
+    let msg = "Invalid lookup records, see the log";
+    error!("{:?}: {:?}: {:?}", msg, path, invalid.len());
+    panic!("{:?}: {:?}: {:?}", msg, path, invalid.len())
+}
+
+// function loads places as load_places does and panics
+// after logging every invalid record.
+pub fn read_places(path: &str) -> Vec<Place> {
+    match load_places(path) {
+        Ok(places) => {
+            info!("Success read {:?} place(s) from {:?}", places.len(), path);
+            places
+        }
+        Err(invalid) => report_invalid(path, &invalid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::test_record;
+    use std::fs;
+
+    fn record(zip_code: &str, state: &str, lat: &str, population: &str) -> ObjLookUp {
+        ObjLookUp {
+            city: "HARTFORD".to_owned(),
+            latitude: lat.to_owned(),
+            longitude: "-72.6857".to_owned(),
+            population: population.to_owned(),
+            ..test_record(zip_code, state)
+        }
+    }
+
+    #[test]
+    fn coordinates_stay_in_range() {
+        assert_eq!(
+            LatLon::parse(" 41.6 ", "-93.6"),
+            Ok(LatLon {
+                lat: 41.6,
+                lon: -93.6
+            })
+        );
+        assert!(LatLon::new(90.5, 0.0).is_err());
+        assert!(LatLon::new(0.0, -180.5).is_err());
+        assert!(LatLon::new(f64::NAN, 0.0).is_err());
+        assert_eq!(
+            LatLon::parse("north", "0"),
+            Err("latitude \"north\" is not a number".to_owned())
+        );
+        let a = LatLon::new(41.6, -93.6).unwrap();
+        assert_eq!(a.miles_to(&a), 0.0);
+    }
+
+    #[test]
+    fn zip_codes_keep_leading_zeros() {
+        let zip: ZipCode = "06106".parse().unwrap();
+        assert_eq!(zip.to_string(), "06106");
+        assert_eq!(serde_json::to_string(&zip).unwrap(), "\"06106\"");
+        assert!("6106".parse::<ZipCode>().is_err());
+        assert!("0610A".parse::<ZipCode>().is_err());
+    }
+
+    #[test]
+    fn state_codes_know_what_they_are() {
+        for (k, code) in StateCode::ALL.iter().enumerate() {
+            assert_eq!(code.as_str(), STATE_CODES[k]);
+            assert_eq!(STATE_CODES[k].parse::<StateCode>(), Ok(*code));
+        }
+        assert_eq!(StateCode::ALL.iter().filter(|c| c.is_state()).count(), 50);
+        assert_eq!(
+            StateCode::ALL.iter().filter(|c| c.is_territory()).count(),
+            5
+        );
+        assert_eq!(" ia".parse::<StateCode>(), Ok(StateCode::IA));
+        assert!(!StateCode::DC.is_state() && !StateCode::DC.is_territory());
+        assert!("XX".parse::<StateCode>().is_err());
+    }
+
+    #[test]
+    fn a_record_lists_all_of_its_problems() {
+        let place = Place::try_from(&record("06106", "CT", "41.7450", "")).unwrap();
+        assert_eq!(place.population, 0);
+        assert_eq!(ObjLookUp::from(&place).latitude, "41.745");
+
+        let errors = Place::try_from(&record("6106", "XX", "95", "1.5")).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        let data = vec![
+            record("06106", "CT", "41.7450", "39902"),
+            record("06103", "CT", "north", "1410"),
+        ];
+        let invalid = validate_look_up(&data).unwrap_err();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!((invalid[0].row, invalid[0].zip_code.as_str()), (2, "06103"));
+    }
+
+    #[test]
+    fn loads_every_bundled_lookup() {
+        assert_eq!(load_places("look_up.json").unwrap().len(), 51);
+        assert_eq!(load_places("zip_codes.csv").unwrap().len(), 51);
+        let places = load_places("codes.csv").unwrap();
+        assert_eq!(places[0].zip_code.as_str(), "00501");
+    }
+
+    #[test]
+    fn a_table_reports_the_rows_it_cannot_read() {
+        let path = std::env::temp_dir().join("read_json_place_test.csv");
+        let table = "ZipCode,City,State,Latitude,Longitude,Classification,Population\n\
+                     \"50319\",\"DES MOINES\",\"IA\",\"41.6006\",\"-93.6087\",\"\",\"0\"\n\
+                     \"1234\",\"NOWHERE\",\"ZZ\",\"41.6\",\"-93.6\",\"\",\"0\"\n\
+                     \"12224\",\"ALBANY\",\"NY\",\"42.6525\"\n";
+        fs::write(&path, table).unwrap();
+        let invalid = load_places(path.to_str().unwrap()).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        let rows: Vec<(usize, usize)> = invalid.iter().map(|r| (r.row, r.errors.len())).collect();
+        assert_eq!(rows, [(2, 2), (3, 1)]);
+    }
+}
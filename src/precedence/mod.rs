@@ -0,0 +1,290 @@
+use crate::lookup::ObjLookUp;
+use log::{error, info};
+use serde::Deserialize;
+
+// One step of an ordered group: a single state or a
+// set of states whose order among themselves is free.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Tier {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Tier {
+    pub fn states(&self) -> Vec<&str> {
+        match self {
+            Tier::One(s) => vec![s.as_str()],
+            Tier::Many(v) => v.iter().map(|s| s.as_str()).collect(),
+        }
+    }
+}
+
+// "a before b" pairs over lookup indices. A pair only
+// constrains a route that visits both states.
+#[derive(Debug, Clone, Default)]
+pub struct Precedence {
+    pub pairs: Vec<(usize, usize)>,
+}
+
+// function expands ordered groups into pairs: every
+// state of a tier comes before every state of each
+// later tier, so `[["IA","NH"],"NV","SC"]` orders IA
+// and NH before NV and NV before SC. States with two
+// lookup records (CT) contribute both records.
+pub fn from_groups(data: &[ObjLookUp], groups: &[Vec<Tier>]) -> Precedence {
+    let records = |state: &str| -> Vec<usize> {
+        let found: Vec<usize> = (0..data.len())
+            .filter(|&i| data[i].state == state)
+            .collect();
+        if found.is_empty() {
+            let msg = "Precedence for a state not in the lookup";
+            error!("{:?}: {:?}", msg, state);
+            panic!("{:?}: {:?}", msg, state)
+        }
+        found
+    };
+
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for group in groups {
+        let tiers: Vec<Vec<usize>> = group
+            .iter()
+            .map(|t| t.states().into_iter().flat_map(records).collect())
+            .collect();
+        for (k, early) in tiers.iter().enumerate() {
+            for late in &tiers[k + 1..] {
+                for &a in early {
+                    for &b in late {
+                        if a == b {
+                            let msg = "State must come before itself";
+                            error!("{:?}: {:?}", msg, data[a].state);
+                            panic!("{:?}: {:?}", msg, data[a].state)
+                        }
+                        if !pairs.contains(&(a, b)) {
+                            pairs.push((a, b));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let prec = Precedence { pairs };
+    let all: Vec<usize> = (0..data.len()).collect();
+    if repair(&all, &prec).is_none() {
+        let msg = "Precedence groups contain a cycle";
+        error!("{:?}: {:?}", msg, groups);
+        panic!("{:?}: {:?}", msg, groups)
+    }
+    info!("Precedence: {:?} pair(s)", prec.pairs.len());
+    prec
+}
+
+// function returns the position of every lookup index
+// in a route, usize::MAX when it is not visited.
+fn positions(route: &[usize]) -> Vec<usize> {
+    let size = route.iter().max().map_or(0, |&m| m + 1);
+    let mut pos = vec![usize::MAX; size];
+    for (k, &i) in route.iter().enumerate() {
+        pos[i] = k;
+    }
+    pos
+}
+
+// function returns the pairs broken by a route.
+pub fn broken(route: &[usize], prec: &Precedence) -> Vec<(usize, usize)> {
+    if prec.pairs.is_empty() {
+        return Vec::new();
+    }
+    let pos = positions(route);
+    let at = |i: usize| pos.get(i).copied().unwrap_or(usize::MAX);
+    prec.pairs
+        .iter()
+        .filter(|&&(a, b)| at(a) != usize::MAX && at(b) != usize::MAX && at(a) > at(b))
+        .copied()
+        .collect()
+}
+
+// function returns true when a route breaks no pair.
+pub fn allows(route: &[usize], prec: &Precedence) -> bool {
+    broken(route, prec).is_empty()
+}
+
+// function explains the pairs broken by a route.
+pub fn violations(route: &[usize], prec: &Precedence, data: &[ObjLookUp]) -> Vec<String> {
+    broken(route, prec)
+        .iter()
+        .map(|&(a, b)| format!("{} must be visited before {}", data[a].state, data[b].state))
+        .collect()
+}
+
+// function panics when a pair puts a stop before the
+// start of the route or after its end. The start and
+// end are fixed, so no ordering of the stops between
+// them keeps such a pair; the solvers only order the
+// stops in between. The return to the start of a
+// closed tour is not a visit.
+pub fn check_ends(
+    first: usize,
+    middle: &[usize],
+    last: Option<usize>,
+    prec: &Precedence,
+    data: &[ObjLookUp],
+) {
+    let end = last.filter(|&l| l != first);
+    let mut route = vec![first];
+    route.extend(middle);
+    route.extend(end);
+    let fixed: Vec<String> = broken(&route, prec)
+        .into_iter()
+        .filter(|&(a, b)| b == first || Some(a) == end)
+        .map(|(a, b)| format!("{} must be visited before {}", data[a].state, data[b].state))
+        .collect();
+    if !fixed.is_empty() {
+        let msg = "Precedence cannot be kept with this start and end";
+        error!("{:?}: {:?}", msg, fixed);
+        panic!("{:?}: {:?}", msg, fixed)
+    }
+}
+
+// function returns, for each state of `middle`, the
+// bit mask of the states of `middle` that must come
+// before it. Used by the subset dynamic programs.
+pub fn predecessor_masks(middle: &[usize], prec: &Precedence) -> Vec<usize> {
+    middle
+        .iter()
+        .map(|&b| {
+            middle
+                .iter()
+                .enumerate()
+                .filter(|&(_, &a)| prec.pairs.contains(&(a, b)))
+                .fold(0, |mask, (k, _)| mask | (1 << k))
+        })
+        .collect()
+}
+
+// function returns true when every state of `among`
+// that must precede `next` is already in `placed`.
+pub fn ready(next: usize, placed: &[usize], among: &[usize], prec: &Precedence) -> bool {
+    prec.pairs
+        .iter()
+        .all(|&(a, b)| b != next || !among.contains(&a) || placed.contains(&a))
+}
+
+// function reorders `order` as little as possible to
+// honor the pairs: repeatedly take the first state
+// whose predecessors are placed. None on a cycle.
+pub fn repair(order: &[usize], prec: &Precedence) -> Option<Vec<usize>> {
+    if prec.pairs.is_empty() {
+        return Some(order.to_vec());
+    }
+    let mut left = order.to_vec();
+    let mut placed: Vec<usize> = Vec::with_capacity(order.len());
+    while !left.is_empty() {
+        let k = left.iter().position(|&s| ready(s, &placed, order, prec))?;
+        placed.push(left.remove(k));
+    }
+    Some(placed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{read_look_up, state_index};
+
+    fn groups(json: &str) -> Vec<Vec<Tier>> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn groups_expand_into_pairs() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let prec = from_groups(&data, &groups(r#"[[["IA","NH"],"NV","SC"],["NY","WI"]]"#));
+        let expected = [
+            (at("IA"), at("NV")),
+            (at("IA"), at("SC")),
+            (at("NH"), at("NV")),
+            (at("NH"), at("SC")),
+            (at("NV"), at("SC")),
+            (at("NY"), at("WI")),
+        ];
+        assert_eq!(prec.pairs.len(), expected.len());
+        assert!(expected.iter().all(|p| prec.pairs.contains(p)));
+        // IA and NH share a tier, so either order is fine
+        assert!(allows(&[at("NH"), at("IA"), at("NV")], &prec));
+        assert!(!allows(&[at("NV"), at("IA")], &prec));
+        // a pair only binds a route that visits both states
+        assert!(allows(&[at("SC"), at("WI")], &prec));
+        assert_eq!(
+            violations(&[at("WI"), at("NY")], &prec, &data),
+            ["NY must be visited before WI"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Precedence groups contain a cycle")]
+    fn a_cycle_is_refused() {
+        let data = read_look_up("look_up.json");
+        from_groups(&data, &groups(r#"[["NY","WI"],["WI","IA"],["IA","NY"]]"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "Precedence for a state not in the lookup")]
+    fn an_unknown_state_is_refused() {
+        let data = read_look_up("look_up.json");
+        from_groups(&data, &groups(r#"[["NY","XX"]]"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "Precedence cannot be kept with this start and end")]
+    fn the_end_in_an_earlier_tier_is_refused() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let prec = from_groups(&data, &groups(r#"[["DC","NY","WI"]]"#));
+        // the start may lead a group, the end close one,
+        // and a closed tour only returns to the start
+        check_ends(at("DC"), &[at("NY")], Some(at("WI")), &prec, &data);
+        check_ends(
+            at("DC"),
+            &[at("WI"), at("NY")],
+            Some(at("DC")),
+            &prec,
+            &data,
+        );
+        check_ends(
+            at("IA"),
+            &[at("NY"), at("WI")],
+            Some(at("DC")),
+            &prec,
+            &data,
+        );
+    }
+
+    #[test]
+    fn repair_moves_only_what_it_must() {
+        let prec = Precedence {
+            pairs: vec![(4, 1), (1, 3)],
+        };
+        assert_eq!(repair(&[2, 4, 1, 3], &prec), Some(vec![2, 4, 1, 3]));
+        assert_eq!(repair(&[3, 1, 2, 4], &prec), Some(vec![2, 4, 1, 3]));
+        assert_eq!(repair(&[1, 5, 2], &prec), Some(vec![1, 5, 2]));
+        let cycle = Precedence {
+            pairs: vec![(1, 2), (2, 1)],
+        };
+        assert_eq!(repair(&[1, 2], &cycle), None);
+        assert_eq!(broken(&[3, 1, 2, 4], &prec), [(4, 1), (1, 3)]);
+    }
+
+    #[test]
+    fn masks_and_readiness_only_count_the_listed_states() {
+        let prec = Precedence {
+            pairs: vec![(4, 1), (1, 3), (7, 3)],
+        };
+        let middle = [1, 3, 4];
+        assert_eq!(predecessor_masks(&middle, &prec), [0b100, 0b001, 0]);
+        assert!(!ready(1, &[], &middle, &prec));
+        assert!(ready(1, &[4], &middle, &prec));
+        // 7 is not among the states, so only 1 must be placed
+        assert!(ready(3, &[4, 1], &middle, &prec));
+    }
+}
@@ -0,0 +1,165 @@
+// The prize sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_value, parse_flag},
+    command::{
+        modal_params, read_airport_table, read_precedence, read_stops, route_stops, write_outputs,
+    },
+    lookup::{stop_indices, stop_name, ObjLookUp},
+    matrix::distance_matrix,
+    precedence::check_ends,
+    prize::{
+        custom_prizes, solve as collect_prizes, Budget, PrizeModel,
+        EXACT_LIMIT as PRIZE_EXACT_LIMIT,
+    },
+    stops::{apart, has_repeats, violations as stop_violations},
+    territory::travel_hours_matrix,
+    weights::{node_weights, route_weight, stop_weights, Weight},
+};
+use log::{error, info};
+use serde_json::json;
+
+const PRIZE_CSV: &str = "prize.csv";
+const PRIZE_JSON: &str = "prize.json";
+
+// prize: collect the most prize within a budget, from
+// the start to the end of states.json. States that do
+// not fit the budget are skipped, except required stops.
+//   --miles 3000 | --days 7     the budget
+//   --max-hours 10              driving hours per day; legs
+//                               to or from AK, HI or a
+//                               territory are flown
+//   --airports file.csv         airport table (default:
+//                               airports.csv)
+//   --prize votes|population|uniform|file.csv
+//                               prize of each state
+//                               (default: electoral votes)
+//   --states IA,NY,...          candidate states (default: all)
+//   --constraints file.json     precedence groups, required
+//                               and optional stops
+pub fn prize(args: &[String], data: &[ObjLookUp], start: &str, end: Option<&str>) {
+    let max_hours: f64 = parse_flag(args, "--max-hours", 10.0);
+    let budget = match (flag_value(args, "--miles"), flag_value(args, "--days")) {
+        (Some(_), None) => Budget::Miles(parse_flag(args, "--miles", 0.0)),
+        (None, Some(_)) => Budget::Days {
+            days: parse_flag(args, "--days", 0.0),
+            max_hours,
+        },
+        _ => {
+            let msg = "Expected exactly one of --miles or --days";
+            error!("{:?}: {:?}", msg, args);
+            panic!("{:?}: {:?}", msg, args)
+        }
+    };
+    let source = flag_value(args, "--prize").unwrap_or("votes");
+    let prizes = match source {
+        "votes" => node_weights(data, Weight::Votes),
+        "population" => node_weights(data, Weight::Population),
+        "uniform" => vec![1.0; data.len()],
+        path => custom_prizes(path, data),
+    };
+
+    // required stops are always visited, every other
+    // state (optional ones too) only when it fits
+    let stops = read_stops(args, data);
+    let (first, middle, last) = route_stops(args, data, start, end, &stops);
+    let (required, mut candidates): (Vec<usize>, Vec<usize>) = middle
+        .iter()
+        .partition(|&&i| stops.is_required(&stop_name(data, i)));
+    for name in &stops.optional {
+        candidates.extend(stop_indices(data, &[name.as_str()]));
+    }
+    let mut unique: Vec<usize> = Vec::with_capacity(candidates.len());
+    for i in candidates {
+        if i != first && Some(i) != last && !unique.contains(&i) {
+            unique.push(i);
+        }
+    }
+    let candidates = unique;
+    let prec = read_precedence(args, data);
+    check_ends(
+        first,
+        &[&required[..], &candidates[..]].concat(),
+        last,
+        &prec,
+        data,
+    );
+
+    let miles = distance_matrix(data);
+    let hours = travel_hours_matrix(&miles, data, &read_airport_table(args), &modal_params(args));
+    // keep the visits of a revisited state apart
+    let (model_miles, model_hours) = match has_repeats(&required) {
+        true => (apart(&miles), apart(&hours)),
+        false => (miles.clone(), hours.clone()),
+    };
+    let model = PrizeModel {
+        miles: &model_miles,
+        hours: &model_hours,
+        prize: prizes,
+        budget,
+    };
+    let best = collect_prizes(first, &required, &candidates, last, &model, &prec);
+
+    let mut rows: Vec<Vec<String>> = vec![[
+        "STOP",
+        "STATE",
+        "PRIZE",
+        "ELECTORAL_VOTES",
+        "POPULATION",
+        "LEG_MILES",
+        "TOTAL_MILES",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()];
+    let weights = stop_weights(&best.route, data);
+    let mut total = 0.0;
+    for (k, &i) in best.route.iter().enumerate() {
+        if k > 0 {
+            total += miles[best.route[k - 1]][i];
+        }
+        let leg = match k {
+            0 => 0.0,
+            _ => miles[best.route[k - 1]][i],
+        };
+        rows.push(vec![
+            (k + 1).to_string(),
+            stop_name(data, i),
+            model.prize[i].to_string(),
+            weights[k].electoral_votes.to_string(),
+            weights[k].population.to_string(),
+            format!("{:.1}", leg),
+            format!("{:.1}", total),
+        ]);
+    }
+
+    let skipped: Vec<String> = candidates
+        .iter()
+        .filter(|i| !best.route.contains(i))
+        .map(|&i| stop_name(data, i))
+        .collect();
+    let (unit, limit) = match budget {
+        Budget::Miles(m) => ("MILES", m),
+        Budget::Days { days, .. } => ("DAYS", days),
+    };
+    let obj = json!({
+        "prize": source,
+        "budget": { "unit": unit, "limit": limit, "spent": (best.spent * 10.0).round() / 10.0 },
+        "max_hours_per_day": max_hours,
+        "route": best.route.iter().map(|&i| stop_name(data, i)).collect::<Vec<String>>(),
+        "prize_collected": best.prize,
+        "miles": (total * 10.0).round() / 10.0,
+        "weights": route_weight(&best.route, data, total),
+        "skipped": skipped,
+        "exact": required.len() + candidates.len() <= PRIZE_EXACT_LIMIT,
+        "feasible": best.feasible,
+        "stop_violations": stop_violations(&best.route, &stops, data),
+        "closed_tour": last == Some(first),
+    });
+    write_outputs(PRIZE_CSV, &rows, PRIZE_JSON, obj);
+    info!(
+        "Prize route collecting {:?} ({:?} of {:?} {:?}) written to {:?} and {:?}",
+        best.prize, best.spent, limit, unit, PRIZE_CSV, PRIZE_JSON
+    );
+}
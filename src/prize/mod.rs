@@ -0,0 +1,493 @@
+pub mod command;
+
+use crate::{
+    costing::travel_days,
+    lookup::ObjLookUp,
+    precedence::{allows, Precedence},
+    solver::{full_route, local_search, nearest_order},
+};
+use log::{debug, error, info};
+use serde::Deserialize;
+
+// Instances with at most this many candidate states
+// are searched exhaustively.
+pub const EXACT_LIMIT: usize = 8;
+
+// What a route may spend: road miles, or travel days
+// at `max_hours` of driving per day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    Miles(f64),
+    Days { days: f64, max_hours: f64 },
+}
+
+// Miles and driving hours between every two records.
+pub struct PrizeModel<'a> {
+    pub miles: &'a [Vec<f64>],
+    pub hours: &'a [Vec<f64>],
+    pub prize: Vec<f64>,
+    pub budget: Budget,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrizeRoute {
+    pub route: Vec<usize>,
+    pub prize: f64,
+    pub spent: f64,
+    // false when the required stops alone overspend
+    pub feasible: bool,
+    pub exact: bool,
+}
+
+#[derive(Deserialize)]
+struct PrizeRow {
+    state: String,
+    prize: f64,
+}
+
+// function reads custom prizes from a csv with the
+// columns state,prize. States not listed are worth 0.
+pub fn custom_prizes(path: &str, data: &[ObjLookUp]) -> Vec<f64> {
+    let mut rdr = match csv::Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening prize table";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let mut prize = vec![0.0; data.len()];
+    for result in rdr.deserialize() {
+        let row: PrizeRow = match result {
+            Ok(row) => row,
+            Err(e) => {
+                let msg = "Error reading prize table row";
+                error!("{:?}: {:?}: {:?}", msg, path, e);
+                panic!("{:?}: {:?}: {:?}", msg, path, e)
+            }
+        };
+        let mut found = false;
+        for (i, rec) in data.iter().enumerate() {
+            if rec.state == row.state {
+                prize[i] = row.prize;
+                found = true;
+            }
+        }
+        if !found {
+            let msg = "Prize for a state not in the lookup";
+            error!("{:?}: {:?}: {:?}", msg, path, row.state);
+            panic!("{:?}: {:?}: {:?}", msg, path, row.state)
+        }
+    }
+    prize
+}
+
+// function returns what a route spends of the budget.
+pub fn spent(route: &[usize], model: &PrizeModel) -> f64 {
+    match model.budget {
+        Budget::Miles(_) => route.windows(2).map(|w| model.miles[w[0]][w[1]]).sum(),
+        Budget::Days { max_hours, .. } => {
+            let hours: Vec<f64> = route.windows(2).map(|w| model.hours[w[0]][w[1]]).collect();
+            match hours.is_empty() {
+                true => 0.0,
+                false => travel_days(&hours, max_hours) as f64,
+            }
+        }
+    }
+}
+
+fn limit(model: &PrizeModel) -> f64 {
+    match model.budget {
+        Budget::Miles(miles) => miles,
+        Budget::Days { days, .. } => days,
+    }
+}
+
+fn collected(route: &[usize], model: &PrizeModel) -> f64 {
+    let mut seen: Vec<usize> = Vec::with_capacity(route.len());
+    let mut total = 0.0;
+    for &i in route {
+        if !seen.contains(&i) {
+            total += model.prize[i];
+            seen.push(i);
+        }
+    }
+    total
+}
+
+// function returns true when a route holds every
+// required stop as often as it is required.
+fn covers(route: &[usize], required: &[usize]) -> bool {
+    required.iter().all(|r| {
+        let need = required.iter().filter(|&x| x == r).count();
+        route.iter().filter(|&x| x == r).count() >= need
+    })
+}
+
+fn miles_of(route: &[usize], model: &PrizeModel) -> f64 {
+    route.windows(2).map(|w| model.miles[w[0]][w[1]]).sum()
+}
+
+// function returns the shortest route through the
+// required stops alone, used when even they do not fit
+// the budget.
+fn required_only(
+    start: usize,
+    required: &[usize],
+    end: Option<usize>,
+    model: &PrizeModel,
+    prec: &Precedence,
+) -> PrizeRoute {
+    let seed = full_route(
+        start,
+        &nearest_order(start, required, model.miles, prec),
+        end,
+    );
+    let (route, _) = local_search(seed, end.is_none(), prec, |r| miles_of(r, model));
+    let cost = spent(&route, model);
+    PrizeRoute {
+        prize: collected(&route, model),
+        spent: cost,
+        feasible: cost <= limit(model) + 1e-9,
+        exact: false,
+        route,
+    }
+}
+
+// function tries every subset and ordering of the
+// candidates, together with every required stop,
+// depth first, cutting a branch as soon as it cannot
+// reach the end within the budget or breaks a
+// precedence pair. Ties in prize go to the cheaper
+// route.
+pub fn exact(
+    start: usize,
+    required: &[usize],
+    candidates: &[usize],
+    end: Option<usize>,
+    model: &PrizeModel,
+    prec: &Precedence,
+) -> PrizeRoute {
+    struct Search<'a, 'b> {
+        model: &'a PrizeModel<'b>,
+        required: &'a [usize],
+        end: Option<usize>,
+        prec: &'a Precedence,
+        best: (Vec<usize>, f64, f64),
+    }
+
+    fn dfs(s: &mut Search, path: &mut Vec<usize>, left: &mut Vec<usize>) {
+        // a pair only binds the stops a route visits, but
+        // once broken no later stop can mend it
+        if !allows(&path[1..], s.prec) {
+            return;
+        }
+        let route = full_route(path[0], &path[1..], s.end);
+        let cost = spent(&route, s.model);
+        if cost > limit(s.model) + 1e-9 {
+            return;
+        }
+        if covers(&route, s.required) {
+            let prize = collected(&route, s.model);
+            let (_, best_prize, best_cost) = s.best;
+            if prize > best_prize || (prize == best_prize && cost < best_cost) {
+                s.best = (route, prize, cost);
+            }
+        }
+        for k in 0..left.len() {
+            let next = left.remove(k);
+            path.push(next);
+            dfs(s, path, left);
+            path.pop();
+            left.insert(k, next);
+        }
+    }
+
+    let mut search = Search {
+        model,
+        required,
+        end,
+        prec,
+        best: (Vec::new(), f64::NEG_INFINITY, f64::INFINITY),
+    };
+    let mut path = vec![start];
+    let mut left = required.to_vec();
+    left.extend_from_slice(candidates);
+    dfs(&mut search, &mut path, &mut left);
+
+    let (route, prize, cost) = search.best;
+    match route.is_empty() {
+        // not even the required stops fit the budget
+        true => PrizeRoute {
+            exact: true,
+            ..required_only(start, required, end, model, prec)
+        },
+        false => PrizeRoute {
+            route,
+            prize,
+            spent: cost,
+            feasible: true,
+            exact: true,
+        },
+    }
+}
+
+// function starts from a short route through the
+// required stops and grows it by inserting the state
+// with the most prize per unit of extra cost, at its
+// cheapest position, while it fits the budget. After
+// every insertion the route is shortened with local
+// search so later states may still fit. Insertions that
+// break a precedence pair are skipped.
+pub fn heuristic(
+    start: usize,
+    required: &[usize],
+    candidates: &[usize],
+    end: Option<usize>,
+    model: &PrizeModel,
+    prec: &Precedence,
+) -> PrizeRoute {
+    let open = end.is_none();
+    let base = required_only(start, required, end, model, prec);
+    if !base.feasible {
+        return base;
+    }
+    let mut route = base.route;
+    let mut left: Vec<usize> = candidates.to_vec();
+    let budget = limit(model);
+
+    loop {
+        let base = miles_of(&route, model);
+        let mut choice: Option<(usize, usize, f64)> = None;
+        for (k, &s) in left.iter().enumerate() {
+            if model.prize[s] <= 0.0 {
+                continue;
+            }
+            let last = route.len() + open as usize;
+            for pos in 1..last {
+                route.insert(pos, s);
+                let fits = allows(&route[1..last], prec) && spent(&route, model) <= budget + 1e-9;
+                let extra = miles_of(&route, model) - base;
+                route.remove(pos);
+                if !fits {
+                    continue;
+                }
+                let ratio = model.prize[s] / extra.max(1e-6);
+                let better = match choice {
+                    Some((_, _, r)) => ratio > r,
+                    None => true,
+                };
+                if better {
+                    choice = Some((k, pos, ratio));
+                }
+            }
+        }
+        match choice {
+            Some((k, pos, _)) => {
+                let s = left.remove(k);
+                route.insert(pos, s);
+                let (shorter, _) = local_search(route, open, prec, |r| miles_of(r, model));
+                route = shorter;
+                debug!("prize insert {:?}: {:?}", s, collected(&route, model));
+            }
+            None => break,
+        }
+    }
+
+    let prize = collected(&route, model);
+    let cost = spent(&route, model);
+    PrizeRoute {
+        route,
+        prize,
+        spent: cost,
+        feasible: true,
+        exact: false,
+    }
+}
+
+// function picks the exact search for small instances,
+// otherwise the greedy insertion heuristic.
+pub fn solve(
+    start: usize,
+    required: &[usize],
+    candidates: &[usize],
+    end: Option<usize>,
+    model: &PrizeModel,
+    prec: &Precedence,
+) -> PrizeRoute {
+    let best = match required.len() + candidates.len() {
+        a if a <= EXACT_LIMIT => exact(start, required, candidates, end, model, prec),
+        _ => heuristic(start, required, candidates, end, model, prec),
+    };
+    match best.feasible {
+        true => info!(
+            "Prize collected: {:?} spending {:?} of {:?}",
+            best.prize,
+            best.spent,
+            limit(model)
+        ),
+        false => error!(
+            "Required stops alone spend {:?} of {:?}",
+            best.spent,
+            limit(model)
+        ),
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random::XorShift, solver::brute_force};
+
+    // miles between random points of a plane, so that
+    // a detour is never shorter than the direct leg as
+    // with haversine miles
+    fn random_matrix(rng: &mut XorShift, num: usize) -> Vec<Vec<f64>> {
+        let points: Vec<(f64, f64)> = (0..num)
+            .map(|_| (rng.unit() * 1000.0, rng.unit() * 1000.0))
+            .collect();
+        points
+            .iter()
+            .map(|a| {
+                points
+                    .iter()
+                    .map(|b| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt())
+                    .collect()
+            })
+            .collect()
+    }
+
+    // the best prize of any subset holding the required
+    // stops whose shortest ordering fits the budget
+    fn brute_force_prize(
+        start: usize,
+        required: &[usize],
+        candidates: &[usize],
+        end: Option<usize>,
+        model: &PrizeModel,
+    ) -> f64 {
+        let mut best = f64::NEG_INFINITY;
+        for mask in 0..1usize << candidates.len() {
+            let mut middle = required.to_vec();
+            middle.extend(
+                (0..candidates.len())
+                    .filter(|k| mask & (1 << k) != 0)
+                    .map(|k| candidates[k]),
+            );
+            let (route, _) = brute_force(start, &middle, end, &Precedence::default(), |r| {
+                miles_of(r, model)
+            });
+            if spent(&route, model) <= limit(model) + 1e-9 {
+                best = best.max(collected(&route, model));
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn exact_matches_every_subset() {
+        let mut rng = XorShift::new(36);
+        let none = Precedence::default();
+        for _ in 0..20 {
+            let num = 2 + rng.below(6);
+            let miles = random_matrix(&mut rng, num + 2);
+            let model = PrizeModel {
+                miles: &miles,
+                hours: &miles,
+                prize: (0..num + 2).map(|_| rng.below(10) as f64).collect(),
+                budget: Budget::Miles(500.0 + rng.unit() * 2500.0),
+            };
+            let required = [1];
+            let candidates: Vec<usize> = (2..=num).collect();
+            for end in [Some(num + 1), None] {
+                let best = brute_force_prize(0, &required, &candidates, end, &model);
+                let found = exact(0, &required, &candidates, end, &model, &none);
+                match best.is_finite() {
+                    true => {
+                        assert!(found.feasible);
+                        assert_eq!(found.prize, best);
+                        assert!(found.spent <= limit(&model) + 1e-9);
+                        assert_eq!(found.spent, spent(&found.route, &model));
+                        assert!(covers(&found.route, &required));
+                    }
+                    false => assert!(!found.feasible),
+                }
+                let greedy = heuristic(0, &required, &candidates, end, &model, &none);
+                if greedy.feasible {
+                    assert!(greedy.prize <= found.prize);
+                    assert!(greedy.spent <= limit(&model) + 1e-9);
+                    assert!(covers(&greedy.route, &required));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn precedence_holds_among_the_collected_stops() {
+        let miles: Vec<Vec<f64>> = (0..4)
+            .map(|a| (0..4).map(|b| (a as f64 - b as f64).abs()).collect())
+            .collect();
+        let model = PrizeModel {
+            miles: &miles,
+            hours: &miles,
+            prize: vec![0.0, 1.0, 1.0, 1.0],
+            budget: Budget::Miles(100.0),
+        };
+        let prec = Precedence {
+            pairs: vec![(3, 1)],
+        };
+        for end in [Some(0), None] {
+            let found = exact(0, &[], &[1, 2, 3], end, &model, &prec);
+            let greedy = heuristic(0, &[], &[1, 2, 3], end, &model, &prec);
+            assert_eq!(found.prize, 3.0);
+            for route in [&found.route, &greedy.route] {
+                let last = route.len() - end.is_some() as usize;
+                assert!(allows(&route[1..last], &prec));
+            }
+        }
+    }
+
+    #[test]
+    fn required_stops_are_kept_when_they_overspend() {
+        let miles = vec![
+            vec![0.0, 100.0, 100.0],
+            vec![100.0, 0.0, 1.0],
+            vec![100.0, 1.0, 0.0],
+        ];
+        let model = PrizeModel {
+            miles: &miles,
+            hours: &miles,
+            prize: vec![0.0, 1.0, 5.0],
+            budget: Budget::Miles(50.0),
+        };
+        let found = solve(0, &[1], &[2], None, &model, &Precedence::default());
+        assert!(!found.feasible);
+        assert_eq!(found.route, [0, 1]);
+        assert_eq!(found.spent, 100.0);
+    }
+
+    #[test]
+    fn a_day_budget_counts_travel_days() {
+        let hours = vec![
+            vec![0.0, 6.0, 6.0],
+            vec![6.0, 0.0, 6.0],
+            vec![6.0, 6.0, 0.0],
+        ];
+        let budget = Budget::Days {
+            days: 1.0,
+            max_hours: 10.0,
+        };
+        let model = PrizeModel {
+            miles: &hours,
+            hours: &hours,
+            prize: vec![0.0, 3.0, 4.0],
+            budget,
+        };
+        assert_eq!(spent(&[0], &model), 0.0);
+        assert_eq!(spent(&[0, 1], &model), 1.0);
+        assert_eq!(spent(&[0, 1, 2], &model), 2.0);
+        let found = exact(0, &[], &[1, 2], None, &model, &Precedence::default());
+        assert_eq!((found.route, found.prize), (vec![0, 2], 4.0));
+    }
+}
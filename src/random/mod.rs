@@ -0,0 +1,79 @@
+// Small seeded xorshift generator. The heuristics
+// only need cheap, repeatable randomness so a run
+// with the same seed gives the same routes.
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // a zero state would only ever produce zeros
+        XorShift {
+            state: seed ^ 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    // value in 0..bound
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    // value in [0.0, 1.0)
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, v: &mut [T]) {
+        for i in (1..v.len()).rev() {
+            let j = self.below(i + 1);
+            v.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = XorShift::new(2020);
+        let mut b = XorShift::new(2020);
+        let mut c = XorShift::new(2021);
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..10).map(|_| b.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(first, (0..10).map(|_| c.next_u64()).collect::<Vec<u64>>());
+        // a zero seed does not get stuck at zero
+        assert_ne!(XorShift::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn values_stay_in_range() {
+        let mut rng = XorShift::new(1);
+        for _ in 0..1000 {
+            assert!(rng.below(7) < 7);
+            let u = rng.unit();
+            assert!((0.0..1.0).contains(&u));
+        }
+    }
+
+    #[test]
+    fn shuffle_keeps_every_element() {
+        let mut rng = XorShift::new(5);
+        let mut v: Vec<usize> = (0..20).collect();
+        rng.shuffle(&mut v);
+        assert_ne!(v, (0..20).collect::<Vec<usize>>());
+        v.sort_unstable();
+        assert_eq!(v, (0..20).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::modal::Mode;
+use log::{error, info};
+use serde_json::Value;
+use std::fs;
+
+// A solved route read back from a file, with the
+// travel mode of every leg when the file has one.
+#[derive(Debug, Clone)]
+pub struct RouteFile {
+    pub route: Vec<String>,
+    pub modes: Option<Vec<Mode>>,
+    // miles the file claims for the route, if any
+    pub distance: Option<f64>,
+}
+
+// function reads a route file. Accepted shapes are a
+// json array of state codes, ["IA", "NY", ..., "DC"],
+// or an object with a "route" array and optional
+// "legs" carrying a "mode" each (modal.json).
+pub fn read_route(path: &str) -> RouteFile {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            let msg = "Error opening route file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let value: Value = match serde_json::from_str(&contents) {
+        Ok(value) => value,
+        Err(e) => {
+            let msg = "Failed to deserialize route file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let parsed = match parse_route(&value) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let msg = "Invalid route file";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    info!(
+        "Success read route of {:?} stop(s) from {:?}",
+        parsed.route.len(),
+        path
+    );
+    parsed
+}
+
+// function converts a json value into a route.
+pub fn parse_route(value: &Value) -> Result<RouteFile, String> {
+    let states = match value {
+        Value::Array(_) => value,
+        Value::Object(obj) => match obj.get("route") {
+            Some(route) => route,
+            None => return Err("object has no \"route\" field".to_owned()),
+        },
+        _ => return Err("expected an array or an object".to_owned()),
+    };
+    let route: Vec<String> = match serde_json::from_value(states.clone()) {
+        Ok(route) => route,
+        Err(e) => return Err(format!("route is not a list of state codes: {}", e)),
+    };
+
+    let modes = match value.get("legs") {
+        Some(legs) => {
+            let legs = match legs.as_array() {
+                Some(legs) => legs,
+                None => return Err("\"legs\" is not an array".to_owned()),
+            };
+            let mut modes: Vec<Mode> = Vec::with_capacity(legs.len());
+            for leg in legs {
+                match leg.get("mode").map(|m| serde_json::from_value(m.clone())) {
+                    Some(Ok(mode)) => modes.push(mode),
+                    _ => return Err(format!("leg without a valid mode: {}", leg)),
+                }
+            }
+            if modes.len() + 1 != route.len() {
+                return Err(format!(
+                    "{} legs for a route of {} stops",
+                    modes.len(),
+                    route.len()
+                ));
+            }
+            Some(modes)
+        }
+        None => None,
+    };
+    let distance = value.get("total_miles").and_then(|m| m.as_f64());
+    Ok(RouteFile {
+        route,
+        modes,
+        distance,
+    })
+}
+
+// function reads one row of a cypher.csv style file,
+// KEY,STATE_1,...,STATE_n,DISTANCE, picked by its KEY.
+// Empty state cells are ignored so rows of a
+// spreadsheet may be shorter than the header.
+pub fn read_route_row(path: &str, key: &str) -> RouteFile {
+    let mut rdr = match csv::ReaderBuilder::new().flexible(true).from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening route csv";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let header = match rdr.headers() {
+        Ok(header) => header.clone(),
+        Err(e) => {
+            let msg = "Error reading route csv header";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    for result in rdr.records() {
+        let row = match result {
+            Ok(row) => row,
+            Err(e) => {
+                let msg = "Error reading route csv row";
+                error!("{:?}: {:?}: {:?}", msg, path, e);
+                panic!("{:?}: {:?}: {:?}", msg, path, e)
+            }
+        };
+        if row.get(0).map(|k| k.trim()) != Some(key) {
+            continue;
+        }
+        let mut route: Vec<String> = Vec::new();
+        let mut distance: Option<f64> = None;
+        for (name, cell) in header.iter().zip(row.iter()) {
+            let cell = cell.trim();
+            if name.starts_with("STATE_") && !cell.is_empty() {
+                route.push(cell.to_uppercase());
+            } else if name == "DISTANCE" {
+                distance = cell.parse::<f64>().ok();
+            }
+        }
+        info!(
+            "Success read route of {:?} stop(s) from {:?} row {:?}",
+            route.len(),
+            path,
+            key
+        );
+        return RouteFile {
+            route,
+            modes: None,
+            distance,
+        };
+    }
+    let msg = "No route csv row with KEY";
+    error!("{:?}: {:?}: {:?}", msg, path, key);
+    panic!("{:?}: {:?}: {:?}", msg, path, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_an_array_or_an_object() {
+        let plain = parse_route(&json!(["IA", "NY", "DC"])).unwrap();
+        assert_eq!(plain.route, vec!["IA", "NY", "DC"]);
+        assert!(plain.modes.is_none());
+        assert!(plain.distance.is_none());
+
+        let modal = parse_route(&json!({
+            "route": ["IA", "NY", "DC"],
+            "legs": [{ "mode": "FLY" }, { "mode": "DRIVE" }],
+            "total_miles": 1234.5
+        }))
+        .unwrap();
+        assert_eq!(modal.modes, Some(vec![Mode::Fly, Mode::Drive]));
+        assert_eq!(modal.distance, Some(1234.5));
+    }
+
+    #[test]
+    fn rejects_malformed_routes() {
+        assert!(parse_route(&json!("IA")).is_err());
+        assert!(parse_route(&json!({ "states": ["IA"] })).is_err());
+        assert!(parse_route(&json!([1, 2])).is_err());
+        let short = json!({ "route": ["IA", "NY", "DC"], "legs": [{ "mode": "FLY" }] });
+        assert!(parse_route(&short).is_err());
+        let bad = json!({ "route": ["IA", "NY"], "legs": [{ "mode": "SAIL" }] });
+        assert!(parse_route(&bad).is_err());
+    }
+}
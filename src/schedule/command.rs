@@ -0,0 +1,144 @@
+// The schedule sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::{flag_value, parse_flag},
+    command::{
+        check_precedence, check_stops, modal_params, positional, read_airport_table, read_legs,
+        stamp, write_json, write_text, MODAL_JSON,
+    },
+    lookup::{stop_name, ObjLookUp},
+    schedule::{schedule as plan_itinerary, summary, to_ical, ScheduleParams},
+    timezone::{abbreviation, from_local, zone_of},
+};
+use chrono::prelude::*;
+use log::{error, info};
+use serde_json::{json, Value};
+
+const ITINERARY_JSON: &str = "itinerary.json";
+const ITINERARY_ICS: &str = "itinerary.ics";
+
+// schedule: turn a solved route into a day by day
+// itinerary with local arrival and departure times.
+//   schedule [route.json]     route file (default: modal.json)
+//   --start 2024-01-15T08:00  local time at the first capital
+//                             (default: tomorrow at --day-start)
+//   --max-drive-hours, --dwell-hours, --day-start, --day-end
+//   --airports file.csv       airport table (default: airports.csv)
+//   --constraints file.json   report broken precedence groups
+//                             and stops
+// Legs to or from AK, HI or a territory are flown;
+// flights are timed as in `modal`.
+pub fn schedule(args: &[String], data: &[ObjLookUp]) {
+    let (route, modes) = read_legs(positional(args, MODAL_JSON), data);
+
+    let defaults = ScheduleParams::default();
+    let params = ScheduleParams {
+        max_drive_hours: parse_flag(args, "--max-drive-hours", defaults.max_drive_hours),
+        dwell_hours: parse_flag(args, "--dwell-hours", defaults.dwell_hours),
+        day_start: parse_flag(args, "--day-start", defaults.day_start),
+        day_end: parse_flag(args, "--day-end", defaults.day_end),
+    };
+    if params.max_drive_hours <= 0.0 || params.day_start >= params.day_end || params.day_end > 23 {
+        let msg = "Invalid schedule limits";
+        error!(
+            "{:?}: max-drive-hours {:?} day {:?}-{:?}",
+            msg, params.max_drive_hours, params.day_start, params.day_end
+        );
+        panic!(
+            "{:?}: max-drive-hours {:?} day {:?}-{:?}",
+            msg, params.max_drive_hours, params.day_start, params.day_end
+        )
+    }
+
+    // the start time is local to the first capital
+    let zone = zone_of(data, route[0]);
+    let local = match flag_value(args, "--start") {
+        Some(v) => match NaiveDateTime::parse_from_str(v, "%Y-%m-%dT%H:%M") {
+            Ok(local) => local,
+            Err(e) => {
+                let msg = "Expected --start YYYY-MM-DDTHH:MM";
+                error!("{:?}: {:?}: {:?}", msg, v, e);
+                panic!("{:?}: {:?}: {:?}", msg, v, e)
+            }
+        },
+        None => (Local::now().date().naive_local() + chrono::Duration::days(1)).and_hms(
+            params.day_start,
+            0,
+            0,
+        ),
+    };
+    let start = from_local(zone, local);
+
+    let airports = read_airport_table(args);
+    let itin = plan_itinerary(
+        &route,
+        &modes,
+        data,
+        &airports,
+        start,
+        &modal_params(args),
+        &params,
+    );
+    let broken = check_precedence(args, &route, data);
+    let missed = check_stops(args, &route, data);
+
+    let mut days: Vec<Value> = Vec::new();
+    for event in &itin.events {
+        if days.len() < event.day {
+            days.push(json!({ "day": event.day, "events": [] }));
+        }
+        let hours = (event.end - event.start).num_minutes() as f64 / 60.0;
+        days[event.day - 1]["events"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({
+                "type": event.kind.as_str(),
+                "from": stop_name(data, event.from),
+                "to": stop_name(data, event.to),
+                "summary": summary(event, data),
+                "start": event.start_local.to_rfc3339(),
+                "end": event.end_local.to_rfc3339(),
+                "start_zone": abbreviation(zone_of(data, event.from), event.start),
+                "end_zone": abbreviation(zone_of(data, event.to), event.end),
+                "start_utc": event.start.to_rfc3339(),
+                "end_utc": event.end.to_rfc3339(),
+                "hours": (hours * 10.0).round() / 10.0,
+            }));
+    }
+    let stops: Vec<Value> = itin
+        .stops
+        .iter()
+        .map(|s| {
+            json!({
+                "state": data[s.stop].state,
+                "city": data[s.stop].city,
+                "time_zone": zone_of(data, s.stop).name,
+                "arrive": s.arrive.map(|t| t.to_rfc3339()),
+                "depart": s.depart.map(|t| t.to_rfc3339()),
+                "arrive_utc": s.arrive.map(|t| t.with_timezone(&Utc).to_rfc3339()),
+                "depart_utc": s.depart.map(|t| t.with_timezone(&Utc).to_rfc3339()),
+            })
+        })
+        .collect();
+
+    let obj = json!({
+        "beginning_state": stop_name(data, route[0]),
+        "ending_state": stop_name(data, route[route.len() - 1]),
+        "start": itin.stops.first().and_then(|s| s.depart).map(|t| t.to_rfc3339()),
+        "days": days,
+        "stops": stops,
+        "precedence_violations": broken,
+        "stop_violations": missed,
+    });
+    write_json(ITINERARY_JSON, &stamp(obj));
+
+    let ical = to_ical(&itin, data, Utc::now());
+    write_text(ITINERARY_ICS, &ical);
+    info!(
+        "Itinerary of {:?} day(s) written to {:?} and {:?}",
+        days.len(),
+        ITINERARY_JSON,
+        ITINERARY_ICS
+    );
+}
@@ -0,0 +1,496 @@
+pub mod command;
+
+use crate::{
+    costing::leg_hours,
+    lookup::ObjLookUp,
+    matrix::distance_matrix,
+    modal::{nearest_airports, Airport, ModalParams, Mode},
+    timezone::{from_local, to_local, zone_of, Zone},
+};
+use chrono::prelude::*;
+use log::info;
+
+// Daily limits of the itinerary. Hours of the day
+// are local to wherever the politician is.
+pub struct ScheduleParams {
+    pub max_drive_hours: f64,
+    pub dwell_hours: f64,
+    pub day_start: u32,
+    pub day_end: u32,
+}
+
+impl Default for ScheduleParams {
+    fn default() -> ScheduleParams {
+        ScheduleParams {
+            max_drive_hours: 10.0,
+            dwell_hours: 2.0,
+            day_start: 8,
+            day_end: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Visit,
+    Drive,
+    Fly,
+    Overnight,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Visit => "VISIT",
+            Kind::Drive => "DRIVE",
+            Kind::Fly => "FLY",
+            Kind::Overnight => "OVERNIGHT",
+        }
+    }
+}
+
+// One block of the itinerary. `from` and `to` are
+// lookup indices and are equal for a visit or an
+// overnight in a capital. An overnight in the middle
+// of a drive has from != to (en route).
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub day: usize,
+    pub kind: Kind,
+    pub from: usize,
+    pub to: usize,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub start_local: DateTime<FixedOffset>,
+    pub end_local: DateTime<FixedOffset>,
+}
+
+// Arrival and departure at each stop of the route.
+#[derive(Debug, Clone)]
+pub struct StopTime {
+    pub stop: usize,
+    pub arrive: Option<DateTime<FixedOffset>>,
+    pub depart: Option<DateTime<FixedOffset>>,
+}
+
+pub struct Itinerary {
+    pub events: Vec<Event>,
+    pub stops: Vec<StopTime>,
+}
+
+fn hours(h: f64) -> chrono::Duration {
+    chrono::Duration::seconds((h * 3600.0).round() as i64)
+}
+
+// function returns the UTC instant of an hour of the
+// local day that contains t.
+fn local_hour(zone: Zone, t: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    from_local(
+        zone,
+        to_local(zone, t).date().naive_local().and_hms(hour, 0, 0),
+    )
+}
+
+// Builds the event list while walking the route.
+struct Planner<'a> {
+    data: &'a [ObjLookUp],
+    params: &'a ScheduleParams,
+    events: Vec<Event>,
+    day: usize,
+    driven: f64,
+    t: DateTime<Utc>,
+}
+
+impl<'a> Planner<'a> {
+    fn push(&mut self, kind: Kind, from: usize, to: usize, end: DateTime<Utc>) {
+        let start = self.t;
+        self.events.push(Event {
+            day: self.day,
+            kind,
+            from,
+            to,
+            start,
+            end,
+            start_local: to_local(zone_of(self.data, from), start),
+            end_local: to_local(zone_of(self.data, to), end),
+        });
+        self.t = end;
+    }
+
+    // hours left before the end of the local day
+    fn left_today(&self, at: usize) -> f64 {
+        let end = local_hour(zone_of(self.data, at), self.t, self.params.day_end);
+        (end - self.t).num_seconds() as f64 / 3600.0
+    }
+
+    // sleep until the next local day start
+    fn overnight(&mut self, from: usize, to: usize) {
+        let zone = zone_of(self.data, from);
+        let mut morning = local_hour(zone, self.t, self.params.day_start);
+        if morning <= self.t {
+            morning = local_hour(
+                zone,
+                self.t + chrono::Duration::days(1),
+                self.params.day_start,
+            );
+        }
+        self.push(Kind::Overnight, from, to, morning);
+        self.day += 1;
+        self.driven = 0.0;
+    }
+
+    // wait for the day start if it is too early, sleep
+    // if `need` hours no longer fit in the day
+    fn fit(&mut self, at: usize, need: f64) {
+        let zone = zone_of(self.data, at);
+        let morning = local_hour(zone, self.t, self.params.day_start);
+        if self.t < morning {
+            self.t = morning;
+        }
+        // a block longer than a whole day starts in the morning
+        if self.left_today(at) < need && self.t > morning {
+            self.overnight(at, at);
+        }
+    }
+
+    fn visit(&mut self, at: usize) -> (DateTime<Utc>, DateTime<Utc>) {
+        self.fit(at, self.params.dwell_hours);
+        let arrive = self.t;
+        let end = self.t + hours(self.params.dwell_hours);
+        self.push(Kind::Visit, at, at, end);
+        (arrive, end)
+    }
+}
+
+// function turns a solved route into a day by day
+// itinerary. Flights, timed as in costing::leg_hours
+// from the airport table, are taken whole within a day,
+// drives stop at the driving limit or the end of the
+// day and resume the next morning, and every capital
+// gets `dwell_hours` of campaigning within the day.
+pub fn schedule(
+    route: &[usize],
+    modes: &[Mode],
+    data: &[ObjLookUp],
+    airports: &[Airport],
+    start: DateTime<Utc>,
+    modal: &ModalParams,
+    params: &ScheduleParams,
+) -> Itinerary {
+    let miles = distance_matrix(data);
+    let airport = nearest_airports(data, airports);
+    let mut plan = Planner {
+        data,
+        params,
+        events: Vec::new(),
+        day: 1,
+        driven: 0.0,
+        t: start,
+    };
+    let mut stops: Vec<StopTime> = Vec::with_capacity(route.len());
+
+    if route.is_empty() {
+        return Itinerary {
+            events: Vec::new(),
+            stops,
+        };
+    }
+
+    let (_, depart) = plan.visit(route[0]);
+    stops.push(StopTime {
+        stop: route[0],
+        arrive: None,
+        depart: Some(to_local(zone_of(data, route[0]), depart)),
+    });
+
+    for (w, &mode) in route.windows(2).zip(modes) {
+        let (from, to) = (w[0], w[1]);
+        let h = leg_hours(from, to, mode, &miles, &airport, airports, modal);
+
+        match mode {
+            Mode::Fly => {
+                plan.fit(from, h);
+                let end = plan.t + hours(h);
+                plan.push(Kind::Fly, from, to, end);
+            }
+            Mode::Drive => {
+                let mut remaining = h;
+                let mut first = true;
+                plan.fit(from, 0.0);
+                while remaining > 1e-9 {
+                    let avail = (params.max_drive_hours - plan.driven).min(plan.left_today(from));
+                    if avail <= 1e-9 {
+                        // sleep at the capital before setting
+                        // out, otherwise en route
+                        match first {
+                            true => plan.overnight(from, from),
+                            false => plan.overnight(from, to),
+                        }
+                        continue;
+                    }
+                    let seg = remaining.min(avail);
+                    let end = plan.t + hours(seg);
+                    plan.push(Kind::Drive, from, to, end);
+                    plan.driven += seg;
+                    remaining -= seg;
+                    first = false;
+                }
+            }
+        }
+
+        let arrive = plan.t;
+        let (_, depart) = plan.visit(to);
+        let zone = zone_of(data, to);
+        stops.push(StopTime {
+            stop: to,
+            arrive: Some(to_local(zone, arrive)),
+            depart: Some(to_local(zone, depart)),
+        });
+    }
+
+    // no departure from the final stop
+    if let Some(last) = stops.last_mut() {
+        last.depart = None;
+    }
+    info!(
+        "Itinerary: {:?} event(s) over {:?} day(s)",
+        plan.events.len(),
+        plan.day
+    );
+    Itinerary {
+        events: plan.events,
+        stops,
+    }
+}
+
+// function returns a one line summary of an event.
+pub fn summary(event: &Event, data: &[ObjLookUp]) -> String {
+    let (a, b) = (&data[event.from], &data[event.to]);
+    match event.kind {
+        Kind::Visit => format!("Campaign stop: {}, {}", a.city, a.state),
+        Kind::Drive => format!("Drive {} to {}", a.state, b.state),
+        Kind::Fly => format!("Fly {} to {}", a.state, b.state),
+        Kind::Overnight if event.from == event.to => format!("Overnight: {}, {}", a.city, a.state),
+        Kind::Overnight => format!("Overnight en route {} to {}", a.state, b.state),
+    }
+}
+
+// function escapes the backslashes, semicolons, commas
+// and newlines of an iCalendar TEXT value (RFC 5545
+// 3.3.11).
+pub fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => (),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// function folds a content line longer than 75 octets
+// into continuation lines that start with a space (RFC
+// 5545 3.1), never splitting a character.
+pub fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            // the leading space counts towards the line
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out
+}
+
+// function renders the itinerary as an iCalendar
+// (RFC 5545) document with times in UTC.
+pub fn to_ical(itin: &Itinerary, data: &[ObjLookUp], stamp: DateTime<Utc>) -> String {
+    let fmt = "%Y%m%dT%H%M%SZ";
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//The Traveling Politician Problem//read_json//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+    ];
+    for (n, event) in itin.events.iter().enumerate() {
+        let location = match event.kind {
+            Kind::Visit | Kind::Overnight => {
+                format!("{}, {}", data[event.from].city, data[event.from].state)
+            }
+            _ => format!("{} - {}", data[event.from].state, data[event.to].state),
+        };
+        lines.push("BEGIN:VEVENT".to_owned());
+        lines.push(format!(
+            "UID:{}-{}-{}@read_json",
+            stamp.format("%Y%m%d%H%M%S"),
+            n,
+            event.kind.as_str()
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp.format(fmt)));
+        lines.push(format!("DTSTART:{}", event.start.format(fmt)));
+        lines.push(format!("DTEND:{}", event.end.format(fmt)));
+        lines.push(format!("SUMMARY:{}", escape_text(&summary(event, data))));
+        lines.push(format!("LOCATION:{}", escape_text(&location)));
+        lines.push(format!("CATEGORIES:{}", event.kind.as_str()));
+        lines.push("END:VEVENT".to_owned());
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    let folded: Vec<String> = lines.iter().map(|l| fold(l)).collect();
+    let mut ical = folded.join("\r\n");
+    ical.push_str("\r\n");
+    ical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lookup::{read_look_up, state_index},
+        modal::{build_model, read_airports},
+    };
+
+    fn start() -> DateTime<Utc> {
+        Utc.ymd(2024, 3, 4).and_hms(14, 0, 0)
+    }
+
+    fn drive_hours(event: &Event) -> f64 {
+        (event.end - event.start).num_seconds() as f64 / 3600.0
+    }
+
+    #[test]
+    fn escapes_text_values() {
+        assert_eq!(escape_text("a;b,c\\d\ne\r"), "a\\;b\\,c\\\\d\\ne");
+        assert_eq!(escape_text("DES MOINES, IA"), "DES MOINES\\, IA");
+    }
+
+    #[test]
+    fn folds_long_lines_without_splitting_a_character() {
+        let line = format!("SUMMARY:{}", "Campaign stop: Saint-Étienne, ÎLE ".repeat(6));
+        let folded = fold(&line);
+        for (n, part) in folded.split("\r\n").enumerate() {
+            assert!(part.len() <= 75);
+            assert_eq!(n > 0, part.starts_with(' '));
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(fold("VERSION:2.0"), "VERSION:2.0");
+    }
+
+    #[test]
+    fn a_long_drive_stops_at_the_daily_limits() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let route = [at("IA"), at("CA")];
+        let (modal, params) = (ModalParams::default(), ScheduleParams::default());
+        let airports = read_airports("airports.csv");
+        let itin = schedule(
+            &route,
+            &[Mode::Drive],
+            &data,
+            &airports,
+            start(),
+            &modal,
+            &params,
+        );
+
+        let miles = distance_matrix(&data);
+        let total: f64 = itin
+            .events
+            .iter()
+            .filter(|e| e.kind == Kind::Drive)
+            .map(drive_hours)
+            .sum();
+        let airport = nearest_airports(&data, &airports);
+        let expected = leg_hours(
+            route[0],
+            route[1],
+            Mode::Drive,
+            &miles,
+            &airport,
+            &airports,
+            &modal,
+        );
+        assert!((total - expected).abs() < 1e-3);
+        assert!(expected > params.max_drive_hours);
+
+        let last_day = itin.events.last().unwrap().day;
+        for day in 1..=last_day {
+            let driven: f64 = itin
+                .events
+                .iter()
+                .filter(|e| e.day == day && e.kind == Kind::Drive)
+                .map(drive_hours)
+                .sum();
+            assert!(driven <= params.max_drive_hours + 1e-3);
+        }
+        assert!(itin
+            .events
+            .iter()
+            .any(|e| e.kind == Kind::Overnight && e.from != e.to));
+        for w in itin.events.windows(2) {
+            assert!(w[1].start >= w[0].end);
+        }
+        for e in itin.events.iter().filter(|e| e.kind == Kind::Visit) {
+            assert!(e.start_local.hour() >= params.day_start);
+            assert!(e.end_local.hour() <= params.day_end);
+        }
+
+        assert_eq!(itin.stops.len(), 2);
+        assert!(itin.stops[0].arrive.is_none());
+        assert!(itin.stops[1].depart.is_none());
+    }
+
+    #[test]
+    fn a_flight_lasts_as_long_as_in_the_modal_model() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let route = [at("IA"), at("HI")];
+        let airports = read_airports("airports.csv");
+        let modal = ModalParams::default();
+        let itin = schedule(
+            &route,
+            &[Mode::Fly],
+            &data,
+            &airports,
+            start(),
+            &modal,
+            &ScheduleParams::default(),
+        );
+        let flight = itin.events.iter().find(|e| e.kind == Kind::Fly).unwrap();
+        let minutes = (flight.end - flight.start).num_minutes() as f64;
+        let expected = build_model(&data, &airports, &modal).fly[route[0]][route[1]];
+        assert!((minutes / 60.0 - expected).abs() < 1.0 / 60.0);
+    }
+
+    #[test]
+    fn ical_has_one_event_per_block() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let route = [at("IA"), at("IL"), at("NY")];
+        let itin = schedule(
+            &route,
+            &[Mode::Drive, Mode::Fly],
+            &data,
+            &read_airports("airports.csv"),
+            start(),
+            &ModalParams::default(),
+            &ScheduleParams::default(),
+        );
+        let ical = to_ical(&itin, &data, start());
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ical.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ical.matches("BEGIN:VEVENT").count(), itin.events.len());
+        assert_eq!(ical.matches("END:VEVENT").count(), itin.events.len());
+        assert!(ical.contains("LOCATION:DES MOINES\\, IA\r\n"));
+        assert!(ical.contains("SUMMARY:Fly IL to NY\r\n"));
+        assert!(ical.split("\r\n").all(|l| l.len() <= 75));
+    }
+}
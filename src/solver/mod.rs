@@ -0,0 +1,464 @@
+use crate::precedence::{allows, predecessor_masks, ready, Precedence};
+use log::{debug, info};
+use permutohedron::Heap;
+
+// Instances with at most this many states between the
+// start and the end are solved by brute force (8! routes).
+pub const BRUTE_LIMIT: usize = 8;
+// Held-Karp keeps 2^n * n partial routes in memory.
+pub const HELD_KARP_LIMIT: usize = 16;
+
+// function joins start, the middle states and end.
+// Without an end the route is an open path; an end
+// equal to start closes the tour.
+pub fn full_route(start: usize, middle: &[usize], end: Option<usize>) -> Vec<usize> {
+    let mut route = Vec::with_capacity(middle.len() + 2);
+    route.push(start);
+    route.extend_from_slice(middle);
+    route.extend(end);
+    route
+}
+
+// function tries every ordering of middle and returns
+// the cheapest full route and its cost. Orderings that
+// break a precedence pair are skipped.
+pub fn brute_force<F>(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    prec: &Precedence,
+    cost: F,
+) -> (Vec<usize>, f64)
+where
+    F: Fn(&[usize]) -> f64,
+{
+    let mut best = full_route(start, middle, end);
+    let mut best_cost = match allows(middle, prec) {
+        true => cost(&best),
+        false => f64::INFINITY,
+    };
+    let mut data = middle.to_vec();
+
+    if data.len() > 1 {
+        let heap = Heap::new(&mut data);
+        for (iv, perm) in heap.enumerate() {
+            if !allows(&perm, prec) {
+                continue;
+            }
+            let route = full_route(start, &perm, end);
+            let c = cost(&route);
+            debug!("brute force #{:?}: {:?} {:?}", iv, route, c);
+            if c < best_cost {
+                best = route;
+                best_cost = c;
+            }
+        }
+    }
+    info!("Brute force best: {:?}", best_cost);
+    (best, best_cost)
+}
+
+// function returns true when middle is the first of a
+// mirrored pair of orderings: a closed tour and its
+// reverse are the same loop, so only orderings that
+// sort no later than their reverse are kept, leaving
+// (n-1)!/2 tours. Comparing whole orderings rather than
+// the first and last index keeps one of each pair when
+// a revisited state both starts and ends the middle.
+pub fn first_of_mirror(middle: &[usize]) -> bool {
+    middle.iter().le(middle.iter().rev())
+}
+
+// function tries every closed tour from start once:
+// start is fixed, which removes the rotations, and
+// mirrored orderings are skipped. The cost must not
+// depend on the direction of the tour. Precedence
+// pairs are direction dependent, so with any pair
+// every ordering is tried.
+pub fn tour_brute_force<F>(
+    start: usize,
+    middle: &[usize],
+    prec: &Precedence,
+    cost: F,
+) -> (Vec<usize>, f64)
+where
+    F: Fn(&[usize]) -> f64,
+{
+    if !prec.pairs.is_empty() {
+        return brute_force(start, middle, Some(start), prec, cost);
+    }
+    let mut best = full_route(start, middle, Some(start));
+    let mut best_cost = cost(&best);
+    let mut data = middle.to_vec();
+    let mut tours = 0;
+
+    if data.len() > 2 {
+        let heap = Heap::new(&mut data);
+        for perm in heap.filter(|p| first_of_mirror(p)) {
+            let route = full_route(start, &perm, Some(start));
+            let c = cost(&route);
+            tours += 1;
+            debug!("tour #{:?}: {:?} {:?}", tours, route, c);
+            if c < best_cost {
+                best = route;
+                best_cost = c;
+            }
+        }
+    }
+    info!(
+        "Tour brute force best of {:?} tour(s): {:?}",
+        tours, best_cost
+    );
+    (best, best_cost)
+}
+
+// function orders middle by repeatedly visiting the
+// closest remaining state whose predecessors are visited.
+pub fn nearest_order(
+    start: usize,
+    middle: &[usize],
+    matrix: &[Vec<f64>],
+    prec: &Precedence,
+) -> Vec<usize> {
+    let mut left = middle.to_vec();
+    let mut order = Vec::with_capacity(left.len());
+    let mut at = start;
+    while !left.is_empty() {
+        let open: Vec<usize> = (0..left.len())
+            .filter(|&i| ready(left[i], &order, middle, prec))
+            .collect();
+        // a cycle leaves nothing ready, fall back to all
+        let open = match open.is_empty() {
+            true => (0..left.len()).collect(),
+            false => open,
+        };
+        let mut best = open[0];
+        for &i in &open[1..] {
+            if matrix[at][left[i]] < matrix[at][left[best]] {
+                best = i;
+            }
+        }
+        at = left.remove(best);
+        order.push(at);
+    }
+    order
+}
+
+// function solves a route exactly with the Held-Karp
+// dynamic program over subsets of middle. An open path
+// ends wherever is cheapest.
+// A state joins a subset only once the subset holds
+// all of its predecessors.
+pub fn held_karp(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    matrix: &[Vec<f64>],
+    prec: &Precedence,
+) -> (Vec<usize>, f64) {
+    let num = middle.len();
+    if num == 0 {
+        let route = full_route(start, &[], end);
+        let c = end.map_or(0.0, |e| matrix[start][e]);
+        return (route, c);
+    }
+    let full = 1usize << num;
+    // cost[mask][k]: cheapest path from start through
+    // mask ending at middle[k]
+    let mut cost = vec![vec![f64::INFINITY; num]; full];
+    let mut prev = vec![vec![usize::MAX; num]; full];
+    let before = predecessor_masks(middle, prec);
+
+    for k in 0..num {
+        if before[k] == 0 {
+            cost[1 << k][k] = matrix[start][middle[k]];
+        }
+    }
+    for mask in 1..full {
+        for k in 0..num {
+            if mask & (1 << k) == 0 || cost[mask][k].is_infinite() {
+                continue;
+            }
+            for n in 0..num {
+                if mask & (1 << n) != 0 || before[n] & !mask != 0 {
+                    continue;
+                }
+                let next = mask | (1 << n);
+                let c = cost[mask][k] + matrix[middle[k]][middle[n]];
+                if c < cost[next][n] {
+                    cost[next][n] = c;
+                    prev[next][n] = k;
+                }
+            }
+        }
+    }
+
+    let mut last = 0;
+    let mut best = f64::INFINITY;
+    for k in 0..num {
+        let c = cost[full - 1][k] + end.map_or(0.0, |e| matrix[middle[k]][e]);
+        if c < best {
+            best = c;
+            last = k;
+        }
+    }
+
+    // walk the predecessors back to the start
+    let mut order = Vec::with_capacity(num);
+    let mut mask = full - 1;
+    let mut k = last;
+    while k != usize::MAX {
+        order.push(middle[k]);
+        let p = prev[mask][k];
+        mask &= !(1 << k);
+        k = p;
+    }
+    order.reverse();
+    info!("Held-Karp best: {:?}", best);
+    (full_route(start, &order, end), best)
+}
+
+// function improves a route with 2-opt (reverse a
+// segment) and relocate (move one state) moves until
+// no move lowers the cost. The first entry never moves,
+// nor does the last one unless the path is `open`.
+// Moves that break a precedence pair are rejected.
+pub fn local_search<F>(
+    mut route: Vec<usize>,
+    open: bool,
+    prec: &Precedence,
+    cost: F,
+) -> (Vec<usize>, f64)
+where
+    F: Fn(&[usize]) -> f64,
+{
+    let mut best = cost(&route);
+    let last = match open {
+        true => route.len(),
+        false => route.len().saturating_sub(1),
+    };
+    let mut improved = true;
+
+    while improved {
+        improved = false;
+
+        // 2-opt
+        for i in 1..last {
+            for j in (i + 1)..last {
+                route[i..=j].reverse();
+                let c = match allows(&route[1..last], prec) {
+                    true => cost(&route),
+                    false => f64::INFINITY,
+                };
+                if c < best - 1e-9 {
+                    best = c;
+                    improved = true;
+                } else {
+                    route[i..=j].reverse();
+                }
+            }
+        }
+
+        // relocate
+        for i in 1..last {
+            for j in 1..last {
+                if i == j {
+                    continue;
+                }
+                let s = route.remove(i);
+                route.insert(j, s);
+                let c = match allows(&route[1..last], prec) {
+                    true => cost(&route),
+                    false => f64::INFINITY,
+                };
+                if c < best - 1e-9 {
+                    best = c;
+                    improved = true;
+                } else {
+                    let s = route.remove(j);
+                    route.insert(i, s);
+                }
+            }
+        }
+        debug!("local search pass: {:?}", best);
+    }
+    info!("Local search best: {:?}", best);
+    (route, best)
+}
+
+// function picks a solver for a route from start. A
+// closed tour (end == start) is costed the same in
+// either direction.
+// brute force for small instances, otherwise a nearest
+// neighbour (or Held-Karp when affordable) route on
+// `matrix` improved by local search on `cost`. Every
+// step honors the precedence pairs.
+pub fn solve<F>(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    matrix: &[Vec<f64>],
+    prec: &Precedence,
+    cost: F,
+) -> (Vec<usize>, f64)
+where
+    F: Fn(&[usize]) -> f64,
+{
+    match middle.len() {
+        a if a <= BRUTE_LIMIT && end == Some(start) => tour_brute_force(start, middle, prec, cost),
+        a if a <= BRUTE_LIMIT => brute_force(start, middle, end, prec, cost),
+        a if a <= HELD_KARP_LIMIT => {
+            let (seed, _) = held_karp(start, middle, end, matrix, prec);
+            local_search(seed, end.is_none(), prec, cost)
+        }
+        _ => {
+            let order = nearest_order(start, middle, matrix, prec);
+            local_search(full_route(start, &order, end), end.is_none(), prec, cost)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+
+    fn closed_cost(matrix: &[Vec<f64>]) -> impl Fn(&[usize]) -> f64 + '_ {
+        move |r: &[usize]| r.windows(2).map(|w| matrix[w[0]][w[1]]).sum()
+    }
+
+    fn random_matrix(rng: &mut XorShift, num: usize) -> Vec<Vec<f64>> {
+        (0..num)
+            .map(|i| {
+                (0..num)
+                    .map(|j| match i == j {
+                        true => 0.0,
+                        false => (rng.below(1000) + 1) as f64,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_route_opens_or_closes_the_path() {
+        assert_eq!(full_route(0, &[3, 1], Some(2)), [0, 3, 1, 2]);
+        assert_eq!(full_route(0, &[3, 1], None), [0, 3, 1]);
+        assert_eq!(full_route(0, &[3, 1], Some(0)), [0, 3, 1, 0]);
+        assert_eq!(full_route(0, &[], None), [0]);
+    }
+
+    #[test]
+    fn held_karp_matches_brute_force() {
+        let mut rng = XorShift::new(27);
+        for _ in 0..30 {
+            let num = 1 + rng.below(8);
+            let matrix = random_matrix(&mut rng, num + 2);
+            let middle: Vec<usize> = (1..=num).collect();
+            for end in [Some(num + 1), None] {
+                let prec = Precedence::default();
+                let (route, c) = held_karp(0, &middle, end, &matrix, &prec);
+                let (_, best) = brute_force(0, &middle, end, &prec, closed_cost(&matrix));
+                assert_eq!(c, best);
+                assert_eq!(closed_cost(&matrix)(&route), best);
+                assert_eq!(route.len(), num + 1 + end.is_some() as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn held_karp_keeps_to_precedence() {
+        let mut rng = XorShift::new(32);
+        for _ in 0..30 {
+            let num = 3 + rng.below(6);
+            let matrix = random_matrix(&mut rng, num + 2);
+            let middle: Vec<usize> = (1..=num).collect();
+            // pairs that follow a random order never form a cycle
+            let mut order = middle.clone();
+            rng.shuffle(&mut order);
+            let pairs = vec![(order[0], order[1]), (order[1], order[num - 1])];
+            let prec = Precedence { pairs };
+            for end in [Some(num + 1), None] {
+                let (route, c) = held_karp(0, &middle, end, &matrix, &prec);
+                let (_, best) = brute_force(0, &middle, end, &prec, closed_cost(&matrix));
+                assert_eq!(c, best);
+                assert!(allows(&route, &prec));
+                assert_eq!(closed_cost(&matrix)(&route), best);
+            }
+        }
+    }
+
+    #[test]
+    fn local_search_never_makes_a_route_worse() {
+        let mut rng = XorShift::new(4);
+        let matrix = random_matrix(&mut rng, 12);
+        let middle: Vec<usize> = (1..11).collect();
+        let start = full_route(0, &middle, Some(11));
+        let before = closed_cost(&matrix)(&start);
+        let (route, c) = local_search(start, false, &Precedence::default(), closed_cost(&matrix));
+        assert!(c <= before);
+        assert_eq!(c, closed_cost(&matrix)(&route));
+        assert_eq!((route[0], route[11]), (0, 11));
+        let mut stops = route[1..11].to_vec();
+        stops.sort_unstable();
+        assert_eq!(stops, middle);
+    }
+
+    #[test]
+    fn first_of_mirror_keeps_one_of_each_pair() {
+        assert!(first_of_mirror(&[1, 2, 3]));
+        assert!(!first_of_mirror(&[3, 2, 1]));
+        // a revisited state first and last: decided by
+        // the states in between
+        assert!(first_of_mirror(&[1, 2, 3, 1]));
+        assert!(!first_of_mirror(&[1, 3, 2, 1]));
+        // a palindrome is its own mirror
+        assert!(first_of_mirror(&[1, 2, 1]));
+        assert!(first_of_mirror(&[]));
+        assert!(first_of_mirror(&[4]));
+    }
+
+    #[test]
+    fn tour_brute_force_matches_every_ordering() {
+        let mut rng = XorShift::new(34);
+        for _ in 0..20 {
+            let num = 1 + rng.below(7);
+            let upper = random_matrix(&mut rng, num + 1);
+            let matrix: Vec<Vec<f64>> = (0..=num)
+                .map(|i| (0..=num).map(|j| upper[i.min(j)][i.max(j)]).collect())
+                .collect();
+            let middle: Vec<usize> = (1..=num).collect();
+            let none = Precedence::default();
+            let (route, c) = tour_brute_force(0, &middle, &none, closed_cost(&matrix));
+            let (_, best) = brute_force(0, &middle, Some(0), &none, closed_cost(&matrix));
+            assert_eq!(c, best);
+            assert_eq!((route[0], route[num + 1]), (0, 0));
+            assert_eq!(closed_cost(&matrix)(&route), best);
+            // a direction dependent pair tries every ordering
+            let prec = Precedence {
+                pairs: vec![(num, 1)],
+            };
+            let (route, c) = tour_brute_force(0, &middle, &prec, closed_cost(&matrix));
+            let (_, best) = brute_force(0, &middle, Some(0), &prec, closed_cost(&matrix));
+            assert_eq!(c, best);
+            assert!(allows(&route, &prec));
+        }
+    }
+
+    #[test]
+    fn tour_visits_a_revisited_state_first_and_last() {
+        // 0 is the start; 1 is visited twice and two
+        // visits in a row cost a penalty, so the only
+        // good tour puts 1 first and last
+        let penalty = 1.0e6;
+        let matrix = vec![
+            vec![0.0, 1.0, 5.0],
+            vec![1.0, penalty, 1.0],
+            vec![5.0, 1.0, 0.0],
+        ];
+        let (route, cost) =
+            tour_brute_force(0, &[1, 1, 2], &Precedence::default(), closed_cost(&matrix));
+        assert_eq!(route, vec![0, 1, 2, 1, 0]);
+        assert_eq!(cost, 4.0);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    constraints::Constraints,
+    lookup::{stop_index, stop_name, ObjLookUp},
+};
+use log::{error, info};
+use std::collections::HashMap;
+
+// Cost of leaving a stop for the same stop, so the
+// solvers keep the visits of a revisited stop apart.
+pub const REPEAT_PENALTY: f64 = 1.0e6;
+
+// Which stops a route must or may visit and how often,
+// by stop name (lookup::stop_name): the state code, or
+// the zip code of a later record of a state. Stops in
+// neither list are visited when they are among the
+// route's stops, as before.
+#[derive(Debug, Clone, Default)]
+pub struct Stops {
+    // visited at least once, and `visits` times when
+    // the solvers build the route
+    pub required: Vec<String>,
+    // may be skipped; only the prize command visits them
+    pub optional: Vec<String>,
+    // visits per stop, 1 when not listed: the count
+    // of a required stop, the most for the others
+    pub visits: HashMap<String, usize>,
+}
+
+impl Stops {
+    pub fn visits_of(&self, state: &str) -> usize {
+        self.visits.get(state).copied().unwrap_or(1)
+    }
+
+    pub fn is_required(&self, state: &str) -> bool {
+        self.required.iter().any(|s| s == state)
+    }
+
+    pub fn is_optional(&self, state: &str) -> bool {
+        self.optional.iter().any(|s| s == state)
+    }
+}
+
+// function reads the required, optional and visits
+// entries of a constraints file, checking every stop
+// against the lookup. A zip code of the first record
+// of a state is read as the state code.
+pub fn from_constraints(data: &[ObjLookUp], cons: &Constraints) -> Stops {
+    let known = |name: &String| match stop_index(data, name) {
+        Some(i) => stop_name(data, i),
+        None => {
+            let msg = "Stop not in the lookup";
+            error!("{:?}: {:?}", msg, name);
+            panic!("{:?}: {:?}", msg, name)
+        }
+    };
+    let required: Vec<String> = cons.required.iter().map(known).collect();
+    let optional: Vec<String> = cons.optional.iter().map(known).collect();
+    let visits: HashMap<String, usize> = cons.visits.iter().map(|(k, &n)| (known(k), n)).collect();
+
+    if let Some(name) = required.iter().find(|s| optional.contains(s)) {
+        let msg = "Stop is both required and optional";
+        error!("{:?}: {:?}", msg, name);
+        panic!("{:?}: {:?}", msg, name)
+    }
+    if let Some((name, _)) = visits.iter().find(|(_, &n)| n == 0) {
+        let msg = "A stop must be allowed at least one visit";
+        error!("{:?}: {:?}", msg, name);
+        panic!("{:?}: {:?}", msg, name)
+    }
+    info!(
+        "Stops: {:?} required, {:?} optional, {:?} visit limit(s)",
+        required.len(),
+        optional.len(),
+        visits.len()
+    );
+    Stops {
+        required,
+        optional,
+        visits,
+    }
+}
+
+// function returns how many times the route visits a
+// stop between its start and end. The start and end
+// count as visits of their own stops.
+pub fn visits_between(name: &str, start: &str, end: Option<&str>, stops: &Stops) -> usize {
+    let ends = (start == name) as usize + (end == Some(name)) as usize;
+    stops.visits_of(name).saturating_sub(ends)
+}
+
+// function returns true when a stop appears twice in
+// the stops between the start and the end.
+pub fn has_repeats(middle: &[usize]) -> bool {
+    middle
+        .iter()
+        .enumerate()
+        .any(|(k, i)| middle[..k].contains(i))
+}
+
+// function returns a copy of a cost matrix in which
+// every leg from a stop to itself costs
+// REPEAT_PENALTY. Records of the same state are
+// different stops and keep their costs.
+pub fn apart(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let mut out = matrix.to_vec();
+    for (i, row) in out.iter_mut().enumerate() {
+        row[i] = REPEAT_PENALTY;
+    }
+    out
+}
+
+// function counts the legs of a route that stay at
+// the same stop.
+pub fn repeat_legs(route: &[usize]) -> usize {
+    route.windows(2).filter(|w| w[0] == w[1]).count()
+}
+
+// function explains how a route breaks the stops: a
+// required stop left out or visited too few times, a
+// stop visited more often than allowed, or a stop
+// visited twice in a row. The return to the start of
+// a closed tour is not a visit.
+pub fn violations(route: &[usize], stops: &Stops, data: &[ObjLookUp]) -> Vec<String> {
+    let closed = route.len() > 1 && route[0] == route[route.len() - 1];
+    let visited = match closed {
+        true => &route[..route.len() - 1],
+        false => route,
+    };
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for &i in visited {
+        let name = stop_name(data, i);
+        match counts.iter_mut().find(|(s, _)| *s == name) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((name, 1)),
+        }
+    }
+    let count = |name: &str| {
+        counts
+            .iter()
+            .find(|(s, _)| s == name)
+            .map_or(0, |&(_, n)| n)
+    };
+
+    let mut found: Vec<String> = Vec::new();
+    for state in &stops.required {
+        let n = count(state);
+        if n == 0 {
+            found.push(format!("{} is required but not visited", state));
+        } else if n < stops.visits_of(state) {
+            found.push(format!(
+                "{} is visited {} time(s), {} required",
+                state,
+                n,
+                stops.visits_of(state)
+            ));
+        }
+    }
+    for (state, n) in &counts {
+        let n = *n;
+        if n > stops.visits_of(state) {
+            found.push(format!(
+                "{} is visited {} time(s), at most {} allowed",
+                state,
+                n,
+                stops.visits_of(state)
+            ));
+        }
+    }
+    for w in route.windows(2) {
+        if w[0] == w[1] {
+            found.push(format!(
+                "{} is visited twice in a row",
+                stop_name(data, w[0])
+            ));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::test_record;
+
+    // IA, OH (Columbus), DC and a second OH record
+    fn data() -> Vec<ObjLookUp> {
+        vec![
+            test_record("50319", "IA"),
+            test_record("43215", "OH"),
+            test_record("20004", "DC"),
+            test_record("44114", "OH"),
+        ]
+    }
+
+    fn constraints(json: &str) -> Constraints {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reads_stops_by_name() {
+        let cons = constraints(
+            r#"{"required": ["DC", "44114"], "optional": ["43215"], "visits": {"DC": 2}}"#,
+        );
+        let stops = from_constraints(&data(), &cons);
+        // the zip code of the first OH record reads as OH
+        assert_eq!(stops.required, ["DC", "44114"]);
+        assert_eq!(stops.optional, ["OH"]);
+        assert!(stops.is_required("44114") && stops.is_optional("OH"));
+        assert_eq!((stops.visits_of("DC"), stops.visits_of("IA")), (2, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Stop is both required and optional")]
+    fn a_stop_cannot_be_required_and_optional() {
+        let cons = constraints(r#"{"required": ["OH"], "optional": ["43215"]}"#);
+        from_constraints(&data(), &cons);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stop not in the lookup")]
+    fn an_unknown_stop_is_refused() {
+        from_constraints(&data(), &constraints(r#"{"required": ["NY"]}"#));
+    }
+
+    #[test]
+    fn the_ends_count_as_visits() {
+        let cons = constraints(r#"{"visits": {"DC": 3, "OH": 2}}"#);
+        let stops = from_constraints(&data(), &cons);
+        assert_eq!(visits_between("DC", "IA", Some("DC"), &stops), 2);
+        assert_eq!(visits_between("OH", "OH", Some("OH"), &stops), 0);
+        assert_eq!(visits_between("OH", "IA", None, &stops), 2);
+        assert_eq!(visits_between("IA", "IA", Some("DC"), &stops), 0);
+    }
+
+    #[test]
+    fn repeats_are_kept_apart() {
+        assert!(has_repeats(&[1, 2, 1]));
+        assert!(!has_repeats(&[1, 2, 3]));
+        let matrix = apart(&[vec![0.0, 4.0], vec![4.0, 0.0]]);
+        assert_eq!(matrix, [[REPEAT_PENALTY, 4.0], [4.0, REPEAT_PENALTY]]);
+        assert_eq!(repeat_legs(&[0, 1, 1, 2, 2, 2]), 3);
+    }
+
+    #[test]
+    fn explains_how_a_route_breaks_the_stops() {
+        let data = data();
+        let cons = constraints(r#"{"required": ["DC", "44114"], "visits": {"DC": 2}}"#);
+        let stops = from_constraints(&data, &cons);
+        assert!(violations(&[0, 2, 3, 1, 2], &stops, &data).is_empty());
+        assert_eq!(
+            violations(&[0, 1, 1, 2], &stops, &data),
+            [
+                "DC is visited 1 time(s), 2 required",
+                "44114 is required but not visited",
+                "OH is visited 2 time(s), at most 1 allowed",
+                "OH is visited twice in a row",
+            ]
+        );
+        // the return to the start of a closed tour
+        let stops = Stops::default();
+        assert!(violations(&[0, 1, 3, 0], &stops, &data).is_empty());
+    }
+}
@@ -38,3 +38,25 @@ pub fn vec_row(row_num: usize, distance: f64, mut vec_row: Vec<&str>) -> Vec<&st
     vec.push(dist); // Distance
     vec // return vec
 }
+
+// function to create the heading of a multi objective
+// csv: KEY, one column per state and one column per
+// objective in place of the single DISTANCE column.
+pub fn title_objectives(route_len: usize, names: &[String]) -> Vec<String> {
+    let mut header: Vec<String> = vec!["KEY".to_owned()];
+    for i in 1..=route_len {
+        header.push(format!("STATE_{}", i));
+    }
+    for name in names {
+        header.push(name.to_uppercase());
+    }
+    header // return header
+}
+
+// function constructs a row of a multi objective csv
+pub fn vec_row_objectives(row_num: usize, values: &[f64], route: Vec<&str>) -> Vec<String> {
+    let mut vec: Vec<String> = vec![format!("{:?}", row_num)]; // Key: row numbers
+    vec.extend(route.iter().map(|s| s.to_string())); // States
+    vec.extend(values.iter().map(|v| format!("{:.1}", v))); // Objectives
+    vec // return vec
+}
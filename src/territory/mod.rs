@@ -0,0 +1,281 @@
+use crate::{
+    lookup::ObjLookUp,
+    matrix::drive_hours_matrix,
+    modal::{flight_hours, nearest_airports, Airport, ModalParams, Mode},
+    place::StateCode,
+};
+use log::{error, info, warn};
+use std::str::FromStr;
+
+// Where a state or territory lies with respect to the
+// road network of the lower 48 states and DC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Contiguous,
+    Alaska,
+    Hawaii,
+    Territory,
+}
+
+impl Region {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Region::Contiguous => "CONTIGUOUS",
+            Region::Alaska => "ALASKA",
+            Region::Hawaii => "HAWAII",
+            Region::Territory => "TERRITORY",
+        }
+    }
+}
+
+// Whether a group of records is kept in the instance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Inclusion {
+    Include,
+    Exclude,
+}
+
+impl FromStr for Inclusion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Inclusion, String> {
+        match s.trim().to_lowercase().as_str() {
+            "include" => Ok(Inclusion::Include),
+            "exclude" => Ok(Inclusion::Exclude),
+            _ => Err(format!("{:?} is not include or exclude", s)),
+        }
+    }
+}
+
+// function returns the region of a state code. The
+// territories, the freely associated states and the
+// armed forces codes are all TERRITORY.
+pub fn region(state: &str) -> Region {
+    match state.parse::<StateCode>() {
+        Ok(StateCode::AK) => Region::Alaska,
+        Ok(StateCode::HI) => Region::Hawaii,
+        Ok(code) if code.is_state() || code == StateCode::DC => Region::Contiguous,
+        _ => Region::Territory,
+    }
+}
+
+// function returns true when a leg between two states
+// can be driven: both in the lower 48 and DC, or both
+// in the same state or territory. Alaska counts as a
+// road network of its own; the Alaska Highway through
+// Canada is not a campaign route.
+pub fn drivable(from: &str, to: &str) -> bool {
+    from == to || (region(from) == Region::Contiguous && region(to) == Region::Contiguous)
+}
+
+// function returns true when the leg between two
+// lookup records has to be flown.
+pub fn flight_only(data: &[ObjLookUp], from: usize, to: usize) -> bool {
+    !drivable(&data[from].state, &data[to].state)
+}
+
+// function returns the legs of a route, by position,
+// that have to be flown.
+pub fn flight_legs(route: &[usize], data: &[ObjLookUp]) -> Vec<usize> {
+    route
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| flight_only(data, w[0], w[1]))
+        .map(|(k, _)| k)
+        .collect()
+}
+
+// function sets the legs that cannot be driven to
+// infinity in a matrix of driving costs.
+pub fn block_driving(matrix: &mut [Vec<f64>], data: &[ObjLookUp]) {
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            if flight_only(data, i, j) {
+                *cell = f64::INFINITY;
+            }
+        }
+    }
+}
+
+// function estimates travel hours from a haversine
+// mileage matrix: driving hours as drive_hours_matrix,
+// except for legs that have to be flown, which are
+// timed as modal::flight_hours.
+pub fn travel_hours_matrix(
+    miles: &[Vec<f64>],
+    data: &[ObjLookUp],
+    airports: &[Airport],
+    params: &ModalParams,
+) -> Vec<Vec<f64>> {
+    let airport = nearest_airports(data, airports);
+    let mut hours = drive_hours_matrix(miles);
+    for (i, row) in hours.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            if flight_only(data, i, j) {
+                let h = flight_hours(i, j, &airport, airports, params);
+                *cell = (h * 10.0).round() / 10.0;
+            }
+        }
+    }
+    hours
+}
+
+// function makes every leg of a route that cannot be
+// driven a flight and returns the legs changed, e.g.
+// for a route file without modes.
+pub fn force_flights(route: &[usize], modes: &mut [Mode], data: &[ObjLookUp]) -> Vec<usize> {
+    let mut changed: Vec<usize> = Vec::new();
+    for k in flight_legs(route, data) {
+        if let Some(mode @ Mode::Drive) = modes.get_mut(k) {
+            *mode = Mode::Fly;
+            changed.push(k);
+            warn!(
+                "Leg {:?} {:?}-{:?} cannot be driven, flying",
+                k + 1,
+                data[route[k]].state,
+                data[route[k + 1]].state
+            );
+        }
+    }
+    changed
+}
+
+// function drops the territories and the states
+// outside the lower 48 (AK, HI) from the lookup as
+// asked. Panics when nothing is left.
+pub fn filter_records(
+    data: Vec<ObjLookUp>,
+    territories: Inclusion,
+    noncontiguous: Inclusion,
+) -> Vec<ObjLookUp> {
+    let before = data.len();
+    let kept: Vec<ObjLookUp> = data
+        .into_iter()
+        .filter(|rec| match region(&rec.state) {
+            Region::Contiguous => true,
+            Region::Alaska | Region::Hawaii => noncontiguous == Inclusion::Include,
+            Region::Territory => territories == Inclusion::Include,
+        })
+        .collect();
+    if kept.is_empty() {
+        let msg = "No lookup records left after excluding regions";
+        error!("{:?}: {:?}", msg, before);
+        panic!("{:?}: {:?}", msg, before)
+    }
+    if kept.len() < before {
+        info!(
+            "Excluded {:?} lookup record(s) (territories {:?}, AK/HI {:?})",
+            before - kept.len(),
+            territories,
+            noncontiguous
+        );
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::test_record;
+
+    // IA, AK, HI, PR and a second PR record
+    fn data() -> Vec<ObjLookUp> {
+        vec![
+            test_record("50319", "IA"),
+            test_record("99801", "AK"),
+            test_record("96813", "HI"),
+            test_record("00901", "PR"),
+            test_record("00603", "PR"),
+        ]
+    }
+
+    #[test]
+    fn regions_of_states_and_territories() {
+        assert_eq!(region("IA"), Region::Contiguous);
+        assert_eq!(region("DC"), Region::Contiguous);
+        assert_eq!(region("AK"), Region::Alaska);
+        assert_eq!(region("HI"), Region::Hawaii);
+        assert_eq!(region("GU"), Region::Territory);
+        assert_eq!(region("AE"), Region::Territory);
+        assert_eq!("Exclude".parse::<Inclusion>(), Ok(Inclusion::Exclude));
+        assert!("maybe".parse::<Inclusion>().is_err());
+    }
+
+    #[test]
+    fn only_legs_on_one_road_network_are_driven() {
+        assert!(drivable("IA", "DC"));
+        assert!(drivable("PR", "PR"));
+        assert!(!drivable("IA", "AK"));
+        assert!(!drivable("AK", "HI"));
+        let data = data();
+        assert_eq!(flight_legs(&[0, 1, 0, 3, 4, 2], &data), [0, 1, 2, 4]);
+    }
+
+    #[test]
+    fn flown_legs_take_the_airport_and_the_flight() {
+        let data = data();
+        let params = ModalParams::default();
+        let miles: Vec<Vec<f64>> = (0..5)
+            .map(|i| (0..5).map(|j| if i == j { 0.0 } else { 900.0 }).collect())
+            .collect();
+        // every test record lies at 0,0, one degree of
+        // longitude from the airport
+        let airports = vec![Airport {
+            code: "TST".to_owned(),
+            name: String::new(),
+            city: String::new(),
+            state: "IA".to_owned(),
+            latitude: 0.0,
+            longitude: 1.0,
+        }];
+        let hours = travel_hours_matrix(&miles, &data, &airports, &params);
+        let airport = nearest_airports(&data, &airports);
+        let flight = flight_hours(0, 1, &airport, &airports, &params);
+        assert!(flight > params.airport_hours);
+        assert_eq!(hours[0][1], (flight * 10.0).round() / 10.0);
+        assert_eq!(hours[3][4], 19.6);
+        assert_eq!(hours[0][0], 0.0);
+
+        let mut blocked = miles.clone();
+        block_driving(&mut blocked, &data);
+        assert!(blocked[0][2].is_infinite());
+        assert_eq!(blocked[4][3], 900.0);
+
+        let mut modes = vec![Mode::Drive, Mode::Fly, Mode::Drive];
+        assert_eq!(force_flights(&[0, 1, 2, 2], &mut modes, &data), [0]);
+        assert_eq!(modes, [Mode::Fly, Mode::Fly, Mode::Drive]);
+    }
+
+    #[test]
+    fn filters_regions_as_asked() {
+        let states = |kept: Vec<ObjLookUp>| -> Vec<String> {
+            kept.into_iter().map(|r| r.zip_code).collect()
+        };
+        assert_eq!(
+            states(filter_records(
+                data(),
+                Inclusion::Exclude,
+                Inclusion::Include
+            )),
+            ["50319", "99801", "96813"]
+        );
+        assert_eq!(
+            states(filter_records(
+                data(),
+                Inclusion::Include,
+                Inclusion::Exclude
+            )),
+            ["50319", "00901", "00603"]
+        );
+        assert_eq!(
+            filter_records(data(), Inclusion::Include, Inclusion::Include).len(),
+            5
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "No lookup records left after excluding regions")]
+    fn an_empty_lookup_is_refused() {
+        filter_records(data()[1..].to_vec(), Inclusion::Exclude, Inclusion::Exclude);
+    }
+}
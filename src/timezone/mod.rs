@@ -0,0 +1,285 @@
+use crate::lookup::{lat_lon, ObjLookUp};
+use chrono::prelude::*;
+use log::error;
+
+// A time zone: its tz database name, standard UTC
+// offset, whether it observes daylight saving time and
+// the abbreviations for standard and daylight time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Zone {
+    pub name: &'static str,
+    pub standard_hours: i32,
+    pub dst: bool,
+    pub abbrev: &'static str,
+    pub dst_abbrev: &'static str,
+}
+
+const fn zone(
+    name: &'static str,
+    standard_hours: i32,
+    dst: bool,
+    abbrev: &'static str,
+    dst_abbrev: &'static str,
+) -> Zone {
+    Zone {
+        name,
+        standard_hours,
+        dst,
+        abbrev,
+        dst_abbrev,
+    }
+}
+
+pub const EASTERN: Zone = zone("America/New_York", -5, true, "EST", "EDT");
+pub const CENTRAL: Zone = zone("America/Chicago", -6, true, "CST", "CDT");
+pub const MOUNTAIN: Zone = zone("America/Denver", -7, true, "MST", "MDT");
+pub const PACIFIC: Zone = zone("America/Los_Angeles", -8, true, "PST", "PDT");
+const ARIZONA: Zone = zone("America/Phoenix", -7, false, "MST", "MST");
+const ALASKA: Zone = zone("America/Anchorage", -9, true, "AKST", "AKDT");
+const ALEUTIAN: Zone = zone("America/Adak", -10, true, "HST", "HDT");
+const HAWAII: Zone = zone("Pacific/Honolulu", -10, false, "HST", "HST");
+const ATLANTIC: Zone = zone("America/Puerto_Rico", -4, false, "AST", "AST");
+const CHAMORRO: Zone = zone("Pacific/Guam", 10, false, "ChST", "ChST");
+const SAMOA: Zone = zone("Pacific/Pago_Pago", -11, false, "SST", "SST");
+
+// function returns the zone of a state's capital.
+pub fn state_zone(state: &str) -> Option<Zone> {
+    let zone = match state {
+        "CT" | "DC" | "DE" | "FL" | "GA" | "KY" | "MA" | "MD" | "ME" | "NC" | "NH" | "NJ"
+        | "NY" | "OH" | "PA" | "RI" | "SC" | "VA" | "VT" | "WV" => EASTERN,
+        "IN" => zone("America/Indiana/Indianapolis", -5, true, "EST", "EDT"),
+        "MI" => zone("America/Detroit", -5, true, "EST", "EDT"),
+        "AL" | "AR" | "IA" | "IL" | "KS" | "LA" | "MN" | "MO" | "MS" | "ND" | "NE" | "OK"
+        | "SD" | "TN" | "TX" | "WI" => CENTRAL,
+        "CO" | "MT" | "NM" | "UT" | "WY" => MOUNTAIN,
+        "ID" => zone("America/Boise", -7, true, "MST", "MDT"),
+        "CA" | "NV" | "OR" | "WA" => PACIFIC,
+        "AZ" => ARIZONA,
+        "AK" => ALASKA,
+        "HI" => HAWAII,
+        "PR" => ATLANTIC,
+        "VI" => zone("America/St_Thomas", -4, false, "AST", "AST"),
+        "GU" => CHAMORRO,
+        "MP" => zone("Pacific/Saipan", 10, false, "ChST", "ChST"),
+        "AS" => SAMOA,
+        "FM" => zone("Pacific/Pohnpei", 11, false, "PONT", "PONT"),
+        "MH" => zone("Pacific/Majuro", 12, false, "MHT", "MHT"),
+        "PW" => zone("Pacific/Palau", 9, false, "PWT", "PWT"),
+        _ => return None,
+    };
+    Some(zone)
+}
+
+// Parts of states that keep a different time than
+// their capital, as (state, min lat, max lat, min lon,
+// max lon, zone). The boxes follow county lines only
+// roughly, so points within a few miles of a zone
+// boundary may land on the wrong side.
+const SPLIT_STATES: [(&str, f64, f64, f64, f64, Zone); 14] = [
+    ("FL", 29.5, 31.1, -87.7, -85.0, CENTRAL),
+    ("TN", 34.9, 36.7, -85.3, -81.6, EASTERN),
+    ("KY", 36.4, 39.2, -89.6, -86.0, CENTRAL),
+    ("IN", 41.0, 41.8, -87.6, -86.8, CENTRAL),
+    ("IN", 37.7, 38.4, -88.1, -86.9, CENTRAL),
+    ("MI", 45.3, 46.5, -90.5, -87.6, CENTRAL),
+    ("ND", 45.9, 47.5, -104.1, -101.3, MOUNTAIN),
+    ("SD", 42.4, 46.0, -104.1, -100.5, MOUNTAIN),
+    ("NE", 40.0, 43.1, -104.1, -101.3, MOUNTAIN),
+    ("KS", 37.7, 39.6, -102.1, -101.45, MOUNTAIN),
+    ("TX", 29.5, 32.1, -106.7, -104.9, MOUNTAIN),
+    ("OR", 41.9, 44.5, -118.2, -116.9, MOUNTAIN),
+    ("ID", 45.5, 49.1, -117.3, -114.3, PACIFIC),
+    ("AK", 51.0, 55.5, -180.0, -169.0, ALEUTIAN),
+];
+
+// function returns the zone at a point of a state:
+// the split-state exceptions first, then the zone of
+// the capital.
+pub fn zone_at(state: &str, lat: f64, lon: f64) -> Option<Zone> {
+    for &(st, lat1, lat2, lon1, lon2, zone) in SPLIT_STATES.iter() {
+        if st == state && lat >= lat1 && lat <= lat2 && lon >= lon1 && lon <= lon2 {
+            return Some(zone);
+        }
+    }
+    state_zone(state)
+}
+
+// function returns the zone of a lookup record.
+pub fn record_zone(rec: &ObjLookUp) -> Option<Zone> {
+    let (lat, lon) = lat_lon(rec);
+    zone_at(&rec.state, lat, lon)
+}
+
+// function returns the zone of the i-th lookup
+// record and panics when the state has none.
+pub fn zone_of(data: &[ObjLookUp], i: usize) -> Zone {
+    match record_zone(&data[i]) {
+        Some(zone) => zone,
+        None => {
+            let msg = "No time zone for state";
+            error!("{:?}: {:?}", msg, data[i].state);
+            panic!("{:?}: {:?}", msg, data[i].state)
+        }
+    }
+}
+
+// function returns the abbreviation in effect, e.g.
+// "CST" or "CDT".
+pub fn abbreviation(zone: Zone, utc: DateTime<Utc>) -> &'static str {
+    match is_dst(zone, utc) {
+        true => zone.dst_abbrev,
+        false => zone.abbrev,
+    }
+}
+
+// function returns the n-th sunday of a month.
+fn nth_sunday(year: i32, month: u32, n: u32) -> NaiveDate {
+    let first = NaiveDate::from_ymd(year, month, 1);
+    let offset = (7 - first.weekday().num_days_from_sunday()) % 7;
+    NaiveDate::from_ymd(year, month, 1 + offset + 7 * (n - 1))
+}
+
+// function returns true when US daylight saving time
+// is in effect for a zone at a UTC instant: from 2:00
+// local standard time on the second sunday of March
+// to 2:00 local daylight time on the first sunday of
+// November.
+pub fn is_dst(zone: Zone, utc: DateTime<Utc>) -> bool {
+    if !zone.dst {
+        return false;
+    }
+    let year = utc.year();
+    let std = chrono::Duration::hours(zone.standard_hours as i64);
+    let begin = nth_sunday(year, 3, 2).and_hms(2, 0, 0) - std;
+    let end = nth_sunday(year, 11, 1).and_hms(1, 0, 0) - std;
+    let now = utc.naive_utc();
+    now >= begin && now < end
+}
+
+// function returns the UTC offset of a zone at an instant.
+pub fn offset_at(zone: Zone, utc: DateTime<Utc>) -> FixedOffset {
+    let hours = match is_dst(zone, utc) {
+        true => zone.standard_hours + 1,
+        false => zone.standard_hours,
+    };
+    FixedOffset::east(hours * 3600)
+}
+
+// function converts a UTC instant to local time.
+pub fn to_local(zone: Zone, utc: DateTime<Utc>) -> DateTime<FixedOffset> {
+    utc.with_timezone(&offset_at(zone, utc))
+}
+
+// function converts a local wall clock time to UTC.
+// Times skipped by the spring change are read as
+// standard time.
+pub fn from_local(zone: Zone, local: NaiveDateTime) -> DateTime<Utc> {
+    let standard = FixedOffset::east(zone.standard_hours * 3600);
+    let guess = standard
+        .from_local_datetime(&local)
+        .unwrap()
+        .with_timezone(&Utc);
+    let offset = offset_at(zone, guess);
+    match offset.from_local_datetime(&local).single() {
+        Some(dt) if offset_at(zone, dt.with_timezone(&Utc)) == offset => dt.with_timezone(&Utc),
+        _ => guess,
+    }
+}
+
+// function renders an instant in a zone's local time
+// followed by the zone abbreviation, e.g.
+// "2024-03-09 08:00:00 CST".
+pub fn local_string(zone: Zone, utc: DateTime<Utc>) -> String {
+    format!(
+        "{} {}",
+        to_local(zone, utc).format("%Y-%m-%d %H:%M:%S"),
+        abbreviation(zone, utc)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{read_look_up, state_index};
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.ymd(y, m, d).and_hms(h, min, 0)
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(h, min, 0)
+    }
+
+    #[test]
+    fn finds_the_sundays_of_the_changes() {
+        assert_eq!(nth_sunday(2024, 3, 2), NaiveDate::from_ymd(2024, 3, 10));
+        assert_eq!(nth_sunday(2024, 11, 1), NaiveDate::from_ymd(2024, 11, 3));
+        assert_eq!(nth_sunday(2026, 3, 2), NaiveDate::from_ymd(2026, 3, 8));
+        assert_eq!(nth_sunday(2026, 11, 1), NaiveDate::from_ymd(2026, 11, 1));
+    }
+
+    #[test]
+    fn daylight_time_starts_and_ends_at_two_local() {
+        // 2:00 EST on March 10 and 2:00 EDT on November 3
+        assert!(!is_dst(EASTERN, utc(2024, 3, 10, 6, 59)));
+        assert!(is_dst(EASTERN, utc(2024, 3, 10, 7, 0)));
+        assert!(is_dst(EASTERN, utc(2024, 11, 3, 5, 59)));
+        assert!(!is_dst(EASTERN, utc(2024, 11, 3, 6, 0)));
+        // an hour later in the west
+        assert!(!is_dst(PACIFIC, utc(2024, 3, 10, 9, 59)));
+        assert!(is_dst(PACIFIC, utc(2024, 3, 10, 10, 0)));
+        assert!(!is_dst(state_zone("AZ").unwrap(), utc(2024, 7, 1, 12, 0)));
+    }
+
+    #[test]
+    fn local_times_around_the_changes() {
+        assert_eq!(
+            from_local(EASTERN, local(2024, 3, 10, 1, 30)),
+            utc(2024, 3, 10, 6, 30)
+        );
+        // skipped by the spring change, read as standard time
+        assert_eq!(
+            from_local(EASTERN, local(2024, 3, 10, 2, 30)),
+            utc(2024, 3, 10, 7, 30)
+        );
+        assert_eq!(
+            from_local(EASTERN, local(2024, 3, 10, 3, 0)),
+            utc(2024, 3, 10, 7, 0)
+        );
+        assert_eq!(
+            from_local(EASTERN, local(2024, 11, 3, 0, 30)),
+            utc(2024, 11, 3, 4, 30)
+        );
+        assert_eq!(
+            from_local(EASTERN, local(2024, 11, 3, 3, 0)),
+            utc(2024, 11, 3, 8, 0)
+        );
+        // the repeated hour of the fall change round trips
+        let twice = local(2024, 11, 3, 1, 30);
+        assert_eq!(
+            to_local(EASTERN, from_local(EASTERN, twice)).naive_local(),
+            twice
+        );
+    }
+
+    #[test]
+    fn abbreviations_follow_the_season() {
+        assert_eq!(abbreviation(CENTRAL, utc(2024, 1, 15, 12, 0)), "CST");
+        assert_eq!(abbreviation(CENTRAL, utc(2024, 7, 15, 12, 0)), "CDT");
+        assert_eq!(
+            local_string(CENTRAL, utc(2024, 3, 9, 14, 0)),
+            "2024-03-09 08:00:00 CST"
+        );
+    }
+
+    #[test]
+    fn zones_of_records_and_split_states() {
+        let data = read_look_up("look_up.json");
+        assert_eq!(zone_of(&data, state_index(&data, "IA").unwrap()), CENTRAL);
+        assert_eq!(zone_of(&data, state_index(&data, "NY").unwrap()), EASTERN);
+        // Pensacola keeps central time, Tallahassee eastern
+        assert_eq!(zone_at("FL", 30.42, -87.22), Some(CENTRAL));
+        assert_eq!(zone_at("FL", 30.44, -84.28), Some(EASTERN));
+        assert_eq!(state_zone("XX"), None);
+    }
+}
@@ -0,0 +1,66 @@
+// The validate sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::flag_value,
+    command::{positional, read_any_route, read_precedence, read_stops, write_outputs, CYPHER_CSV},
+    lookup::ObjLookUp,
+    validate::validate as check_route,
+};
+use log::info;
+use serde_json::json;
+
+const VALIDATE_CSV: &str = "validate.csv";
+const VALIDATE_JSON: &str = "validate.json";
+
+// validate: check and score a hand-built route against
+// the lookup, the start and end of states.json and the
+// constraints, with the gap to the best known route.
+//   validate [route.json|route.csv]  (default: cypher.csv)
+//   --row KEY                  row of a csv route (default: 0)
+//   --constraints file.json    precedence groups and stops
+pub fn validate(args: &[String], data: &[ObjLookUp], start: &str, end: Option<&str>) {
+    let path = positional(args, CYPHER_CSV);
+    let file = read_any_route(path, flag_value(args, "--row").unwrap_or("0"));
+    let report = check_route(
+        &file.route,
+        file.distance,
+        data,
+        start,
+        end,
+        &read_precedence(args, data),
+        &read_stops(args, data),
+    );
+
+    let mut rows: Vec<Vec<String>> =
+        vec![["LEG", "FROM", "TO", "FLIGHT_ONLY", "MILES", "TOTAL_MILES"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()];
+    for leg in &report.legs {
+        rows.push(vec![
+            leg.leg.to_string(),
+            leg.from.clone(),
+            leg.to.clone(),
+            leg.flight_only.to_string(),
+            format!("{:.1}", leg.miles),
+            format!("{:.1}", leg.total_miles),
+        ]);
+    }
+
+    let obj = json!({
+        "file": path,
+        "beginning_state": start,
+        "ending_state": end,
+        "validation": report,
+    });
+    write_outputs(VALIDATE_CSV, &rows, VALIDATE_JSON, obj);
+    info!(
+        "Route valid: {:?}, {:?} mi ({:?} mi over the best known) written to {:?} and {:?}",
+        report.valid,
+        report.miles,
+        report.gap_miles.unwrap_or(0.0),
+        VALIDATE_CSV,
+        VALIDATE_JSON
+    );
+}
@@ -0,0 +1,291 @@
+pub mod command;
+
+use crate::{
+    distance::haversine_dist,
+    lookup::{lat_lon, stop_index, stop_name, ObjLookUp},
+    matrix::{distance_matrix, route_total},
+    precedence::{violations, Precedence},
+    solver::{solve, HELD_KARP_LIMIT},
+    stops::{apart, has_repeats, violations as stop_violations, Stops},
+    territory::flight_only,
+};
+use log::{error, info};
+use serde::Serialize;
+
+// A leg of a checked route, miles by haversine_dist;
+// `flight_only` marks a leg with no driving route.
+#[derive(Serialize, Debug, Clone)]
+pub struct LegCheck {
+    pub leg: usize,
+    pub from: String,
+    pub to: String,
+    pub flight_only: bool,
+    pub miles: f64,
+    pub total_miles: f64,
+}
+
+// Everything found about a hand-built route. `valid`
+// is true when `errors` is empty; the gap to the best
+// known route is reported but is not an error.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Validation {
+    pub route: Vec<String>,
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub unknown_states: Vec<String>,
+    pub endpoint_errors: Vec<String>,
+    pub precedence_violations: Vec<String>,
+    pub stop_violations: Vec<String>,
+    pub legs: Vec<LegCheck>,
+    pub miles: f64,
+    pub claimed_miles: Option<f64>,
+    pub best_route: Vec<String>,
+    pub best_miles: Option<f64>,
+    pub best_exact: bool,
+    pub gap_miles: Option<f64>,
+    pub gap_percent: Option<f64>,
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+// function checks the endpoints of a route against the
+// start and end of states.json. An open path (no end)
+// may end anywhere.
+fn check_endpoints(codes: &[String], start: &str, end: Option<&str>) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    match codes.first() {
+        None => found.push("route is empty".to_owned()),
+        Some(first) if first != start => {
+            found.push(format!("route starts at {}, expected {}", first, start))
+        }
+        _ => (),
+    }
+    if let (Some(last), Some(end)) = (codes.last(), end) {
+        if last != end {
+            found.push(format!("route ends at {}, expected {}", last, end));
+        }
+    }
+    found
+}
+
+// function returns the best route found by the solvers
+// through the same states, from the same first stop to
+// the same last stop (anywhere for an open path).
+fn best_known(
+    route: &[usize],
+    open: bool,
+    data: &[ObjLookUp],
+    prec: &Precedence,
+) -> (Vec<usize>, f64) {
+    let first = route[0];
+    let last = route[route.len() - 1];
+    let (middle, end) = match open {
+        true => (route[1..].to_vec(), None),
+        false => (route[1..route.len() - 1].to_vec(), Some(last)),
+    };
+    let miles = distance_matrix(data);
+    // keep the visits of a revisited stop apart
+    let matrix = match has_repeats(&middle) {
+        true => apart(&miles),
+        false => miles,
+    };
+    solve(first, &middle, end, &matrix, prec, |r| {
+        route_total(r, &matrix)
+    })
+}
+
+// function validates and scores a route given as stop
+// names, state or zip codes: every stop must be in
+// the lookup, the route
+// must start and end where states.json says and keep
+// to the precedence groups and stops. Legs and the
+// total are recomputed with haversine_dist and compared
+// with the miles the route claims, if any, and with the
+// best known route through the same states.
+pub fn validate(
+    codes: &[String],
+    claimed: Option<f64>,
+    data: &[ObjLookUp],
+    start: &str,
+    end: Option<&str>,
+    prec: &Precedence,
+    stops: &Stops,
+) -> Validation {
+    let mut report = Validation {
+        route: codes.to_vec(),
+        claimed_miles: claimed,
+        ..Validation::default()
+    };
+    report.unknown_states = codes
+        .iter()
+        .filter(|s| stop_index(data, s).is_none())
+        .cloned()
+        .collect();
+    report.endpoint_errors = check_endpoints(codes, start, end);
+
+    let mut errors: Vec<String> = report
+        .unknown_states
+        .iter()
+        .map(|s| format!("{} is not in the lookup", s))
+        .collect();
+    errors.extend(report.endpoint_errors.iter().cloned());
+
+    if report.unknown_states.is_empty() && !codes.is_empty() {
+        let route: Vec<usize> = codes.iter().filter_map(|s| stop_index(data, s)).collect();
+        report.precedence_violations = violations(&route, prec, data);
+        report.stop_violations = stop_violations(&route, stops, data);
+        errors.extend(report.precedence_violations.iter().cloned());
+        errors.extend(report.stop_violations.iter().cloned());
+
+        let mut total = 0.0;
+        for (k, w) in route.windows(2).enumerate() {
+            let (lat1, lon1) = lat_lon(&data[w[0]]);
+            let (lat2, lon2) = lat_lon(&data[w[1]]);
+            let miles = haversine_dist(lat1, lon1, lat2, lon2);
+            total += miles;
+            report.legs.push(LegCheck {
+                leg: k + 1,
+                from: stop_name(data, w[0]),
+                to: stop_name(data, w[1]),
+                flight_only: flight_only(data, w[0], w[1]),
+                miles,
+                total_miles: round1(total),
+            });
+        }
+        report.miles = round1(total);
+        if let Some(c) = claimed {
+            if (c - report.miles).abs() > 0.05 {
+                errors.push(format!(
+                    "route claims {:.1} mi, its legs add up to {:.1} mi",
+                    c, report.miles
+                ));
+            }
+        }
+
+        if route.len() > 1 {
+            let (best, best_miles) = best_known(&route, end.is_none(), data, prec);
+            let best_miles = round1(best_miles);
+            let between = route.len() - 1 - end.is_some() as usize;
+            report.best_exact = between <= HELD_KARP_LIMIT;
+            report.best_route = best.iter().map(|&i| stop_name(data, i)).collect();
+            report.best_miles = Some(best_miles);
+            report.gap_miles = Some(round1(report.miles - best_miles));
+            if best_miles > 0.0 {
+                report.gap_percent = Some(round1(100.0 * (report.miles - best_miles) / best_miles));
+            }
+        }
+    }
+
+    for e in &errors {
+        error!("Invalid route: {}", e);
+    }
+    report.valid = errors.is_empty();
+    report.errors = errors;
+    info!(
+        "Route of {:?} stop(s) checked: valid {:?}, {:?} mi",
+        codes.len(),
+        report.valid,
+        report.miles
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{read_look_up, state_index};
+
+    fn codes(states: &[&str]) -> Vec<String> {
+        states.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn check(states: &[&str], claimed: Option<f64>, end: Option<&str>) -> Validation {
+        let data = read_look_up("look_up.json");
+        validate(
+            &codes(states),
+            claimed,
+            &data,
+            "IA",
+            end,
+            &Precedence::default(),
+            &Stops::default(),
+        )
+    }
+
+    #[test]
+    fn checks_the_endpoints() {
+        assert!(check_endpoints(&codes(&["IA", "NY", "DC"]), "IA", Some("DC")).is_empty());
+        assert_eq!(
+            check_endpoints(&codes(&["NY", "IA"]), "IA", Some("DC")),
+            [
+                "route starts at NY, expected IA",
+                "route ends at IA, expected DC"
+            ]
+        );
+        // an open path ends anywhere
+        assert!(check_endpoints(&codes(&["IA", "NY"]), "IA", None).is_empty());
+        assert_eq!(check_endpoints(&[], "IA", None), ["route is empty"]);
+    }
+
+    #[test]
+    fn the_best_route_has_no_gap() {
+        let first = check(&["IA", "TX", "NY", "WI", "DC"], None, Some("DC"));
+        assert!(first.valid);
+        assert!(first.best_exact);
+        assert!(first.gap_miles.unwrap() >= 0.0);
+        let best: Vec<&str> = first.best_route.iter().map(|s| s.as_str()).collect();
+        let again = check(&best, first.best_miles, Some("DC"));
+        assert!(again.valid);
+        assert_eq!(again.gap_miles, Some(0.0));
+        assert_eq!(again.miles, first.best_miles.unwrap());
+        assert_eq!(again.legs.len(), 4);
+        assert_eq!(again.legs[3].total_miles, again.miles);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let report = check(&["IA", "XX", "NY"], None, Some("DC"));
+        assert!(!report.valid);
+        assert_eq!(report.unknown_states, ["XX"]);
+        assert!(report.legs.is_empty());
+        assert_eq!(
+            report.errors,
+            ["XX is not in the lookup", "route ends at NY, expected DC"]
+        );
+
+        let report = check(&["IA", "NY", "DC"], Some(100.0), Some("DC"));
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].starts_with("route claims 100.0 mi"));
+    }
+
+    #[test]
+    fn marks_legs_that_must_be_flown() {
+        let report = check(&["IA", "HI", "CA"], None, None);
+        assert!(report.valid);
+        assert!(report.legs[0].flight_only && report.legs[1].flight_only);
+        let report = check(&["IA", "NY"], None, None);
+        assert!(!report.legs[0].flight_only);
+    }
+
+    #[test]
+    fn reports_broken_precedence() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let prec = Precedence {
+            pairs: vec![(at("NY"), at("WI"))],
+        };
+        let report = validate(
+            &codes(&["IA", "WI", "NY", "DC"]),
+            None,
+            &data,
+            "IA",
+            Some("DC"),
+            &prec,
+            &Stops::default(),
+        );
+        assert_eq!(report.errors, ["NY must be visited before WI"]);
+        assert_eq!(report.best_route, ["IA", "NY", "WI", "DC"]);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::lookup::ObjLookUp;
+use log::{info, warn};
+use serde::Serialize;
+
+// Electoral votes for the 2024 and 2028 elections and
+// resident population from the 2020 census, per state
+// code. The population of a capital's zip code in the
+// lookup is mostly "0", so this table is the statewide
+// figure.
+const STATE_WEIGHTS: [(&str, u32, u64); 51] = [
+    ("AL", 9, 5_024_279),
+    ("AK", 3, 733_391),
+    ("AZ", 11, 7_151_502),
+    ("AR", 6, 3_011_524),
+    ("CA", 54, 39_538_223),
+    ("CO", 10, 5_773_714),
+    ("CT", 7, 3_605_944),
+    ("DE", 3, 989_948),
+    ("DC", 3, 689_545),
+    ("FL", 30, 21_538_187),
+    ("GA", 16, 10_711_908),
+    ("HI", 4, 1_455_271),
+    ("ID", 4, 1_839_106),
+    ("IL", 19, 12_812_508),
+    ("IN", 11, 6_785_528),
+    ("IA", 6, 3_190_369),
+    ("KS", 6, 2_937_880),
+    ("KY", 8, 4_505_836),
+    ("LA", 8, 4_657_757),
+    ("ME", 4, 1_362_359),
+    ("MD", 10, 6_177_224),
+    ("MA", 11, 7_029_917),
+    ("MI", 15, 10_077_331),
+    ("MN", 10, 5_706_494),
+    ("MS", 6, 2_961_279),
+    ("MO", 10, 6_154_913),
+    ("MT", 4, 1_084_225),
+    ("NE", 5, 1_961_504),
+    ("NV", 6, 3_104_614),
+    ("NH", 4, 1_377_529),
+    ("NJ", 14, 9_288_994),
+    ("NM", 5, 2_117_522),
+    ("NY", 28, 20_201_249),
+    ("NC", 16, 10_439_388),
+    ("ND", 3, 779_094),
+    ("OH", 17, 11_799_448),
+    ("OK", 7, 3_959_353),
+    ("OR", 8, 4_237_256),
+    ("PA", 19, 13_002_700),
+    ("RI", 4, 1_097_379),
+    ("SC", 9, 5_118_425),
+    ("SD", 3, 886_667),
+    ("TN", 11, 6_910_840),
+    ("TX", 40, 29_145_505),
+    ("UT", 6, 3_271_616),
+    ("VT", 3, 643_077),
+    ("VA", 13, 8_631_393),
+    ("WA", 12, 7_705_281),
+    ("WV", 4, 1_793_716),
+    ("WI", 10, 5_893_718),
+    ("WY", 3, 576_851),
+];
+
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct StateWeight {
+    pub electoral_votes: u32,
+    pub population: u64,
+}
+
+// Which weight a solver gives each state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weight {
+    Votes,
+    Population,
+}
+
+impl Weight {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Weight::Votes => "VOTES",
+            Weight::Population => "POPULATION",
+        }
+    }
+}
+
+// function returns the bundled weights of a state code.
+pub fn state_weight(state: &str) -> Option<StateWeight> {
+    STATE_WEIGHTS
+        .iter()
+        .find(|(s, _, _)| *s == state)
+        .map(|&(_, electoral_votes, population)| StateWeight {
+            electoral_votes,
+            population,
+        })
+}
+
+// function joins the bundled table to the lookup
+// records by state code, one entry per record. States
+// not in the table weigh nothing.
+pub fn join(data: &[ObjLookUp]) -> Vec<StateWeight> {
+    let joined: Vec<StateWeight> = data
+        .iter()
+        .map(|rec| match state_weight(&rec.state) {
+            Some(w) => w,
+            None => {
+                warn!("No electoral votes or population for {:?}", rec.state);
+                StateWeight::default()
+            }
+        })
+        .collect();
+    info!(
+        "Joined state weights to {:?} lookup record(s)",
+        joined.len()
+    );
+    joined
+}
+
+// function returns one node weight per lookup record
+// for the solvers.
+pub fn node_weights(data: &[ObjLookUp], weight: Weight) -> Vec<f64> {
+    join(data)
+        .iter()
+        .map(|w| match weight {
+            Weight::Votes => w.electoral_votes as f64,
+            Weight::Population => w.population as f64,
+        })
+        .collect()
+}
+
+// Weights a route reaches. A state visited twice, or
+// with two lookup records (CT), counts once.
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+pub struct RouteWeight {
+    pub electoral_votes: u32,
+    pub population: u64,
+    pub votes_per_100_miles: f64,
+}
+
+// function returns, per stop, the weights first reached
+// there (zero on a repeat visit).
+pub fn stop_weights(route: &[usize], data: &[ObjLookUp]) -> Vec<StateWeight> {
+    let mut seen: Vec<&str> = Vec::with_capacity(route.len());
+    route
+        .iter()
+        .map(|&i| {
+            let state = data[i].state.as_str();
+            match seen.contains(&state) {
+                true => StateWeight::default(),
+                false => {
+                    seen.push(state);
+                    state_weight(state).unwrap_or_default()
+                }
+            }
+        })
+        .collect()
+}
+
+// function totals the weights of a route and the
+// electoral votes it reaches per 100 miles.
+pub fn route_weight(route: &[usize], data: &[ObjLookUp], miles: f64) -> RouteWeight {
+    let stops = stop_weights(route, data);
+    let electoral_votes = stops.iter().map(|w| w.electoral_votes).sum();
+    let population = stops.iter().map(|w| w.population).sum();
+    RouteWeight {
+        electoral_votes,
+        population,
+        votes_per_100_miles: per_100_miles(electoral_votes as f64, miles),
+    }
+}
+
+// function returns a quantity per 100 miles, 0 for a
+// route without miles.
+pub fn per_100_miles(value: f64, miles: f64) -> f64 {
+    match miles > 0.0 {
+        true => (value * 1000.0 / miles).round() / 10.0,
+        false => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{read_look_up, state_index};
+
+    #[test]
+    fn the_table_adds_up_to_538_votes() {
+        let votes: u32 = STATE_WEIGHTS.iter().map(|w| w.1).sum();
+        let population: u64 = STATE_WEIGHTS.iter().map(|w| w.2).sum();
+        assert_eq!(votes, 538);
+        assert_eq!(population, 331_449_281);
+        for (k, w) in STATE_WEIGHTS.iter().enumerate() {
+            assert!(STATE_WEIGHTS[k + 1..].iter().all(|x| x.0 != w.0));
+            assert!(w.1 >= 3);
+        }
+    }
+
+    #[test]
+    fn looks_up_a_state() {
+        let ca = state_weight("CA").unwrap();
+        assert_eq!((ca.electoral_votes, ca.population), (54, 39_538_223));
+        assert_eq!(state_weight("PR"), None);
+    }
+
+    #[test]
+    fn joins_one_weight_per_record() {
+        let data = read_look_up("look_up.json");
+        let votes = node_weights(&data, Weight::Votes);
+        let population = node_weights(&data, Weight::Population);
+        assert_eq!(votes.len(), data.len());
+        let tx = state_index(&data, "TX").unwrap();
+        assert_eq!((votes[tx], population[tx]), (40.0, 29_145_505.0));
+    }
+
+    #[test]
+    fn a_revisited_state_counts_once() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let route = [at("IA"), at("NY"), at("IA"), at("DC")];
+        let stops = stop_weights(&route, &data);
+        assert_eq!(stops[2], StateWeight::default());
+        let total = route_weight(&route, &data, 2000.0);
+        assert_eq!(total.electoral_votes, 6 + 28 + 3);
+        assert_eq!(total.population, 3_190_369 + 20_201_249 + 689_545);
+        assert_eq!(total.votes_per_100_miles, 1.9);
+        assert_eq!(
+            route_weight(&route[..1], &data, 0.0).votes_per_100_miles,
+            0.0
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// The windows sub command of the binary; the flags and
+// output files they share are in crate::command.
+
+use crate::{
+    cli::flag_value,
+    command::{modal_params, positional, read_airport_table, route_stops, write_outputs},
+    constraints::read_constraints,
+    lookup::{stop_name, ObjLookUp},
+    precedence::{check_ends, from_groups, violations},
+    stops::{apart, from_constraints, has_repeats, violations as stop_violations},
+    timezone::{from_local, local_string, to_local, zone_of},
+    windows::{at as tw_at, build_model as tw_model, diagnose, late_stops, solve as tw_solve},
+};
+use chrono::prelude::*;
+use log::{error, info};
+use serde_json::{json, Value};
+
+const CONSTRAINTS_JSON: &str = "constraints.json";
+const WINDOWS_CSV: &str = "windows.csv";
+const WINDOWS_JSON: &str = "windows.json";
+
+// windows: shortest route that meets the campaign
+// event windows, precedence groups and stops in a
+// constraints file.
+//   windows [constraints.json]  (default: constraints.json)
+//   --states IA,NY,...          states to visit (default: all)
+//   --start 2024-01-15T08:00    overrides start_time of the file
+// Legs to or from AK, HI or a territory are flown
+// (--airports, --fly-mph, --airport-hours).
+pub fn windows(args: &[String], data: &[ObjLookUp], start: &str, end: Option<&str>) {
+    let path = positional(args, CONSTRAINTS_JSON);
+    let cons = read_constraints(path);
+    let prec = from_groups(data, &cons.precedence);
+    let stops = from_constraints(data, &cons);
+    let (first, middle, last) = route_stops(args, data, start, end, &stops);
+    check_ends(first, &middle, last, &prec, data);
+
+    // the start time is local to the first capital
+    let text = match flag_value(args, "--start") {
+        Some(v) => Some(v.to_owned()),
+        None => cons.start_time.clone(),
+    };
+    let local = match text {
+        Some(v) => match NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M") {
+            Ok(local) => local,
+            Err(e) => {
+                let msg = "Expected a start time YYYY-MM-DDTHH:MM";
+                error!("{:?}: {:?}: {:?}", msg, v, e);
+                panic!("{:?}: {:?}: {:?}", msg, v, e)
+            }
+        },
+        None => (Local::now().date().naive_local() + chrono::Duration::days(1)).and_hms(8, 0, 0),
+    };
+    let start_utc = from_local(zone_of(data, first), local);
+    let airports = read_airport_table(args);
+    let mut model = tw_model(data, &cons, start_utc, &airports, &modal_params(args));
+    // keep the visits of a revisited state apart
+    if has_repeats(&middle) {
+        model.miles = apart(&model.miles);
+        model.travel = apart(&model.travel);
+    }
+    let sol = tw_solve(first, &middle, last, &model, &prec);
+    let broken = violations(&sol.route, &prec, data);
+    let missed = stop_violations(&sol.route, &stops, data);
+
+    let reasons = match sol.feasible {
+        true => Vec::new(),
+        false => {
+            let mut reasons = diagnose(first, &middle, last, &model, data);
+            reasons.extend(late_stops(&sol, data));
+
+            for r in &reasons {
+                error!("Infeasible windows: {}", r);
+            }
+            reasons
+        }
+    };
+
+    let rows: Vec<Vec<String>> = std::iter::once(
+        [
+            "STOP",
+            "STATE",
+            "ARRIVE",
+            "BEGIN",
+            "DEPART",
+            "OPEN",
+            "CLOSE",
+            "LATE_HOURS",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect(),
+    )
+    .chain(sol.timings.iter().enumerate().map(|(k, t)| {
+        let zone = zone_of(data, t.stop);
+        let bound = |h: f64| match h.is_finite() {
+            true => local_string(zone, tw_at(&model, h)),
+            false => String::new(),
+        };
+        vec![
+            (k + 1).to_string(),
+            stop_name(data, t.stop),
+            local_string(zone, tw_at(&model, t.arrive)),
+            local_string(zone, tw_at(&model, t.begin)),
+            local_string(zone, tw_at(&model, t.depart)),
+            bound(model.open[t.stop]),
+            bound(model.close[t.stop]),
+            format!("{:.1}", t.late),
+        ]
+    }))
+    .collect();
+
+    let stops: Vec<Value> = sol
+        .timings
+        .iter()
+        .map(|t| {
+            let zone = zone_of(data, t.stop);
+            json!({
+                "state": data[t.stop].state,
+                "city": data[t.stop].city,
+                "arrive": to_local(zone, tw_at(&model, t.arrive)).to_rfc3339(),
+                "begin": to_local(zone, tw_at(&model, t.begin)).to_rfc3339(),
+                "depart": to_local(zone, tw_at(&model, t.depart)).to_rfc3339(),
+                "begin_utc": tw_at(&model, t.begin).to_rfc3339(),
+                "late_hours": (t.late * 10.0).round() / 10.0,
+            })
+        })
+        .collect();
+    let obj = json!({
+        "beginning_state": start,
+        "ending_state": end,
+        "route": sol.route.iter().map(|&i| stop_name(data, i)).collect::<Vec<String>>(),
+        "total_miles": sol.miles,
+        "closed_tour": last == Some(first),
+        "feasible": sol.feasible,
+        "exact": sol.exact,
+        "stops": stops,
+        "infeasible": reasons,
+        "precedence_violations": broken,
+        "stop_violations": missed,
+    });
+    write_outputs(WINDOWS_CSV, &rows, WINDOWS_JSON, obj);
+    info!(
+        "Time window route of {:?} mi (feasible: {:?}) written to {:?} and {:?}",
+        sol.miles, sol.feasible, WINDOWS_CSV, WINDOWS_JSON
+    );
+}
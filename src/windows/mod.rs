@@ -0,0 +1,588 @@
+pub mod command;
+
+use crate::{
+    constraints::Constraints,
+    lookup::ObjLookUp,
+    matrix::distance_matrix,
+    modal::{Airport, ModalParams},
+    precedence::{allows, ready, Precedence},
+    solver::{full_route, local_search},
+    territory::travel_hours_matrix,
+    timezone::{from_local, zone_of},
+};
+use chrono::prelude::*;
+use log::{error, info};
+
+// Instances with at most this many states between the
+// start and the end are searched exhaustively.
+pub const EXACT_LIMIT: usize = 10;
+// Hours spent in a capital when nothing else is given.
+pub const SERVICE_HOURS: f64 = 2.0;
+// Cost per hour of lateness while searching, so the
+// heuristic can work its way out of infeasible routes.
+const LATE_PENALTY: f64 = 1.0e6;
+
+// Travel times, service durations and windows of every
+// lookup record. Times are hours after the start.
+pub struct TwModel {
+    pub miles: Vec<Vec<f64>>,
+    pub travel: Vec<Vec<f64>>,
+    pub service: Vec<f64>,
+    pub open: Vec<f64>,
+    pub close: Vec<f64>,
+    pub start: DateTime<Utc>,
+}
+
+// When the route reaches a stop and how late it is.
+#[derive(Debug, Clone)]
+pub struct StopTiming {
+    pub stop: usize,
+    pub arrive: f64,
+    pub begin: f64,
+    pub depart: f64,
+    pub late: f64,
+}
+
+pub struct TwSolution {
+    pub route: Vec<usize>,
+    pub miles: f64,
+    pub feasible: bool,
+    pub exact: bool,
+    pub timings: Vec<StopTiming>,
+}
+
+// function parses a window bound local to a capital.
+// A bare close date means the end of that day.
+fn parse_bound(value: &str, closing: bool) -> Option<NaiveDateTime> {
+    if let Ok(dt) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        return Some(dt);
+    }
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(d) if closing => Some(d.and_hms(23, 59, 59)),
+        Ok(d) => Some(d.and_hms(0, 0, 0)),
+        Err(_) => None,
+    }
+}
+
+// function returns the hours from start to a local
+// time at the i-th lookup record.
+fn hours_after(data: &[ObjLookUp], i: usize, local: NaiveDateTime, start: DateTime<Utc>) -> f64 {
+    let utc = from_local(zone_of(data, i), local);
+    (utc - start).num_seconds() as f64 / 3600.0
+}
+
+// function builds the time window model. Travel is
+// continuous, driving where a leg can be driven and
+// flying where it cannot, as territory::travel_hours_matrix.
+pub fn build_model(
+    data: &[ObjLookUp],
+    cons: &Constraints,
+    start: DateTime<Utc>,
+    airports: &[Airport],
+    params: &ModalParams,
+) -> TwModel {
+    let num = data.len();
+    let miles = distance_matrix(data);
+    let travel = travel_hours_matrix(&miles, data, airports, params);
+    let mut service = vec![cons.service_hours.unwrap_or(SERVICE_HOURS); num];
+    let mut open = vec![f64::NEG_INFINITY; num];
+    let mut close = vec![f64::INFINITY; num];
+
+    for w in &cons.windows {
+        let records: Vec<usize> = (0..num).filter(|&i| data[i].state == w.state).collect();
+        if records.is_empty() {
+            let msg = "Time window for a state not in the lookup";
+            error!("{:?}: {:?}", msg, w.state);
+            panic!("{:?}: {:?}", msg, w.state)
+        }
+        for i in records {
+            if let Some(v) = &w.open {
+                match parse_bound(v, false) {
+                    Some(dt) => open[i] = hours_after(data, i, dt, start),
+                    None => {
+                        let msg = "Invalid window open time";
+                        error!("{:?}: {:?} {:?}", msg, w.state, v);
+                        panic!("{:?}: {:?} {:?}", msg, w.state, v)
+                    }
+                }
+            }
+            if let Some(v) = &w.close {
+                match parse_bound(v, true) {
+                    Some(dt) => close[i] = hours_after(data, i, dt, start),
+                    None => {
+                        let msg = "Invalid window close time";
+                        error!("{:?}: {:?} {:?}", msg, w.state, v);
+                        panic!("{:?}: {:?} {:?}", msg, w.state, v)
+                    }
+                }
+            }
+            if let Some(h) = w.service_hours {
+                service[i] = h;
+            }
+        }
+    }
+    TwModel {
+        miles,
+        travel,
+        service,
+        open,
+        close,
+        start,
+    }
+}
+
+// function walks a route: service begins at arrival or
+// when the window opens, whichever is later, and is late
+// by however much it begins after the window closes.
+pub fn simulate(route: &[usize], model: &TwModel) -> (Vec<StopTiming>, f64) {
+    let mut timings: Vec<StopTiming> = Vec::with_capacity(route.len());
+    let mut t = 0.0;
+    let mut late_total = 0.0;
+    for (k, &stop) in route.iter().enumerate() {
+        if k > 0 {
+            t += model.travel[route[k - 1]][stop];
+        }
+        let arrive = t;
+        let begin = arrive.max(model.open[stop]);
+        let late = (begin - model.close[stop]).max(0.0);
+        let depart = begin + model.service[stop];
+        late_total += late;
+        timings.push(StopTiming {
+            stop,
+            arrive,
+            begin,
+            depart,
+            late,
+        });
+        t = depart;
+    }
+    (timings, late_total)
+}
+
+// miles plus a large penalty per hour late
+fn penalized(route: &[usize], model: &TwModel) -> f64 {
+    let (_, late) = simulate(route, model);
+    let miles: f64 = route.windows(2).map(|w| model.miles[w[0]][w[1]]).sum();
+    miles + late * LATE_PENALTY
+}
+
+// function searches every feasible ordering depth first,
+// cutting branches that are late, visit a state before
+// its predecessors or are already longer than the best
+// route found.
+pub fn exact(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    model: &TwModel,
+    prec: &Precedence,
+) -> Option<Vec<usize>> {
+    struct Search<'a> {
+        model: &'a TwModel,
+        prec: &'a Precedence,
+        middle: &'a [usize],
+        end: Option<usize>,
+        best: Option<(Vec<usize>, f64)>,
+    }
+
+    fn dfs(s: &mut Search, path: &mut Vec<usize>, left: &mut Vec<usize>, t: f64, miles: f64) {
+        let at = path[path.len() - 1];
+        if let Some((_, best)) = &s.best {
+            if miles >= *best {
+                return;
+            }
+        }
+        if left.is_empty() {
+            // an open path is done at the last state
+            let (on_time, total) = match s.end {
+                Some(end) => {
+                    let begin = (t + s.model.travel[at][end]).max(s.model.open[end]);
+                    (begin <= s.model.close[end], miles + s.model.miles[at][end])
+                }
+                None => (true, miles),
+            };
+            let better = match &s.best {
+                Some((_, best)) => total < *best,
+                None => true,
+            };
+            if on_time && better {
+                let mut route = path.clone();
+                route.extend(s.end);
+                s.best = Some((route, total));
+            }
+            return;
+        }
+        for k in 0..left.len() {
+            let next = left[k];
+            if !ready(next, &path[1..], s.middle, s.prec) {
+                continue;
+            }
+            let begin = (t + s.model.travel[at][next]).max(s.model.open[next]);
+            if begin > s.model.close[next] {
+                continue;
+            }
+            left.remove(k);
+            path.push(next);
+            let depart = begin + s.model.service[next];
+            dfs(s, path, left, depart, miles + s.model.miles[at][next]);
+            path.pop();
+            left.insert(k, next);
+        }
+    }
+
+    let begin = model.open[start].max(0.0);
+    if begin > model.close[start] {
+        return None;
+    }
+    let mut search = Search {
+        model,
+        prec,
+        middle,
+        end,
+        best: None,
+    };
+    let mut path = vec![start];
+    let mut left = middle.to_vec();
+    dfs(
+        &mut search,
+        &mut path,
+        &mut left,
+        begin + model.service[start],
+        0.0,
+    );
+    search.best.map(|(route, _)| route)
+}
+
+// function builds a route by cheapest insertion, the
+// tightest windows first and only where no precedence
+// pair is broken, then improves it with local search on
+// miles plus lateness.
+pub fn heuristic(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    model: &TwModel,
+    prec: &Precedence,
+) -> Vec<usize> {
+    let mut order = middle.to_vec();
+    order.sort_by(|&a, &b| {
+        model.close[a].partial_cmp(&model.close[b]).unwrap().then(
+            model.miles[start][b]
+                .partial_cmp(&model.miles[start][a])
+                .unwrap(),
+        )
+    });
+
+    let open = end.is_none();
+    let mut route = full_route(start, &[], end);
+    for s in order {
+        let mut best = (1, f64::INFINITY);
+        // an open path may also grow at its tail
+        let last = route.len() + open as usize;
+        for pos in 1..last {
+            route.insert(pos, s);
+            let fixed = route.len() - !open as usize;
+            let c = match allows(&route[1..fixed], prec) {
+                true => penalized(&route, model),
+                false => f64::INFINITY,
+            };
+            route.remove(pos);
+            if c < best.1 {
+                best = (pos, c);
+            }
+        }
+        route.insert(best.0, s);
+    }
+    let (route, _) = local_search(route, open, prec, |r| penalized(r, model));
+    route
+}
+
+// function returns the shortest feasible route it can
+// find: exact search for small instances, otherwise
+// the insertion heuristic.
+pub fn solve(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    model: &TwModel,
+    prec: &Precedence,
+) -> TwSolution {
+    let exact_run = middle.len() <= EXACT_LIMIT;
+    let route = match exact_run {
+        true => match exact(start, middle, end, model, prec) {
+            Some(route) => route,
+            // nothing is feasible: keep the least late order
+            None => heuristic(start, middle, end, model, prec),
+        },
+        false => heuristic(start, middle, end, model, prec),
+    };
+    let (timings, late) = simulate(&route, model);
+    let miles: f64 = route.windows(2).map(|w| model.miles[w[0]][w[1]]).sum();
+    info!(
+        "Time window route: {:?} mi, {:?} hour(s) late, exact search: {:?}",
+        miles, late, exact_run
+    );
+    TwSolution {
+        route,
+        miles: (miles * 10.0).round() / 10.0,
+        feasible: late <= 0.0,
+        exact: exact_run,
+        timings,
+    }
+}
+
+// function explains why windows cannot all be met:
+// windows that close before the state can be reached,
+// windows that leave no time to reach the end, and
+// pairs of windows that exclude each other.
+pub fn diagnose(
+    start: usize,
+    middle: &[usize],
+    end: Option<usize>,
+    model: &TwModel,
+    data: &[ObjLookUp],
+) -> Vec<String> {
+    let mut reasons: Vec<String> = Vec::new();
+    let fmt = |h: f64| -> String {
+        let t = model.start + chrono::Duration::seconds((h * 3600.0) as i64);
+        t.format("%Y-%m-%d %H:%M UTC").to_string()
+    };
+    let leave = model.open[start].max(0.0) + model.service[start];
+    // earliest time service can begin at each stop
+    let earliest = |i: usize| (leave + model.travel[start][i]).max(model.open[i]);
+
+    if model.open[start].max(0.0) > model.close[start] {
+        reasons.push(format!(
+            "{} window closed at {}, before the campaign starts",
+            data[start].state,
+            fmt(model.close[start])
+        ));
+    }
+    for &i in middle.iter().chain(end.iter()) {
+        if earliest(i) > model.close[i] {
+            reasons.push(format!(
+                "{} window closes at {} but the earliest arrival, going there directly from {}, is {}",
+                data[i].state,
+                fmt(model.close[i]),
+                data[start].state,
+                fmt(earliest(i))
+            ));
+        }
+    }
+    for &i in middle {
+        let end = match end {
+            Some(end) => end,
+            None => break,
+        };
+        let finish = earliest(i) + model.service[i] + model.travel[i][end];
+        if finish.max(model.open[end]) > model.close[end] {
+            reasons.push(format!(
+                "{} window opens too late to reach {} by {}",
+                data[i].state,
+                data[end].state,
+                fmt(model.close[end])
+            ));
+        }
+    }
+    for (a, &i) in middle.iter().enumerate() {
+        for &j in &middle[a + 1..] {
+            if model.close[i].is_infinite() || model.close[j].is_infinite() {
+                continue;
+            }
+            let i_then_j = (earliest(i) + model.service[i] + model.travel[i][j]).max(model.open[j])
+                <= model.close[j];
+            let j_then_i = (earliest(j) + model.service[j] + model.travel[j][i]).max(model.open[i])
+                <= model.close[i];
+            if !i_then_j && !j_then_i {
+                reasons.push(format!(
+                    "{} and {} windows conflict: neither can be visited after the other in time",
+                    data[i].state, data[j].state
+                ));
+            }
+        }
+    }
+    if reasons.is_empty() {
+        reasons.push(
+            "No single window is impossible; the windows together leave no feasible order"
+                .to_owned(),
+        );
+    }
+    reasons
+}
+
+// function lists the late stops of a route.
+pub fn late_stops(sol: &TwSolution, data: &[ObjLookUp]) -> Vec<String> {
+    sol.timings
+        .iter()
+        .filter(|t| t.late > 0.0)
+        .map(|t| format!("{} is {:.1} hour(s) late", data[t.stop].state, t.late))
+        .collect()
+}
+
+// function returns the UTC instant of an hour offset.
+pub fn at(model: &TwModel, h: f64) -> DateTime<Utc> {
+    model.start + chrono::Duration::seconds((h * 3600.0).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constraints::read_constraints,
+        lookup::{read_look_up, state_index},
+        modal::read_airports,
+        random::XorShift,
+        solver::brute_force,
+    };
+
+    // a model over random miles with random windows, some
+    // of them left open
+    fn random_model(rng: &mut XorShift, num: usize) -> TwModel {
+        let miles: Vec<Vec<f64>> = (0..num)
+            .map(|i| {
+                (0..num)
+                    .map(|j| match i == j {
+                        true => 0.0,
+                        false => (rng.below(500) + 1) as f64,
+                    })
+                    .collect()
+            })
+            .collect();
+        let travel = miles
+            .iter()
+            .map(|row| row.iter().map(|m| m / 50.0).collect())
+            .collect();
+        let mut open = vec![f64::NEG_INFINITY; num];
+        let mut close = vec![f64::INFINITY; num];
+        for i in 1..num {
+            if rng.below(2) == 0 {
+                open[i] = rng.unit() * 30.0;
+                close[i] = open[i] + 4.0 + rng.unit() * 30.0;
+            }
+        }
+        TwModel {
+            miles,
+            travel,
+            service: vec![1.0; num],
+            open,
+            close,
+            start: Utc.ymd(2024, 1, 15).and_hms(14, 0, 0),
+        }
+    }
+
+    fn on_time_miles(route: &[usize], model: &TwModel) -> f64 {
+        match simulate(route, model).1 > 0.0 {
+            true => f64::INFINITY,
+            false => route.windows(2).map(|w| model.miles[w[0]][w[1]]).sum(),
+        }
+    }
+
+    #[test]
+    fn parses_window_bounds() {
+        let at = |d: u32, h: u32, m: u32, s: u32| NaiveDate::from_ymd(2024, 1, d).and_hms(h, m, s);
+        assert_eq!(
+            parse_bound("2024-01-20T09:30", false),
+            Some(at(20, 9, 30, 0))
+        );
+        assert_eq!(parse_bound("2024-01-20", false), Some(at(20, 0, 0, 0)));
+        assert_eq!(parse_bound("2024-01-20", true), Some(at(20, 23, 59, 59)));
+        assert_eq!(parse_bound("Jan 20", true), None);
+    }
+
+    #[test]
+    fn simulate_waits_for_the_window_and_counts_lateness() {
+        let mut rng = XorShift::new(3);
+        let mut model = random_model(&mut rng, 3);
+        model.open = vec![f64::NEG_INFINITY, 100.0, f64::NEG_INFINITY];
+        model.close = vec![f64::INFINITY, 200.0, 0.5];
+        let (timings, late) = simulate(&[0, 1, 2], &model);
+        assert_eq!(timings[1].arrive, 1.0 + model.travel[0][1]);
+        assert_eq!(timings[1].begin, 100.0);
+        assert_eq!(timings[1].late, 0.0);
+        assert_eq!(timings[2].arrive, 101.0 + model.travel[1][2]);
+        assert_eq!(late, timings[2].begin - 0.5);
+    }
+
+    #[test]
+    fn exact_matches_brute_force() {
+        let mut rng = XorShift::new(31);
+        let none = Precedence::default();
+        for num in 3..=7 {
+            for _ in 0..5 {
+                let model = random_model(&mut rng, num);
+                let middle: Vec<usize> = (1..num - 1).collect();
+                let end = Some(num - 1);
+                let (_, best) = brute_force(0, &middle, end, &none, |r| on_time_miles(r, &model));
+                match exact(0, &middle, end, &model, &none) {
+                    Some(route) => {
+                        assert!((on_time_miles(&route, &model) - best).abs() < 1e-9);
+                    }
+                    None => assert!(best.is_infinite()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solve_keeps_to_precedence() {
+        let mut rng = XorShift::new(8);
+        for _ in 0..10 {
+            let mut model = random_model(&mut rng, 8);
+            model.close = vec![f64::INFINITY; 8];
+            let prec = Precedence {
+                pairs: vec![(5, 2), (2, 6)],
+            };
+            let middle: Vec<usize> = (1..7).collect();
+            let sol = solve(0, &middle, Some(7), &model, &prec);
+            assert!(sol.feasible && sol.exact);
+            assert!(allows(&sol.route, &prec));
+            let (_, best) = brute_force(0, &middle, Some(7), &prec, |r| on_time_miles(r, &model));
+            assert!((sol.miles - best).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn builds_windows_local_to_each_capital() {
+        let data = read_look_up("look_up.json");
+        let cons = read_constraints("constraints.json");
+        let ia = state_index(&data, "IA").unwrap();
+        let nh = state_index(&data, "NH").unwrap();
+        let start = from_local(
+            zone_of(&data, ia),
+            parse_bound("2024-01-15T08:00", false).unwrap(),
+        );
+        let airports = read_airports("airports.csv");
+        let model = build_model(&data, &cons, start, &airports, &ModalParams::default());
+        // midnight EST on January 20 is 111 hours after 8:00 CST on January 15
+        assert!((model.open[nh] - 111.0).abs() < 1e-9);
+        assert!((model.close[nh] - (207.0 - 1.0 / 3600.0)).abs() < 1e-9);
+        assert_eq!(model.service[nh], 6.0);
+        assert_eq!(model.service[ia], 2.0);
+        assert!(model.close[ia].is_infinite());
+        assert_eq!(
+            at(&model, model.open[nh]),
+            Utc.ymd(2024, 1, 20).and_hms(5, 0, 0)
+        );
+    }
+
+    #[test]
+    fn diagnose_names_a_window_that_cannot_be_reached() {
+        let data = read_look_up("look_up.json");
+        let at = |state: &str| state_index(&data, state).unwrap();
+        let mut model = build_model(
+            &data,
+            &Constraints::default(),
+            Utc.ymd(2024, 1, 15).and_hms(14, 0, 0),
+            &read_airports("airports.csv"),
+            &ModalParams::default(),
+        );
+        let (ia, ca, ny) = (at("IA"), at("CA"), at("NY"));
+        model.close[ca] = 5.0;
+        let sol = solve(ia, &[ca, ny], None, &model, &Precedence::default());
+        assert!(!sol.feasible);
+        assert_eq!(late_stops(&sol, &data).len(), 1);
+        let reasons = diagnose(ia, &[ca, ny], None, &model, &data);
+        assert_eq!(reasons.len(), 1);
+        assert!(reasons[0].starts_with("CA window closes at"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::lookup::{lat_lon, ObjLookUp};
+use log::{error, info, warn};
+use serde::Deserialize;
+
+// A row of a zip code table such as codes.csv (every
+// US zip code) or zip_codes.csv (the capitals), with
+// typed coordinates and population.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ZipRecord {
+    #[serde(rename = "ZipCode", alias = "zip_code")]
+    pub zip_code: String,
+    #[serde(rename = "City")]
+    pub city: String,
+    #[serde(rename = "State")]
+    pub state: String,
+    #[serde(rename = "Latitude")]
+    pub latitude: f64,
+    #[serde(rename = "Longitude")]
+    pub longitude: f64,
+    #[serde(rename = "Classification")]
+    pub classification: String,
+    #[serde(rename = "Population")]
+    pub population: u64,
+}
+
+impl ZipRecord {
+    // function converts the record into the lookup
+    // record the solvers and commands work with.
+    pub fn to_look_up(&self) -> ObjLookUp {
+        ObjLookUp {
+            zip_code: self.zip_code.clone(),
+            city: self.city.clone(),
+            state: self.state.clone(),
+            latitude: self.latitude.to_string(),
+            longitude: self.longitude.to_string(),
+            classification: self.classification.clone(),
+            population: self.population.to_string(),
+        }
+    }
+
+    // function returns false for the rows of codes.csv
+    // (mostly military and PO box zip codes) that have
+    // 0.0000, 0.0000 for coordinates.
+    pub fn located(&self) -> bool {
+        self.latitude != 0.0 || self.longitude != 0.0
+    }
+}
+
+impl From<&ZipRecord> for ObjLookUp {
+    fn from(rec: &ZipRecord) -> ObjLookUp {
+        rec.to_look_up()
+    }
+}
+
+impl From<&ObjLookUp> for ZipRecord {
+    fn from(rec: &ObjLookUp) -> ZipRecord {
+        let (latitude, longitude) = lat_lon(rec);
+        ZipRecord {
+            zip_code: rec.zip_code.clone(),
+            city: rec.city.clone(),
+            state: rec.state.clone(),
+            latitude,
+            longitude,
+            classification: rec.classification.clone(),
+            // look_up.json leaves some populations blank
+            population: rec.population.parse::<u64>().unwrap_or(0),
+        }
+    }
+}
+
+// function reads a zip code table. Rows that do not
+// parse are logged and skipped.
+pub fn read_zip_csv(path: &str) -> Vec<ZipRecord> {
+    let mut rdr = match csv::Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => {
+            let msg = "Error opening zip code table";
+            error!("{:?}: {:?}: {:?}", msg, path, e);
+            panic!("{:?}: {:?}: {:?}", msg, path, e)
+        }
+    };
+    let mut records: Vec<ZipRecord> = Vec::new();
+    let mut skipped = 0;
+    for result in rdr.deserialize() {
+        match result {
+            Ok(rec) => records.push(rec),
+            Err(e) => {
+                warn!("Skipped zip code row of {:?}: {:?}", path, e);
+                skipped += 1;
+            }
+        }
+    }
+    info!(
+        "Success read {:?} zip code(s) from {:?}, {:?} row(s) skipped",
+        records.len(),
+        path,
+        skipped
+    );
+    records
+}
+
+// function reads a zip code table as lookup records,
+// keeping only the listed zip codes (in list order)
+// unless the list is empty. Panics on a listed zip
+// code the table does not have.
+pub fn read_zip_look_up(path: &str, zips: &[&str]) -> Vec<ObjLookUp> {
+    let records = read_zip_csv(path);
+    if zips.is_empty() {
+        return records.iter().map(ObjLookUp::from).collect();
+    }
+    zips.iter()
+        .map(
+            |zip| match records.iter().find(|rec| rec.zip_code == *zip) {
+                Some(rec) => rec.to_look_up(),
+                None => {
+                    let msg = "Zip code not found in table";
+                    error!("{:?}: {:?}: {:?}", msg, path, zip);
+                    panic!("{:?}: {:?}: {:?}", msg, path, zip)
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_capitals_table() {
+        let records = read_zip_csv("zip_codes.csv");
+        assert_eq!(records.len(), 51);
+        let iowa = &records[0];
+        assert_eq!(
+            (iowa.zip_code.as_str(), iowa.state.as_str()),
+            ("50319", "IA")
+        );
+        assert_eq!((iowa.latitude, iowa.longitude), (41.6006, -93.6087));
+        assert!(records.iter().all(|r| r.located()));
+    }
+
+    #[test]
+    fn keeps_the_listed_zip_codes_in_order() {
+        let records = read_zip_look_up("codes.csv", &["44114", "00501", "43215"]);
+        let zips: Vec<&str> = records.iter().map(|r| r.zip_code.as_str()).collect();
+        assert_eq!(zips, ["44114", "00501", "43215"]);
+        assert_eq!(records[1].state, "NY");
+        assert_eq!(records[2].population, "12790");
+    }
+
+    #[test]
+    #[should_panic(expected = "Zip code not found in table")]
+    fn an_unknown_zip_code_is_refused() {
+        read_zip_look_up("zip_codes.csv", &["44114"]);
+    }
+
+    #[test]
+    fn converts_to_and_from_lookup_records() {
+        let records = read_zip_look_up("codes.csv", &["09002", "06103"]);
+        let apo = ZipRecord::from(&records[0]);
+        assert!(!apo.located());
+        let hartford = ZipRecord::from(&records[1]);
+        assert_eq!(ObjLookUp::from(&hartford).latitude, "41.7652");
+        assert_eq!(hartford.population, 1410);
+    }
+}